@@ -1,7 +1,7 @@
 #![no_main]
 
-use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
-use risc0_aggregation_program::{ChunkAggregatorInput, Hash32};
+use aggregation_merkle_tree::MerkleTree;
+use risc0_aggregation_program::ChunkAggregatorInput;
 use risc0_zkvm::guest::env;
 
 risc0_zkvm::guest::entry!(main);
@@ -15,7 +15,7 @@ pub const USER_PROOFS_AGGREGATOR_PROGRAM_IMAGE_ID: [u8; 32] = [
 fn main() {
     let input = env::read::<ChunkAggregatorInput>();
 
-    let mut leaves: Vec<Hash32> = vec![];
+    let mut leaves: Vec<[u8; 32]> = vec![];
 
     for (proof, leaves_commitment) in input.proofs_and_leaves_commitment {
         let image_id = proof.image_id;
@@ -33,10 +33,8 @@ fn main() {
             .try_into()
             .expect("Public input to be the chunk merkle root");
 
-        let leaves_commitment: Vec<Hash32> =
-            leaves_commitment.into_iter().map(|el| Hash32(el)).collect();
-        let merkle_tree = MerkleTree::<Hash32>::build(&leaves_commitment).unwrap();
-        assert!(merkle_root == merkle_tree.root);
+        let merkle_tree = MerkleTree::build(&leaves_commitment).unwrap();
+        assert!(merkle_root == merkle_tree.root());
 
         leaves.extend(leaves_commitment);
 
@@ -44,7 +42,7 @@ fn main() {
         env::verify(image_id, &proof.public_inputs).expect("proof to be verified correctly");
     }
 
-    let merkle_tree = MerkleTree::<Hash32>::build(&leaves).unwrap();
+    let merkle_tree = MerkleTree::build(&leaves).unwrap();
 
-    env::commit_slice(&merkle_tree.root);
+    env::commit_slice(&merkle_tree.root());
 }
@@ -1,7 +1,7 @@
 #![no_main]
 
-use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
-use risc0_aggregation_program::{Risc0ImageIdAndPubInputs, UserProofsAggregatorInput};
+use aggregation_merkle_tree::MerkleTree;
+use risc0_aggregation_program::UserProofsAggregatorInput;
 use risc0_zkvm::guest::env;
 
 risc0_zkvm::guest::entry!(main);
@@ -14,9 +14,12 @@ fn main() {
             .expect("proof to be verified correctly");
     }
 
-    let merkle_tree =
-        MerkleTree::<Risc0ImageIdAndPubInputs>::build(&input.proofs_image_id_and_pub_inputs)
-            .unwrap();
+    let leaves: Vec<[u8; 32]> = input
+        .proofs_image_id_and_pub_inputs
+        .iter()
+        .map(|proof| proof.commitment())
+        .collect();
+    let merkle_tree = MerkleTree::build(&leaves).unwrap();
 
-    env::commit_slice(&merkle_tree.root);
+    env::commit_slice(&merkle_tree.root());
 }
@@ -1,9 +1,9 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
+use aggregation_merkle_tree::MerkleTree;
 use sha2::{Digest, Sha256};
-use sp1_aggregation_program::{ChunkAggregatorInput, Hash32};
+use sp1_aggregation_program::ChunkAggregatorInput;
 
 // Generated with `make proof_aggregator_write_program_ids` and copied from program_ids.json
 pub const USER_PROOFS_AGGREGATOR_PROGRAM_VK_HASH: [u32; 8] = [
@@ -33,10 +33,8 @@ pub fn main() {
             .expect("Public input to be the hash of the chunk tree");
 
         // Reconstruct the merkle tree and verify that the roots match
-        let leaves_commitment: Vec<Hash32> =
-            leaves_commitment.into_iter().map(|el| Hash32(el)).collect();
-        let merkle_tree: MerkleTree<Hash32> = MerkleTree::build(&leaves_commitment).unwrap();
-        assert!(merkle_tree.root == merkle_root);
+        let merkle_tree = MerkleTree::build(&leaves_commitment).unwrap();
+        assert!(merkle_tree.root() == merkle_root);
 
         leaves.extend(leaves_commitment);
 
@@ -44,7 +42,7 @@ pub fn main() {
     }
 
     // Finally, compute the final merkle root with all the leaves
-    let merkle_tree: MerkleTree<Hash32> = MerkleTree::build(&leaves).unwrap();
+    let merkle_tree = MerkleTree::build(&leaves).unwrap();
 
-    sp1_zkvm::io::commit_slice(&merkle_tree.root);
+    sp1_zkvm::io::commit_slice(&merkle_tree.root());
 }
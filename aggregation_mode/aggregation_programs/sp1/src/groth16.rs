@@ -0,0 +1,462 @@
+//! Groth16 proofs over BN254 generated with gnark or snarkjs (Circom).
+//!
+//! The proof and verification key are kept in the format they were submitted to the batcher,
+//! so the program id of the leaf is the keccak of the verification key file the user holds.
+//! Public inputs are normalized to 32 bytes big endian field elements, which is how
+//! contracts receive them.
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInt, BigInteger, One, PrimeField, Zero};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+const FIELD_ELEMENT_SIZE: usize = 32;
+
+/// Serialization of the proof and the verification key.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Groth16Format {
+    /// Written with gnark `WriteTo`, compressed or raw.
+    Gnark,
+    /// `proof.json` and `verification_key.json` as generated by snarkjs.
+    Circom,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Groth16Error {
+    MalformedProof(String),
+    MalformedVerificationKey(String),
+    MalformedPublicInputs(String),
+    /// Gnark proofs with Pedersen commitments can't be verified with the plain Groth16 equation.
+    UnsupportedCommitments,
+    Verification,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Groth16Bn254Proof {
+    pub format: Groth16Format,
+    pub proof: Vec<u8>,
+    pub vk: Vec<u8>,
+    /// Concatenation of the public inputs as 32 bytes big endian field elements.
+    pub public_inputs: Vec<u8>,
+}
+
+impl Groth16Bn254Proof {
+    /// Builds the proof from gnark files. The public witness is the one written with gnark
+    /// `WriteTo`, which is what the batcher receives as public input.
+    pub fn from_gnark(
+        proof: Vec<u8>,
+        vk: Vec<u8>,
+        public_witness: &[u8],
+    ) -> Result<Self, Groth16Error> {
+        Ok(Self {
+            format: Groth16Format::Gnark,
+            proof,
+            vk,
+            public_inputs: gnark_public_inputs(public_witness)?,
+        })
+    }
+
+    /// Builds the proof from snarkjs files. The public signals are the `public.json` file.
+    pub fn from_circom(
+        proof: Vec<u8>,
+        vk: Vec<u8>,
+        public_signals: &[u8],
+    ) -> Result<Self, Groth16Error> {
+        Ok(Self {
+            format: Groth16Format::Circom,
+            proof,
+            vk,
+            public_inputs: circom_public_inputs(public_signals)?,
+        })
+    }
+
+    pub fn vk_hash(&self) -> [u8; 32] {
+        Keccak256::digest(&self.vk).into()
+    }
+
+    /// Leaf commitment: `keccak(keccak(vk) || public_inputs)`.
+    pub fn commitment(&self) -> [u8; 32] {
+        aggregation_merkle_tree::commitment(&self.vk_hash(), &self.public_inputs)
+    }
+
+    pub fn verify(&self) -> Result<(), Groth16Error> {
+        let (proof, vk) = match self.format {
+            Groth16Format::Gnark => (gnark::parse_proof(&self.proof)?, gnark::parse_vk(&self.vk)?),
+            Groth16Format::Circom => (
+                circom::parse_proof(&self.proof)?,
+                circom::parse_vk(&self.vk)?,
+            ),
+        };
+
+        let public_inputs = self
+            .public_inputs
+            .chunks(FIELD_ELEMENT_SIZE)
+            .map(parse_field_be::<Fr>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Groth16Error::MalformedPublicInputs)?;
+        if public_inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return Err(Groth16Error::MalformedPublicInputs(format!(
+                "expected {} public inputs, got {}",
+                vk.gamma_abc_g1.len() - 1,
+                public_inputs.len()
+            )));
+        }
+
+        let prepared_vk = PreparedVerifyingKey::from(vk);
+        match Groth16::<Bn254>::verify_proof(&prepared_vk, &proof, &public_inputs) {
+            Ok(true) => Ok(()),
+            _ => Err(Groth16Error::Verification),
+        }
+    }
+}
+
+/// Public inputs of a gnark public witness: `[nb_public u32][nb_secret u32][len u32][elements]`.
+fn gnark_public_inputs(public_witness: &[u8]) -> Result<Vec<u8>, Groth16Error> {
+    let mut reader = gnark::Reader::new(public_witness);
+    let (nb_public, nb_secret, len) = reader
+        .read_witness_header()
+        .map_err(Groth16Error::MalformedPublicInputs)?;
+    if nb_secret != 0 || len != nb_public {
+        return Err(Groth16Error::MalformedPublicInputs(
+            "witness is not a public witness".to_string(),
+        ));
+    }
+    let public_inputs = reader.remaining();
+    if public_inputs.len() != len as usize * FIELD_ELEMENT_SIZE {
+        return Err(Groth16Error::MalformedPublicInputs(format!(
+            "expected {} field elements",
+            len
+        )));
+    }
+    Ok(public_inputs.to_vec())
+}
+
+/// Public inputs of a snarkjs `public.json`: a list of field elements in decimal.
+fn circom_public_inputs(public_signals: &[u8]) -> Result<Vec<u8>, Groth16Error> {
+    let public_signals: Vec<String> = serde_json::from_slice(public_signals)
+        .map_err(|e| Groth16Error::MalformedPublicInputs(e.to_string()))?;
+    let mut public_inputs = Vec::with_capacity(public_signals.len() * FIELD_ELEMENT_SIZE);
+    for signal in public_signals {
+        let signal: Fr =
+            parse_field_decimal(&signal).map_err(Groth16Error::MalformedPublicInputs)?;
+        public_inputs.extend(signal.into_bigint().to_bytes_be());
+    }
+    Ok(public_inputs)
+}
+
+/// Parses a field element in big endian, rejecting values that are not reduced.
+fn parse_field_be<F: PrimeField>(bytes: &[u8]) -> Result<F, String> {
+    if bytes.len() != FIELD_ELEMENT_SIZE {
+        return Err("field element is not 32 bytes long".to_string());
+    }
+    let value = F::from_be_bytes_mod_order(bytes);
+    if value.into_bigint().to_bytes_be() != bytes {
+        return Err("field element is not reduced".to_string());
+    }
+    Ok(value)
+}
+
+/// Parses a field element in decimal, rejecting values that are not reduced.
+fn parse_field_decimal<F: PrimeField<BigInt = BigInt<4>>>(value: &str) -> Result<F, String> {
+    let bigint: BigInt<4> = value
+        .parse()
+        .map_err(|_| format!("invalid field element {}", value))?;
+    F::from_bigint(bigint).ok_or_else(|| format!("field element {} is not reduced", value))
+}
+
+mod gnark {
+    use super::*;
+
+    const FLAGS_MASK: u8 = 0b11 << 6;
+    const UNCOMPRESSED: u8 = 0b00 << 6;
+    const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+    const COMPRESSED_INFINITY: u8 = 0b01 << 6;
+
+    pub(super) struct Reader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl<'a> Reader<'a> {
+        pub(super) fn new(bytes: &'a [u8]) -> Self {
+            Self { bytes }
+        }
+
+        fn read(&mut self, len: usize) -> Result<&'a [u8], String> {
+            if self.bytes.len() < len {
+                return Err("unexpected end of input".to_string());
+            }
+            let (read, rest) = self.bytes.split_at(len);
+            self.bytes = rest;
+            Ok(read)
+        }
+
+        fn read_u32(&mut self) -> Result<u32, String> {
+            let bytes = self.read(4)?;
+            Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+        }
+
+        /// Reads `(nb_public, nb_secret, len)` of a witness.
+        pub(super) fn read_witness_header(&mut self) -> Result<(u32, u32, u32), String> {
+            Ok((self.read_u32()?, self.read_u32()?, self.read_u32()?))
+        }
+
+        pub(super) fn remaining(&self) -> &'a [u8] {
+            self.bytes
+        }
+
+        fn peek_flags(&self) -> Result<u8, String> {
+            self.bytes
+                .first()
+                .map(|byte| byte & FLAGS_MASK)
+                .ok_or_else(|| "unexpected end of input".to_string())
+        }
+
+        fn read_fq(&mut self) -> Result<Fq, String> {
+            parse_field_be(self.read(FIELD_ELEMENT_SIZE)?)
+        }
+
+        /// Reads `x` of a compressed point, clearing the flags of its first byte.
+        fn read_compressed_fq(&mut self) -> Result<Fq, String> {
+            let mut bytes = [0u8; FIELD_ELEMENT_SIZE];
+            bytes.copy_from_slice(self.read(FIELD_ELEMENT_SIZE)?);
+            bytes[0] &= !FLAGS_MASK;
+            parse_field_be(&bytes)
+        }
+
+        /// gnark writes `E2` elements as `A1 || A0`.
+        fn read_fq2(&mut self) -> Result<Fq2, String> {
+            let c1 = self.read_fq()?;
+            let c0 = self.read_fq()?;
+            Ok(Fq2::new(c0, c1))
+        }
+
+        fn read_compressed_fq2(&mut self) -> Result<Fq2, String> {
+            let c1 = self.read_compressed_fq()?;
+            let c0 = self.read_fq()?;
+            Ok(Fq2::new(c0, c1))
+        }
+
+        fn read_g1(&mut self) -> Result<G1Affine, String> {
+            let point = match self.peek_flags()? {
+                UNCOMPRESSED => {
+                    let (x, y) = (self.read_fq()?, self.read_fq()?);
+                    // The raw encoding of the point at infinity is all zeros
+                    if x.is_zero() && y.is_zero() {
+                        return Ok(G1Affine::zero());
+                    }
+                    let point = G1Affine::new_unchecked(x, y);
+                    if !point.is_on_curve() {
+                        return Err("G1 point is not on the curve".to_string());
+                    }
+                    point
+                }
+                COMPRESSED_INFINITY => {
+                    self.read(FIELD_ELEMENT_SIZE)?;
+                    return Ok(G1Affine::zero());
+                }
+                flags => {
+                    let x = self.read_compressed_fq()?;
+                    G1Affine::get_point_from_x_unchecked(x, flags == COMPRESSED_LARGEST)
+                        .ok_or_else(|| "G1 point is not on the curve".to_string())?
+                }
+            };
+            // BN254 G1 has cofactor 1, so every point on the curve is in the subgroup
+            Ok(point)
+        }
+
+        fn read_g2(&mut self) -> Result<G2Affine, String> {
+            let point = match self.peek_flags()? {
+                UNCOMPRESSED => {
+                    let (x, y) = (self.read_fq2()?, self.read_fq2()?);
+                    if x.is_zero() && y.is_zero() {
+                        return Ok(G2Affine::zero());
+                    }
+                    let point = G2Affine::new_unchecked(x, y);
+                    if !point.is_on_curve() {
+                        return Err("G2 point is not on the curve".to_string());
+                    }
+                    point
+                }
+                COMPRESSED_INFINITY => {
+                    self.read(2 * FIELD_ELEMENT_SIZE)?;
+                    return Ok(G2Affine::zero());
+                }
+                flags => {
+                    let x = self.read_compressed_fq2()?;
+                    G2Affine::get_point_from_x_unchecked(x, flags == COMPRESSED_LARGEST)
+                        .ok_or_else(|| "G2 point is not on the curve".to_string())?
+                }
+            };
+            if !point.is_in_correct_subgroup_assuming_on_curve() {
+                return Err("G2 point is not in the subgroup".to_string());
+            }
+            Ok(point)
+        }
+
+        fn read_g1_slice(&mut self) -> Result<Vec<G1Affine>, String> {
+            let len = self.read_u32()?;
+            (0..len).map(|_| self.read_g1()).collect()
+        }
+    }
+
+    /// `[Ar]1, [Bs]2, [Krs]1, commitments, commitment proof of knowledge`.
+    pub(super) fn parse_proof(proof: &[u8]) -> Result<Proof<Bn254>, Groth16Error> {
+        let (proof, nb_commitments) =
+            read_proof(&mut Reader::new(proof)).map_err(Groth16Error::MalformedProof)?;
+        if nb_commitments != 0 {
+            return Err(Groth16Error::UnsupportedCommitments);
+        }
+        Ok(proof)
+    }
+
+    fn read_proof(reader: &mut Reader) -> Result<(Proof<Bn254>, u32), String> {
+        let a = reader.read_g1()?;
+        let b = reader.read_g2()?;
+        let c = reader.read_g1()?;
+        let nb_commitments = reader.read_u32()?;
+        Ok((Proof { a, b, c }, nb_commitments))
+    }
+
+    /// `[α]1, [β]1, [β]2, [γ]2, [δ]1, [δ]2, [K]1, ...`. Keys with commitments have more
+    /// points in `K` than public inputs plus one, so they are rejected when verifying.
+    pub(super) fn parse_vk(vk: &[u8]) -> Result<VerifyingKey<Bn254>, Groth16Error> {
+        read_vk(&mut Reader::new(vk)).map_err(Groth16Error::MalformedVerificationKey)
+    }
+
+    fn read_vk(reader: &mut Reader) -> Result<VerifyingKey<Bn254>, String> {
+        let alpha_g1 = reader.read_g1()?;
+        let _beta_g1 = reader.read_g1()?;
+        let beta_g2 = reader.read_g2()?;
+        let gamma_g2 = reader.read_g2()?;
+        let _delta_g1 = reader.read_g1()?;
+        let delta_g2 = reader.read_g2()?;
+        let gamma_abc_g1 = reader.read_g1_slice()?;
+        Ok(VerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+        })
+    }
+}
+
+mod circom {
+    use super::*;
+
+    const PROTOCOL: &str = "groth16";
+    const CURVE: &str = "bn128";
+
+    #[derive(Deserialize)]
+    struct SnarkjsProof {
+        pi_a: Vec<String>,
+        pi_b: Vec<Vec<String>>,
+        pi_c: Vec<String>,
+        protocol: String,
+        curve: String,
+    }
+
+    #[derive(Deserialize)]
+    struct SnarkjsVerificationKey {
+        protocol: String,
+        curve: String,
+        vk_alpha_1: Vec<String>,
+        vk_beta_2: Vec<Vec<String>>,
+        vk_gamma_2: Vec<Vec<String>>,
+        vk_delta_2: Vec<Vec<String>>,
+        #[serde(rename = "IC")]
+        ic: Vec<Vec<String>>,
+    }
+
+    pub(super) fn parse_proof(proof: &[u8]) -> Result<Proof<Bn254>, Groth16Error> {
+        read_proof(proof).map_err(Groth16Error::MalformedProof)
+    }
+
+    fn read_proof(proof: &[u8]) -> Result<Proof<Bn254>, String> {
+        let proof: SnarkjsProof = serde_json::from_slice(proof).map_err(|e| e.to_string())?;
+        check_protocol_and_curve(&proof.protocol, &proof.curve)?;
+        Ok(Proof {
+            a: parse_g1(&proof.pi_a)?,
+            b: parse_g2(&proof.pi_b)?,
+            c: parse_g1(&proof.pi_c)?,
+        })
+    }
+
+    pub(super) fn parse_vk(vk: &[u8]) -> Result<VerifyingKey<Bn254>, Groth16Error> {
+        read_vk(vk).map_err(Groth16Error::MalformedVerificationKey)
+    }
+
+    fn read_vk(vk: &[u8]) -> Result<VerifyingKey<Bn254>, String> {
+        let vk: SnarkjsVerificationKey = serde_json::from_slice(vk).map_err(|e| e.to_string())?;
+        check_protocol_and_curve(&vk.protocol, &vk.curve)?;
+        Ok(VerifyingKey {
+            alpha_g1: parse_g1(&vk.vk_alpha_1)?,
+            beta_g2: parse_g2(&vk.vk_beta_2)?,
+            gamma_g2: parse_g2(&vk.vk_gamma_2)?,
+            delta_g2: parse_g2(&vk.vk_delta_2)?,
+            gamma_abc_g1: vk
+                .ic
+                .iter()
+                .map(|point| parse_g1(point))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn check_protocol_and_curve(protocol: &str, curve: &str) -> Result<(), String> {
+        if protocol != PROTOCOL {
+            return Err("protocol is not groth16".to_string());
+        }
+        if curve != CURVE {
+            return Err("curve is not bn128".to_string());
+        }
+        Ok(())
+    }
+
+    /// Parses a G1 point in projective coordinates `[x, y, z]`, as output by snarkjs.
+    /// snarkjs normalizes points, so `z` is `1`, or `0` for the point at infinity.
+    fn parse_g1(point: &[String]) -> Result<G1Affine, String> {
+        let [x, y, z] = point else {
+            return Err("G1 point must have 3 coordinates".to_string());
+        };
+        let z: Fq = parse_field_decimal(z)?;
+        if z.is_zero() {
+            return Ok(G1Affine::zero());
+        }
+        if !z.is_one() {
+            return Err("G1 point is not normalized".to_string());
+        }
+        let point = G1Affine::new_unchecked(parse_field_decimal(x)?, parse_field_decimal(y)?);
+        if !point.is_on_curve() {
+            return Err("G1 point is not on the curve".to_string());
+        }
+        Ok(point)
+    }
+
+    /// Parses a G2 point in projective coordinates `[[x.c0, x.c1], [y.c0, y.c1], [z.c0, z.c1]]`.
+    fn parse_g2(point: &[Vec<String>]) -> Result<G2Affine, String> {
+        let [x, y, z] = point else {
+            return Err("G2 point must have 3 coordinates".to_string());
+        };
+        let z = parse_fq2(z)?;
+        if z.is_zero() {
+            return Ok(G2Affine::zero());
+        }
+        if !z.is_one() {
+            return Err("G2 point is not normalized".to_string());
+        }
+        let point = G2Affine::new_unchecked(parse_fq2(x)?, parse_fq2(y)?);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return Err("G2 point is not in the subgroup".to_string());
+        }
+        Ok(point)
+    }
+
+    fn parse_fq2(coordinate: &[String]) -> Result<Fq2, String> {
+        let [c0, c1] = coordinate else {
+            return Err("G2 coordinate must have 2 elements".to_string());
+        };
+        Ok(Fq2::new(parse_field_decimal(c0)?, parse_field_decimal(c1)?))
+    }
+}
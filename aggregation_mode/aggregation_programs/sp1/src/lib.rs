@@ -1,6 +1,7 @@
-use lambdaworks_crypto::merkle_tree::traits::IsMerkleTreeBackend;
+pub mod groth16;
+
+use groth16::Groth16Bn254Proof;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
 
 #[derive(Serialize, Deserialize)]
 pub struct SP1VkAndPubInputs {
@@ -9,106 +10,41 @@ pub struct SP1VkAndPubInputs {
 }
 
 impl SP1VkAndPubInputs {
-    pub fn commitment(&self) -> [u8; 32] {
-        let mut hasher = Keccak256::new();
-        for &word in &self.vk {
-            hasher.update(word.to_be_bytes());
+    /// The verification key hash as bytes, as `HashableKey::hash_bytes` in the SP1 SDK.
+    pub fn program_id(&self) -> [u8; 32] {
+        let mut program_id = [0u8; 32];
+        for (bytes, word) in program_id.chunks_exact_mut(4).zip(self.vk) {
+            bytes.copy_from_slice(&word.to_be_bytes());
         }
-        hasher.update(&self.public_inputs);
-        hasher.finalize().into()
+        program_id
     }
-}
-
-// Note: this MerkleTreeBackend is defined in three locations
-// - aggregation_mode/src/aggregators/mod.rs
-// - aggregation_mode/src/aggregators/risc0_aggregator.rs and
-// - aggregation_mode/src/aggregators/sp1_aggregator.rs
-// All 3 implementations should match
-// The definition on aggregator/mod.rs supports taking proofs from both Risc0 and SP1,
-// Additionally, a version that takes the leaves as already hashed data is defined on:
-// - crates/sdk/src/aggregation_layer/types.rs
-// This one is used in the SDK since
-// the user may not have access to the proofs that they didn't submit
-impl IsMerkleTreeBackend for SP1VkAndPubInputs {
-    type Data = SP1VkAndPubInputs;
-    type Node = [u8; 32];
 
-    fn hash_data(leaf: &Self::Data) -> Self::Node {
-        leaf.commitment()
-    }
-
-    /// Computes a commutative Keccak256 hash, ensuring H(a, b) == H(b, a).
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#Hashes
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/Hashes.sol#L17-L19
-    ///
-    /// Compliant with OpenZeppelin's `processProofCalldata` function from MerkleProof.sol.
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#MerkleProof
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/MerkleProof.sol#L114-L128
-    fn hash_new_parent(child_1: &Self::Node, child_2: &Self::Node) -> Self::Node {
-        let mut hasher = Keccak256::new();
-        if child_1 < child_2 {
-            hasher.update(child_1);
-            hasher.update(child_2);
-        } else {
-            hasher.update(child_2);
-            hasher.update(child_1);
-        }
-        hasher.finalize().into()
+    pub fn commitment(&self) -> [u8; 32] {
+        aggregation_merkle_tree::commitment(&self.program_id(), &self.public_inputs)
     }
 }
 
+/// A proof aggregated by the user proofs aggregator.
 #[derive(Serialize, Deserialize)]
-pub struct Hash32(pub [u8; 32]);
-
-// Note: this MerkleTreeBackend is defined in three locations
-// - aggregation_mode/src/aggregators/mod.rs
-// - aggregation_mode/src/aggregators/risc0_aggregator.rs and
-// - aggregation_mode/src/aggregators/sp1_aggregator.rs
-// All 3 implementations should match
-// The definition on aggregator/mod.rs supports taking proofs from both Risc0 and SP1,
-// Additionally, a version that takes the leaves as already hashed data is defined on:
-// - batcher/aligned-sdk/src/sdk/aggregation.rs
-// This one is used in the SDK since
-// the user may not have access to the proofs that they didn't submit
-impl IsMerkleTreeBackend for Hash32 {
-    type Data = Hash32;
-    type Node = [u8; 32];
-
-    fn hash_data(leaf: &Self::Data) -> Self::Node {
-        leaf.0
-    }
+pub enum UserProof {
+    /// A compressed SP1 proof, written to the program stdin in the same order.
+    SP1(SP1VkAndPubInputs),
+    Groth16Bn254(Groth16Bn254Proof),
+}
 
-    /// Computes a commutative Keccak256 hash, ensuring H(a, b) == H(b, a).
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#Hashes
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/Hashes.sol#L17-L19
-    ///
-    /// Compliant with OpenZeppelin's `processProofCalldata` function from MerkleProof.sol.
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#MerkleProof
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/MerkleProof.sol#L114-L128
-    fn hash_new_parent(child_1: &Self::Node, child_2: &Self::Node) -> Self::Node {
-        let mut hasher = Keccak256::new();
-        if child_1 < child_2 {
-            hasher.update(child_1);
-            hasher.update(child_2);
-        } else {
-            hasher.update(child_2);
-            hasher.update(child_1);
+impl UserProof {
+    pub fn commitment(&self) -> [u8; 32] {
+        match self {
+            UserProof::SP1(proof) => proof.commitment(),
+            UserProof::Groth16Bn254(proof) => proof.commitment(),
         }
-        hasher.finalize().into()
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct UserProofsAggregatorInput {
-    pub proofs_vk_and_pub_inputs: Vec<SP1VkAndPubInputs>,
+    /// Proofs in the order of the leaves of the merkle tree.
+    pub proofs: Vec<UserProof>,
 }
 
 #[derive(Serialize, Deserialize)]
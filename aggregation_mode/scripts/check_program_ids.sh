@@ -0,0 +1,40 @@
+#!/bin/bash
+# Checks that the committed program ids match the aggregation programs built from this tree.
+# If this fails, run `make proof_aggregator_write_program_ids` and commit the changes.
+set -e
+
+# Get the program ids
+cargo run --release --bin write_program_image_id_vk_hash
+if ! git diff --exit-code programs_ids.json; then
+    echo "programs_ids.json is outdated, run make proof_aggregator_write_program_ids and commit the changes"
+    exit 1
+fi
+
+# Prints the value of a constant array, without whitespace
+const_value() {
+    sed -n "/^pub const $1/,/];/p" "$2" | tr -d ' \n' | sed -e 's/^.*=//' -e 's/;$//' -e 's/,]$/]/'
+}
+
+check() {
+    if [ "$1" != "$2" ]; then
+        echo "$3 is outdated, expected $2 but found $1"
+        exit 1
+    fi
+}
+
+check "$(const_value USER_PROOFS_AGGREGATOR_PROGRAM_VK_HASH aggregation_programs/sp1/src/chunk_aggregator_main.rs)" \
+    "$(jq -r .sp1_user_proofs_aggregator_vk_hash_words programs_ids.json | tr -d ' ')" \
+    "USER_PROOFS_AGGREGATOR_PROGRAM_VK_HASH in the SP1 chunk aggregator"
+check "$(const_value USER_PROOFS_AGGREGATOR_PROGRAM_IMAGE_ID aggregation_programs/risc0/src/chunk_aggregator_main.rs)" \
+    "$(jq -r .risc0_user_proofs_aggregator_image_id_bytes programs_ids.json | tr -d ' ')" \
+    "USER_PROOFS_AGGREGATOR_PROGRAM_IMAGE_ID in the Risc0 chunk aggregator"
+
+# The devnet contracts are deployed with the ids of the programs in this tree
+for config in ../contracts/script/deploy/config/devnet/proof-aggregator-service.devnet*.config.json; do
+    check "$(jq -r .programs_id.sp1AggregationProgramVKHash "$config")" \
+        "$(jq -r .sp1_chunk_aggregator_vk_hash programs_ids.json)" \
+        "sp1AggregationProgramVKHash in $config"
+    check "$(jq -r .programs_id.risc0AggregationProgramImageId "$config")" \
+        "$(jq -r .risc0_chunk_aggregator_image_id programs_ids.json)" \
+        "risc0AggregationProgramImageId in $config"
+done
@@ -0,0 +1,63 @@
+#!/bin/bash
+# Runs the whole aggregation mode flow in mock proving mode against anvil: proofs are sent to a local
+# batcher, aggregated by one proof aggregator run and their aggregated proofs checked on chain.
+# Run from the root of the repository, e.g. with `make proof_aggregator_e2e_mock`.
+set -e
+
+RPC_URL=http://localhost:8545
+PROOF_AGGREGATION_SERVICE_ADDRESS=$(sed -n 's/^proof_aggregation_service_address: "\(.*\)"/\1/p' config-files/config-proof-aggregator-mock.yaml)
+LOGS_DIR=$(mktemp -d)
+
+cleanup() {
+    pkill -f target/release/aligned-batcher || true
+    pkill anvil || true
+    docker compose -f storage-docker-compose.yaml down || true
+}
+trap cleanup EXIT
+
+# Waits up to $2 seconds for $1 to succeed
+wait_for() {
+    for _ in $(seq "$2"); do
+        if eval "$1" >/dev/null 2>&1; then
+            return 0
+        fi
+        sleep 1
+    done
+    echo "Timed out waiting for: $1"
+    exit 1
+}
+
+echo "Starting anvil..."
+make anvil_start >"$LOGS_DIR/anvil.log" 2>&1 &
+wait_for "cast block-number --rpc-url $RPC_URL" 60
+
+echo "Starting batcher..."
+make batcher_start_local >"$LOGS_DIR/batcher.log" 2>&1 &
+wait_for "nc -z localhost 8080" 1800
+
+echo "Sending proofs..."
+make batcher_send_sp1_burst RPC_URL=$RPC_URL NETWORK=devnet BURST_SIZE=2
+make batcher_send_risc0_burst RPC_URL=$RPC_URL NETWORK=devnet BURST_SIZE=2
+
+echo "Running proof aggregator..."
+make reset_last_aggregated_block
+AGGREGATOR=sp1,risc0 cargo run --manifest-path ./aggregation_mode/Cargo.toml --release --bin proof_aggregator_dev \
+    config-files/config-proof-aggregator-mock.yaml
+
+# Each engine verifies its own aggregated proof
+verified=$(cast logs --rpc-url $RPC_URL --from-block 0 --address "$PROOF_AGGREGATION_SERVICE_ADDRESS" \
+    "AggregatedProofVerified(bytes32 indexed,bytes32)" --json | jq length)
+if [ "$verified" -lt 2 ]; then
+    echo "Expected an aggregated proof verified per engine, found $verified"
+    echo "Anvil and batcher logs are in $LOGS_DIR"
+    exit 1
+fi
+
+report=config-files/proof-aggregator.last_aggregated_block.json.reports/latest.json
+if [ "$(jq '[.cycles[] | select(.status == "submitted")] | length' "$report")" -ne 2 ]; then
+    echo "Expected both engines to submit, run report:"
+    cat "$report"
+    exit 1
+fi
+
+echo "$verified aggregated proofs verified on chain"
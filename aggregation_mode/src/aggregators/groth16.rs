@@ -0,0 +1,53 @@
+use aligned_sdk::common::types::{ProvingSystemId, VerificationData};
+pub use sp1_aggregation_program::groth16::{Groth16Bn254Proof, Groth16Error};
+
+#[derive(Debug)]
+pub enum AlignedGroth16VerificationError {
+    /// The proof, verification key or public input is missing.
+    MissingData,
+    UnsupportedProvingSystem(ProvingSystemId),
+    Verification(Groth16Error),
+}
+
+/// Proving systems whose proofs are aggregated by the SP1 user proofs aggregator
+/// with its Groth16 BN254 verifier.
+pub fn is_groth16_bn254(proving_system: ProvingSystemId) -> bool {
+    matches!(
+        proving_system,
+        ProvingSystemId::GnarkGroth16Bn254 | ProvingSystemId::CircomGroth16Bn256
+    )
+}
+
+/// Constructs a Groth16 proof from the verification data submitted to the batcher,
+/// verifying it with the same verifier the aggregation program runs.
+pub fn groth16_proof_from_verification_data(
+    verification_data: VerificationData,
+) -> Result<Groth16Bn254Proof, AlignedGroth16VerificationError> {
+    let (Some(vk), Some(pub_input)) = (
+        verification_data.verification_key,
+        verification_data.pub_input,
+    ) else {
+        return Err(AlignedGroth16VerificationError::MissingData);
+    };
+
+    let proof = match verification_data.proving_system {
+        ProvingSystemId::GnarkGroth16Bn254 => {
+            Groth16Bn254Proof::from_gnark(verification_data.proof, vk, &pub_input)
+        }
+        ProvingSystemId::CircomGroth16Bn256 => {
+            Groth16Bn254Proof::from_circom(verification_data.proof, vk, &pub_input)
+        }
+        proving_system => {
+            return Err(AlignedGroth16VerificationError::UnsupportedProvingSystem(
+                proving_system,
+            ))
+        }
+    }
+    .map_err(AlignedGroth16VerificationError::Verification)?;
+
+    proof
+        .verify()
+        .map_err(AlignedGroth16VerificationError::Verification)?;
+
+    Ok(proof)
+}
@@ -1,15 +1,22 @@
+pub mod groth16;
 pub mod risc0_aggregator;
 pub mod sp1_aggregator;
 
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
 
-use lambdaworks_crypto::merkle_tree::traits::IsMerkleTreeBackend;
+use crate::backend::checkpoint::AggregationCheckpoint;
+use groth16::Groth16Bn254Proof;
 use risc0_aggregator::{Risc0AggregationError, Risc0ProofReceiptAndImageId};
-use sha3::{Digest, Keccak256};
+use serde::{Deserialize, Serialize};
 use sp1_aggregator::{SP1AggregationError, SP1ProofWithPubValuesAndElf};
-use tracing::info;
+use sp1_sdk::HashableKey;
+use tracing::{info, warn};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ZKVMEngine {
     SP1,
     RISC0,
@@ -24,6 +31,37 @@ impl Display for ZKVMEngine {
     }
 }
 
+/// Whether the aggregation programs are proven or only executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvingMode {
+    Real,
+    /// SP1 mock prover and Risc0 dev mode. The aggregated proofs are only accepted by an
+    /// `AlignedProofAggregationService` whose verifier is `VERIFIER_MOCK_ADDRESS`.
+    Mock,
+}
+
+impl Default for ProvingMode {
+    /// Binaries built without the `prove` feature mock proving unless told otherwise.
+    fn default() -> Self {
+        if cfg!(feature = "prove") {
+            Self::Real
+        } else {
+            Self::Mock
+        }
+    }
+}
+
+/// How the work of an aggregation went, for metrics and run reports.
+#[derive(Debug, Default, Clone)]
+pub struct AggregationStats {
+    pub chunks_proven: usize,
+    /// Chunks taken from a checkpoint instead of being proven
+    pub chunks_restored: usize,
+    pub chunk_proving_durations: Vec<Duration>,
+    pub final_aggregation_duration: Duration,
+}
+
 #[derive(Debug)]
 pub enum ProofAggregationError {
     SP1Aggregation(SP1AggregationError),
@@ -32,16 +70,32 @@ pub enum ProofAggregationError {
 }
 
 impl ZKVMEngine {
-    pub fn from_env() -> Option<Self> {
+    /// Engines to run, from the comma separated `AGGREGATOR` env variable (e.g. `sp1,risc0`).
+    /// Each engine aggregates its own proofs and submits its own aggregated proof.
+    pub fn from_env() -> Option<Vec<Self>> {
         let key = "AGGREGATOR";
         let value = std::env::var(key).ok()?;
-        let engine = match value.as_str() {
-            "sp1" => ZKVMEngine::SP1,
-            "risc0" => ZKVMEngine::RISC0,
-            _ => panic!("Invalid AGGREGATOR, possible options are: sp1|risc0"),
-        };
+        let mut engines = vec![];
+        for engine in value.split(',').map(str::trim) {
+            let engine = match engine {
+                "sp1" => ZKVMEngine::SP1,
+                "risc0" => ZKVMEngine::RISC0,
+                _ => panic!("Invalid AGGREGATOR, possible options are: sp1|risc0|sp1,risc0"),
+            };
+            if !engines.contains(&engine) {
+                engines.push(engine);
+            }
+        }
+
+        Some(engines)
+    }
 
-        Some(engine)
+    /// Name of the engine in the `AGGREGATOR` env variable and the last aggregated block file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SP1 => "sp1",
+            Self::RISC0 => "risc0",
+        }
     }
 
     /// Aggregates a list of [`AlignedProof`]s into a single [`AlignedProof`].
@@ -54,21 +108,26 @@ impl ZKVMEngine {
     /// `proofs_per_chunk`` and uses the `user_proofs_aggregator` to aggregate the proofs.
     /// Then, the `chunk_aggregator` takes the resulting proofs and their corresponding leaves commitments
     /// to produce the final aggregated proof.
+    ///
+    /// When a `checkpoint` is given, each aggregated chunk is persisted to it and chunks
+    /// already aggregated from the same proofs by a previous run are restored instead of proven again.
+    ///
+    /// Proving durations and restored chunks are recorded in `stats`.
     pub fn aggregate_proofs(
         &self,
         proofs: Vec<AlignedProof>,
         proofs_per_chunk: u16,
+        checkpoint: Option<&AggregationCheckpoint>,
+        proving_mode: ProvingMode,
+        stats: &mut AggregationStats,
     ) -> Result<(AlignedProof, [u8; 32]), ProofAggregationError> {
         let res = match self {
             ZKVMEngine::SP1 => {
-                let proofs: Vec<SP1ProofWithPubValuesAndElf> = proofs
+                let proofs: Vec<AlignedProof> = proofs
                     .into_iter()
-                    // Fetcher already filtered for SP1
-                    // We do this for type casting, as to avoid using generics
-                    // or macros in this function
-                    .filter_map(|proof| match proof {
-                        AlignedProof::SP1(proof) => Some(*proof),
-                        _ => None,
+                    // Fetcher already filtered for SP1 and Groth16 proofs
+                    .filter(|proof| {
+                        matches!(proof, AlignedProof::SP1(_) | AlignedProof::Groth16(_))
                     })
                     .collect();
 
@@ -82,18 +141,43 @@ impl ZKVMEngine {
 
                 let mut agg_proofs: Vec<(SP1ProofWithPubValuesAndElf, Vec<[u8; 32]>)> = vec![];
                 for (i, chunk) in chunks.enumerate() {
-                    let leaves_commitment =
-                        chunk.iter().map(|e| e.hash_vk_and_pub_inputs()).collect();
-                    let agg_proof = sp1_aggregator::run_user_proofs_aggregator(chunk)
+                    let leaves_commitment: Vec<[u8; 32]> =
+                        chunk.iter().map(|e| e.commitment()).collect();
+
+                    if let Some(proof) =
+                        checkpoint.and_then(|c| c.load_chunk(i, &leaves_commitment))
+                    {
+                        let agg_proof = sp1_aggregator::user_proofs_aggregation_from_proof(proof);
+                        agg_proofs.push((agg_proof, leaves_commitment));
+                        stats.chunks_restored += 1;
+                        info!("Chunk number {} restored from checkpoint", i);
+                        continue;
+                    }
+
+                    let started_at = Instant::now();
+                    let agg_proof = sp1_aggregator::run_user_proofs_aggregator(chunk, proving_mode)
                         .map_err(ProofAggregationError::SP1Aggregation)?;
+                    if let Some(checkpoint) = checkpoint {
+                        if let Err(e) = checkpoint.save_chunk(
+                            i,
+                            &leaves_commitment,
+                            &agg_proof.proof_with_pub_values,
+                        ) {
+                            warn!("Could not checkpoint chunk number {}: {:?}", i, e);
+                        }
+                    }
                     agg_proofs.push((agg_proof, leaves_commitment));
+                    stats.chunks_proven += 1;
+                    stats.chunk_proving_durations.push(started_at.elapsed());
 
                     info!("Chunk number {} has been aggregated", i);
                 }
 
                 info!("All chunks have been aggregated, performing last aggregation...");
-                let mut agg_proof = sp1_aggregator::run_chunk_aggregator(&agg_proofs)
+                let started_at = Instant::now();
+                let mut agg_proof = sp1_aggregator::run_chunk_aggregator(&agg_proofs, proving_mode)
                     .map_err(ProofAggregationError::SP1Aggregation)?;
+                stats.final_aggregation_duration = started_at.elapsed();
 
                 let merkle_root: [u8; 32] = agg_proof
                     .proof_with_pub_values
@@ -124,20 +208,47 @@ impl ZKVMEngine {
 
                 let mut agg_proofs: Vec<(Risc0ProofReceiptAndImageId, Vec<[u8; 32]>)> = vec![];
                 for (i, chunk) in chunks.enumerate() {
-                    let leaves_commitment = chunk
+                    let leaves_commitment: Vec<[u8; 32]> = chunk
                         .iter()
                         .map(|e| e.hash_image_id_and_public_inputs())
                         .collect();
+
+                    if let Some(receipt) =
+                        checkpoint.and_then(|c| c.load_chunk(i, &leaves_commitment))
+                    {
+                        let agg_proof = Risc0ProofReceiptAndImageId {
+                            image_id:
+                                risc0_aggregator::RISC0_USER_PROOFS_AGGREGATOR_PROGRAM_ID_BYTES,
+                            receipt,
+                        };
+                        agg_proofs.push((agg_proof, leaves_commitment));
+                        stats.chunks_restored += 1;
+                        info!("Chunk number {} restored from checkpoint", i);
+                        continue;
+                    }
+
+                    let started_at = Instant::now();
                     let agg_proof = risc0_aggregator::run_user_proofs_aggregator(chunk)
                         .map_err(ProofAggregationError::Risc0Aggregation)?;
+                    if let Some(checkpoint) = checkpoint {
+                        if let Err(e) =
+                            checkpoint.save_chunk(i, &leaves_commitment, &agg_proof.receipt)
+                        {
+                            warn!("Could not checkpoint chunk number {}: {:?}", i, e);
+                        }
+                    }
                     agg_proofs.push((agg_proof, leaves_commitment));
+                    stats.chunks_proven += 1;
+                    stats.chunk_proving_durations.push(started_at.elapsed());
 
                     info!("Chunk number {} has been aggregated", i);
                 }
 
                 info!("All chunks have been aggregated, performing last aggregation...");
+                let started_at = Instant::now();
                 let agg_proof = risc0_aggregator::run_chunk_aggregator(&agg_proofs)
                     .map_err(ProofAggregationError::Risc0Aggregation)?;
+                stats.final_aggregation_duration = started_at.elapsed();
 
                 // Note: journal.decode() won't work here as risc0 deserializer works under u32 words
                 let public_input_bytes = agg_proof.receipt.journal.as_ref();
@@ -156,62 +267,27 @@ impl ZKVMEngine {
 pub enum AlignedProof {
     SP1(Box<SP1ProofWithPubValuesAndElf>),
     Risc0(Box<Risc0ProofReceiptAndImageId>),
+    /// A gnark or Circom Groth16 BN254 proof, aggregated by the SP1 aggregator.
+    Groth16(Box<Groth16Bn254Proof>),
 }
 
 impl AlignedProof {
-    pub fn commitment(&self) -> [u8; 32] {
+    /// The verification key hash in SP1, the image ID in RISC Zero
+    /// or the keccak of the verification key file for Groth16 proofs.
+    pub fn program_id(&self) -> [u8; 32] {
         match self {
-            AlignedProof::SP1(proof) => proof.hash_vk_and_pub_inputs(),
-            AlignedProof::Risc0(proof) => proof.hash_image_id_and_public_inputs(),
+            AlignedProof::SP1(proof) => proof.vk.hash_bytes(),
+            AlignedProof::Risc0(proof) => proof.image_id,
+            AlignedProof::Groth16(proof) => proof.vk_hash(),
         }
     }
-}
-
-/// Merkle tree commitment for aligned proofs.
-///
-/// Each leaf node (representing a proof) is committed by hashing:
-/// — The program id: the verification key hash in SP1 or the image ID in RISC Zero
-/// — Public inputs.
-///
-/// Intermediate nodes in the tree are formed by computing the keccak pairs of child nodes.
-// Note: this MerkleTreeBackend is defined in three locations
-// - aggregation_mode/src/aggregators/mod.rs
-// - aggregation_mode/src/aggregators/risc0_aggregator.rs
-// - aggregation_mode/src/aggregators/sp1_aggregator.rs
-// All 3 implementations should match
-// The definition on aggregator/mod.rs supports taking proofs from both Risc0 and SP1,
-// Additionally, a version that takes the leaves as already hashed data is defined on:
-// - crates/sdk/src/aggregation_layer/types.rs
-// This one is used in the SDK since,
-// the user may not have access to the proofs that he didn't submit
-impl IsMerkleTreeBackend for AlignedProof {
-    type Data = AlignedProof;
-    type Node = [u8; 32];
-
-    fn hash_data(leaf: &Self::Data) -> Self::Node {
-        leaf.commitment()
-    }
 
-    /// Computes a commutative Keccak256 hash, ensuring H(a, b) == H(b, a).
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#Hashes
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/Hashes.sol#L17-L19
-    ///
-    /// Compliant with OpenZeppelin's `processProofCalldata` function from MerkleProof.sol.
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#MerkleProof
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/MerkleProof.sol#L114-L128
-    fn hash_new_parent(child_1: &Self::Node, child_2: &Self::Node) -> Self::Node {
-        let mut hasher = Keccak256::new();
-        if child_1 < child_2 {
-            hasher.update(child_1);
-            hasher.update(child_2);
-        } else {
-            hasher.update(child_2);
-            hasher.update(child_1);
+    /// Leaf of the proof in the aggregation merkle tree, `keccak(program_id || public_inputs)`.
+    pub fn commitment(&self) -> [u8; 32] {
+        match self {
+            AlignedProof::SP1(proof) => proof.hash_vk_and_pub_inputs(),
+            AlignedProof::Risc0(proof) => proof.hash_image_id_and_public_inputs(),
+            AlignedProof::Groth16(proof) => proof.commitment(),
         }
-        hasher.finalize().into()
     }
 }
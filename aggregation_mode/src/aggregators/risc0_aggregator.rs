@@ -1,6 +1,6 @@
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
 
-use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, Receipt};
+use risc0_zkvm::{default_prover, ExecutorEnv, InnerReceipt, ProverOpts, Receipt};
 use sha3::{Digest, Keccak256};
 
 pub struct Risc0ProofReceiptAndImageId {
@@ -11,7 +11,12 @@ pub struct Risc0ProofReceiptAndImageId {
 #[derive(Debug)]
 pub enum AlignedRisc0VerificationError {
     Verification(String),
-    UnsupportedProof,
+    /// The receipt can't be aggregated. Holds the receipt kind.
+    ///
+    /// Receipts are added as assumptions of the aggregation programs, which the prover
+    /// resolves with the recursion circuit. It only supports composite and succinct receipts,
+    /// so Groth16 receipts, although accepted by the batcher, are left out of aggregation.
+    UnsupportedReceiptKind(&'static str),
 }
 
 impl Risc0ProofReceiptAndImageId {
@@ -20,17 +25,20 @@ impl Risc0ProofReceiptAndImageId {
         image_id: [u8; 32],
         receipt: Receipt,
     ) -> Result<Self, AlignedRisc0VerificationError> {
-        let is_supported_proof =
-            receipt.inner.composite().is_ok() || receipt.inner.succinct().is_ok();
-
-        if is_supported_proof {
-            receipt
-                .verify(image_id)
-                .map_err(|e| AlignedRisc0VerificationError::Verification(e.to_string()))?;
-        } else {
-            return Err(AlignedRisc0VerificationError::UnsupportedProof);
+        let unsupported_kind = match &receipt.inner {
+            InnerReceipt::Composite(_) | InnerReceipt::Succinct(_) => None,
+            InnerReceipt::Groth16(_) => Some("groth16"),
+            InnerReceipt::Fake(_) => Some("fake"),
+            _ => Some("unknown"),
+        };
+        if let Some(kind) = unsupported_kind {
+            return Err(AlignedRisc0VerificationError::UnsupportedReceiptKind(kind));
         }
 
+        receipt
+            .verify(image_id)
+            .map_err(|e| AlignedRisc0VerificationError::Verification(e.to_string()))?;
+
         Ok(Self { image_id, receipt })
     }
 
@@ -86,6 +94,19 @@ impl Risc0ProofReceiptAndImageId {
     }
 }
 
+/// Makes the Risc0 prover produce fake receipts, which are also accepted when verifying them.
+/// Risc0 reads its dev mode from the environment, so this applies to the whole process and has
+/// to be called before spawning any thread, e.g. before starting the tokio runtime.
+pub fn enable_dev_mode() {
+    std::env::set_var("RISC0_DEV_MODE", "1");
+}
+
+/// Whether the Risc0 dev mode is enabled, by [`enable_dev_mode`] or by the environment the process started with.
+pub fn is_dev_mode_enabled() -> bool {
+    std::env::var("RISC0_DEV_MODE")
+        .is_ok_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
 pub(crate) fn run_user_proofs_aggregator(
     proofs: &[Risc0ProofReceiptAndImageId],
 ) -> Result<Risc0ProofReceiptAndImageId, Risc0AggregationError> {
@@ -1,12 +1,11 @@
 use std::sync::LazyLock;
 
+use super::{AlignedProof, ProvingMode};
 use alloy::primitives::Keccak256;
-use sp1_aggregation_program::SP1VkAndPubInputs;
-#[cfg(feature = "prove")]
-use sp1_sdk::EnvProver;
+use sp1_aggregation_program::{SP1VkAndPubInputs, UserProof};
 use sp1_sdk::{
-    CpuProver, HashableKey, Prover, ProverClient, SP1ProofWithPublicValues, SP1Stdin,
-    SP1VerifyingKey,
+    CpuProver, EnvProver, HashableKey, Prover, ProverClient, SP1ProofMode,
+    SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey,
 };
 
 const CHUNK_PROGRAM_ELF: &[u8] =
@@ -15,9 +14,12 @@ const CHUNK_PROGRAM_ELF: &[u8] =
 const USER_PROOFS_PROGRAM_ELF: &[u8] =
     include_bytes!("../../aggregation_programs/sp1/elf/sp1_user_proofs_aggregator_program");
 
-#[cfg(feature = "prove")]
 static SP1_PROVER_CLIENT: LazyLock<EnvProver> = LazyLock::new(ProverClient::from_env);
 
+/// Prover used in [`ProvingMode::Mock`], it executes the programs but doesn't prove them.
+static SP1_MOCK_PROVER_CLIENT: LazyLock<CpuProver> =
+    LazyLock::new(|| ProverClient::builder().mock().build());
+
 /// Separate prover instance configured to always use the CPU.
 /// This is used for verification, which is performed in parallel and
 /// cannot be done on the GPU.
@@ -78,28 +80,34 @@ pub enum SP1AggregationError {
 }
 
 pub(crate) fn run_user_proofs_aggregator(
-    proofs: &[SP1ProofWithPubValuesAndElf],
+    proofs: &[AlignedProof],
+    proving_mode: ProvingMode,
 ) -> Result<SP1ProofWithPubValuesAndElf, SP1AggregationError> {
     let mut stdin = SP1Stdin::new();
 
-    let mut program_input = sp1_aggregation_program::UserProofsAggregatorInput {
-        proofs_vk_and_pub_inputs: vec![],
-    };
+    let mut program_input = sp1_aggregation_program::UserProofsAggregatorInput { proofs: vec![] };
 
-    // write vk + public inputs
+    // write vk + public inputs for SP1 proofs and the whole proof for Groth16 proofs,
+    // in the same order the leaves are committed
     for proof in proofs.iter() {
-        program_input
-            .proofs_vk_and_pub_inputs
-            .push(SP1VkAndPubInputs {
+        let user_proof = match proof {
+            AlignedProof::SP1(proof) => UserProof::SP1(SP1VkAndPubInputs {
                 public_inputs: proof.proof_with_pub_values.public_values.to_vec(),
                 vk: proof.vk.hash_u32(),
-            });
+            }),
+            AlignedProof::Groth16(proof) => UserProof::Groth16Bn254(*proof.clone()),
+            AlignedProof::Risc0(_) => return Err(SP1AggregationError::UnsupportedProof),
+        };
+        program_input.proofs.push(user_proof);
     }
 
     stdin.write(&program_input);
 
     // write proofs
     for input_proof in proofs.iter() {
+        let AlignedProof::SP1(input_proof) = input_proof else {
+            continue;
+        };
         let vk = input_proof.vk.vk.clone();
         // we only support sp1 Compressed proofs for now
         let sp1_sdk::SP1Proof::Compressed(proof) = input_proof.proof_with_pub_values.proof.clone()
@@ -110,24 +118,12 @@ pub(crate) fn run_user_proofs_aggregator(
         stdin.write_proof(*proof, vk);
     }
 
-    #[cfg(feature = "prove")]
-    let client = &*SP1_PROVER_CLIENT;
-    // If not in prove mode, create a mock proof via mock client
-    #[cfg(not(feature = "prove"))]
-    let client = ProverClient::builder().mock().build();
-
-    let (pk, vk) = client.setup(USER_PROOFS_PROGRAM_ELF);
-
-    let proof = client
-        .prove(&pk, &stdin)
-        .compressed()
-        .run()
-        .map_err(|e| SP1AggregationError::Prove(e.to_string()))?;
-
-    // a sanity check, vm already performs it
-    client
-        .verify(&proof, &vk)
-        .map_err(SP1AggregationError::Verification)?;
+    let (proof, vk) = prove(
+        USER_PROOFS_PROGRAM_ELF,
+        &stdin,
+        SP1ProofMode::Compressed,
+        proving_mode,
+    )?;
 
     let proof_and_elf = SP1ProofWithPubValuesAndElf {
         proof_with_pub_values: proof,
@@ -138,8 +134,20 @@ pub(crate) fn run_user_proofs_aggregator(
     Ok(proof_and_elf)
 }
 
+/// Rebuilds the result of [`run_user_proofs_aggregator`] from its proof, as stored in a checkpoint.
+pub(crate) fn user_proofs_aggregation_from_proof(
+    proof_with_pub_values: SP1ProofWithPublicValues,
+) -> SP1ProofWithPubValuesAndElf {
+    SP1ProofWithPubValuesAndElf {
+        proof_with_pub_values,
+        elf: USER_PROOFS_PROGRAM_ELF.to_vec(),
+        vk: vk_from_elf(USER_PROOFS_PROGRAM_ELF),
+    }
+}
+
 pub(crate) fn run_chunk_aggregator(
     proofs: &[(SP1ProofWithPubValuesAndElf, Vec<[u8; 32]>)],
+    proving_mode: ProvingMode,
 ) -> Result<SP1ProofWithPubValuesAndElf, SP1AggregationError> {
     let mut stdin = SP1Stdin::new();
 
@@ -172,35 +180,12 @@ pub(crate) fn run_chunk_aggregator(
         stdin.write_proof(*proof, vk);
     }
 
-    #[cfg(feature = "prove")]
-    let client = &*SP1_PROVER_CLIENT;
-    // If not in prove mode, create a mock proof via mock client
-    #[cfg(not(feature = "prove"))]
-    let client = ProverClient::builder().mock().build();
-
-    let (pk, vk) = client.setup(CHUNK_PROGRAM_ELF);
-
-    #[cfg(feature = "prove")]
-    let proof = client
-        .prove(&pk, &stdin)
-        .groth16()
-        .run()
-        .map_err(|e| SP1AggregationError::Prove(e.to_string()))?;
-    #[cfg(not(feature = "prove"))]
-    let proof = client
-        .prove(&pk, &stdin)
-        // In mock mode, deferred proof verification must be disabled to avoid recursive proof verification.
-        // This is because chunk proofs are mocked, and enabling verification would cause a panic.
-        // See: https://docs.succinct.xyz/docs/sp1/writing-programs/proof-aggregation#proof-aggregation-in-mock-mode
-        .deferred_proof_verification(false)
-        .groth16()
-        .run()
-        .map_err(|e| SP1AggregationError::Prove(e.to_string()))?;
-
-    // a sanity check, vm already performs it
-    client
-        .verify(&proof, &vk)
-        .map_err(SP1AggregationError::Verification)?;
+    let (proof, vk) = prove(
+        CHUNK_PROGRAM_ELF,
+        &stdin,
+        SP1ProofMode::Groth16,
+        proving_mode,
+    )?;
 
     let proof_and_elf = SP1ProofWithPubValuesAndElf {
         proof_with_pub_values: proof,
@@ -211,6 +196,49 @@ pub(crate) fn run_chunk_aggregator(
     Ok(proof_and_elf)
 }
 
+/// Proves `elf` in the given `mode` with the prover of `proving_mode`, verifying the proof as a sanity check,
+/// the vm already performs it.
+fn prove(
+    elf: &[u8],
+    stdin: &SP1Stdin,
+    mode: SP1ProofMode,
+    proving_mode: ProvingMode,
+) -> Result<(SP1ProofWithPublicValues, SP1VerifyingKey), SP1AggregationError> {
+    match proving_mode {
+        ProvingMode::Real => {
+            let client = &*SP1_PROVER_CLIENT;
+            let (pk, vk) = client.setup(elf);
+            let proof = client
+                .prove(&pk, stdin)
+                .mode(mode)
+                .run()
+                .map_err(|e| SP1AggregationError::Prove(e.to_string()))?;
+            client
+                .verify(&proof, &vk)
+                .map_err(SP1AggregationError::Verification)?;
+            Ok((proof, vk))
+        }
+        ProvingMode::Mock => {
+            let client = &*SP1_MOCK_PROVER_CLIENT;
+            let (pk, vk) = client.setup(elf);
+            let proof = client
+                .prove(&pk, stdin)
+                // In mock mode, deferred proof verification must be disabled to avoid recursive proof verification.
+                // This is because chunk proofs are mocked, and enabling verification would cause a panic.
+                // User proofs were already verified when fetched.
+                // See: https://docs.succinct.xyz/docs/sp1/writing-programs/proof-aggregation#proof-aggregation-in-mock-mode
+                .deferred_proof_verification(false)
+                .mode(mode)
+                .run()
+                .map_err(|e| SP1AggregationError::Prove(e.to_string()))?;
+            client
+                .verify(&proof, &vk)
+                .map_err(SP1AggregationError::Verification)?;
+            Ok((proof, vk))
+        }
+    }
+}
+
 pub fn vk_from_elf(elf: &[u8]) -> SP1VerifyingKey {
     let prover = &*SP1_PROVER_CLIENT_CPU;
     let (_, vk) = prover.setup(elf);
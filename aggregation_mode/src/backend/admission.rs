@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::fetcher::FetchedProof;
+use crate::aggregators::ZKVMEngine;
+use alloy::hex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Which of the fetched proofs get aggregated. The default admits every proof.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AdmissionPolicyConfig {
+    /// Only proofs of these program ids (SP1 vk hash, Risc0 image id or Groth16 vk keccak)
+    /// are aggregated. Every program is when empty.
+    #[serde(default)]
+    pub allowed_program_ids: Vec<String>,
+    /// Proofs of these program ids are never aggregated.
+    #[serde(default)]
+    pub denied_program_ids: Vec<String>,
+    /// Proofs of a submitter aggregated per cycle, the ones over it are left for a later cycle.
+    #[serde(default)]
+    pub max_proofs_per_submitter: Option<usize>,
+    /// Blocks a batch has to be deep to have its proofs fetched. Younger batches are left for a later cycle.
+    #[serde(default)]
+    pub min_proof_age_blocks: Option<u64>,
+    /// Aggregate a single proof out of the ones with the same program id and public inputs.
+    #[serde(default)]
+    pub dedup_commitments: bool,
+}
+
+#[derive(Debug)]
+pub enum AdmissionPolicyError {
+    InvalidProgramId(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionReason {
+    ProgramNotAllowed,
+    ProgramDenied,
+    DuplicateCommitment,
+    SubmitterLimitReached,
+    /// Left out by the fetcher, the proof, program or public inputs are missing or can't be deserialized
+    InvalidProofData,
+    /// Left out by the fetcher, the aggregation programs can't take this kind of proof
+    UnsupportedProof,
+    /// Left out by the fetcher, the proof doesn't verify
+    VerificationFailed,
+}
+
+impl ExclusionReason {
+    /// Name of the reason in logs, metrics labels and run reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ProgramNotAllowed => "program_not_allowed",
+            Self::ProgramDenied => "program_denied",
+            Self::DuplicateCommitment => "duplicate_commitment",
+            Self::SubmitterLimitReached => "submitter_limit_reached",
+            Self::InvalidProofData => "invalid_proof_data",
+            Self::UnsupportedProof => "unsupported_proof",
+            Self::VerificationFailed => "verification_failed",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExcludedProof {
+    pub program_id: [u8; 32],
+    pub submitter: [u8; 20],
+    pub block_number: u64,
+    pub reason: ExclusionReason,
+}
+
+/// Outcome of applying the [`AdmissionPolicy`] to the fetched proofs.
+#[derive(Debug, Default)]
+pub struct AdmissionReport {
+    pub admitted: usize,
+    pub excluded: Vec<ExcludedProof>,
+    /// Block from which the proofs are left for the next cycle, as a submitter reached its limit in it
+    pub deferred_from_block: Option<u64>,
+    /// Proofs of the blocks from `deferred_from_block` on
+    pub deferred: usize,
+}
+
+impl AdmissionReport {
+    pub fn excluded_by_reason(&self) -> BTreeMap<&'static str, usize> {
+        let mut excluded_by_reason = BTreeMap::new();
+        for excluded in &self.excluded {
+            *excluded_by_reason
+                .entry(excluded.reason.name())
+                .or_default() += 1;
+        }
+        excluded_by_reason
+    }
+
+    pub fn log(&self, engine: &ZKVMEngine) {
+        let engine = engine.name();
+        for excluded in &self.excluded {
+            info!(
+                engine,
+                program_id = format!("0x{}", hex::encode(excluded.program_id)),
+                submitter = format!("0x{}", hex::encode(excluded.submitter)),
+                block_number = excluded.block_number,
+                reason = excluded.reason.name(),
+                "Proof excluded from aggregation"
+            );
+        }
+
+        info!(
+            engine,
+            admitted = self.admitted,
+            excluded = self.excluded.len(),
+            excluded_by_reason = ?self.excluded_by_reason(),
+            deferred = self.deferred,
+            deferred_from_block = self.deferred_from_block,
+            "Admission policy applied"
+        );
+    }
+}
+
+pub struct AdmissionPolicy {
+    allowed_program_ids: HashSet<[u8; 32]>,
+    denied_program_ids: HashSet<[u8; 32]>,
+    max_proofs_per_submitter: Option<usize>,
+    dedup_commitments: bool,
+}
+
+impl AdmissionPolicy {
+    pub fn new(config: &AdmissionPolicyConfig) -> Result<Self, AdmissionPolicyError> {
+        Ok(Self {
+            allowed_program_ids: parse_program_ids(&config.allowed_program_ids)?,
+            denied_program_ids: parse_program_ids(&config.denied_program_ids)?,
+            max_proofs_per_submitter: config.max_proofs_per_submitter,
+            dedup_commitments: config.dedup_commitments,
+        })
+    }
+
+    /// Returns the admitted proofs, in the order they were fetched, and the report of the excluded ones.
+    ///
+    /// Program lists are checked first, then duplicates and last the submitter limits,
+    /// so excluded proofs don't count towards the limit of their submitter.
+    ///
+    /// Proofs are fetched by whole blocks, so when a submitter reaches its limit the proofs of its block
+    /// and the following ones are deferred, see [`AdmissionReport::deferred_from_block`]. Only in the
+    /// first block, which would be fetched again with the same proofs, the ones over the limit are excluded.
+    pub fn apply(&self, proofs: Vec<FetchedProof>) -> (Vec<FetchedProof>, AdmissionReport) {
+        let mut admitted = vec![];
+        let mut report = AdmissionReport::default();
+        let mut seen_commitments = HashSet::new();
+        let mut proofs_per_submitter: HashMap<[u8; 20], usize> = HashMap::new();
+        let fetched_proofs = proofs.len();
+        let first_block = proofs.first().map(|fetched| fetched.block_number);
+
+        for fetched in proofs {
+            let program_id = fetched.proof.program_id();
+            let reason = if !self.allowed_program_ids.is_empty()
+                && !self.allowed_program_ids.contains(&program_id)
+            {
+                Some(ExclusionReason::ProgramNotAllowed)
+            } else if self.denied_program_ids.contains(&program_id) {
+                Some(ExclusionReason::ProgramDenied)
+            } else if self.dedup_commitments && !seen_commitments.insert(fetched.proof.commitment())
+            {
+                Some(ExclusionReason::DuplicateCommitment)
+            } else if self.max_proofs_per_submitter.is_some_and(|max| {
+                proofs_per_submitter
+                    .get(&fetched.submitter)
+                    .is_some_and(|count| *count >= max)
+            }) {
+                Some(ExclusionReason::SubmitterLimitReached)
+            } else {
+                None
+            };
+
+            if reason == Some(ExclusionReason::SubmitterLimitReached)
+                && first_block != Some(fetched.block_number)
+            {
+                let block = fetched.block_number;
+                admitted.retain(|admitted: &FetchedProof| admitted.block_number < block);
+                report
+                    .excluded
+                    .retain(|excluded| excluded.block_number < block);
+                report.deferred_from_block = Some(block);
+                break;
+            }
+
+            match reason {
+                Some(reason) => report.excluded.push(ExcludedProof {
+                    program_id,
+                    submitter: fetched.submitter,
+                    block_number: fetched.block_number,
+                    reason,
+                }),
+                None => {
+                    *proofs_per_submitter.entry(fetched.submitter).or_default() += 1;
+                    admitted.push(fetched);
+                }
+            }
+        }
+
+        report.admitted = admitted.len();
+        report.deferred = fetched_proofs - report.admitted - report.excluded.len();
+        (admitted, report)
+    }
+}
+
+fn parse_program_ids(program_ids: &[String]) -> Result<HashSet<[u8; 32]>, AdmissionPolicyError> {
+    program_ids
+        .iter()
+        .map(|program_id| {
+            hex::decode(program_id.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| AdmissionPolicyError::InvalidProgramId(program_id.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdmissionPolicy, AdmissionPolicyConfig, ExclusionReason};
+    use crate::{
+        aggregators::{risc0_aggregator::Risc0ProofReceiptAndImageId, AlignedProof},
+        backend::fetcher::FetchedProof,
+    };
+    use alloy::hex;
+    use risc0_zkvm::{InnerReceipt, Receipt};
+
+    const RISC0_PROOF_PATH: &str =
+        "../scripts/test_files/risc_zero/fibonacci_proof_generator/risc_zero_fibonacci_2_2_0.proof";
+
+    /// A Risc0 proof whose program id and public inputs are made of `program_id` and `public_input`.
+    /// The policy doesn't verify proofs, so the receipt is not checked against them.
+    fn proof(program_id: u8, public_input: u8, submitter: u8, block_number: u64) -> FetchedProof {
+        let inner_receipt: InnerReceipt =
+            bincode::deserialize(&std::fs::read(RISC0_PROOF_PATH).unwrap()).unwrap();
+        FetchedProof {
+            proof: AlignedProof::Risc0(Box::new(Risc0ProofReceiptAndImageId {
+                image_id: [program_id; 32],
+                receipt: Receipt::new(inner_receipt, vec![public_input]),
+            })),
+            submitter: [submitter; 20],
+            block_number,
+        }
+    }
+
+    fn policy(config: AdmissionPolicyConfig) -> AdmissionPolicy {
+        AdmissionPolicy::new(&config).unwrap()
+    }
+
+    fn program_id(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 32]))
+    }
+
+    #[test]
+    fn proofs_of_programs_not_allowed_are_excluded() {
+        let policy = policy(AdmissionPolicyConfig {
+            allowed_program_ids: vec![program_id(1)],
+            ..Default::default()
+        });
+
+        let (admitted, report) = policy.apply(vec![proof(1, 1, 1, 10), proof(2, 2, 1, 10)]);
+        assert_eq!(admitted.len(), 1);
+        assert_eq!(admitted[0].proof.program_id(), [1; 32]);
+        assert_eq!(report.excluded.len(), 1);
+        assert_eq!(report.excluded[0].program_id, [2; 32]);
+        assert_eq!(
+            report.excluded[0].reason,
+            ExclusionReason::ProgramNotAllowed
+        );
+        assert_eq!(report.deferred, 0);
+    }
+
+    #[test]
+    fn proofs_of_denied_programs_are_excluded() {
+        let policy = policy(AdmissionPolicyConfig {
+            denied_program_ids: vec![program_id(2)],
+            ..Default::default()
+        });
+
+        let (admitted, report) = policy.apply(vec![proof(1, 1, 1, 10), proof(2, 2, 1, 10)]);
+        assert_eq!(admitted.len(), 1);
+        assert_eq!(admitted[0].proof.program_id(), [1; 32]);
+        assert_eq!(report.excluded.len(), 1);
+        assert_eq!(report.excluded[0].reason, ExclusionReason::ProgramDenied);
+    }
+
+    #[test]
+    fn duplicated_commitments_are_excluded() {
+        let policy = policy(AdmissionPolicyConfig {
+            dedup_commitments: true,
+            ..Default::default()
+        });
+
+        let (admitted, report) = policy.apply(vec![
+            proof(1, 1, 1, 10),
+            proof(1, 1, 2, 11),
+            proof(1, 2, 1, 11),
+        ]);
+        assert_eq!(admitted.len(), 2);
+        assert_eq!(
+            admitted[1].proof.commitment(),
+            proof(1, 2, 1, 11).proof.commitment()
+        );
+        assert_eq!(report.excluded.len(), 1);
+        assert_eq!(report.excluded[0].submitter, [2; 20]);
+        assert_eq!(
+            report.excluded[0].reason,
+            ExclusionReason::DuplicateCommitment
+        );
+    }
+
+    #[test]
+    fn proofs_over_the_submitter_limit_defer_their_block() {
+        let policy = policy(AdmissionPolicyConfig {
+            max_proofs_per_submitter: Some(1),
+            denied_program_ids: vec![program_id(2)],
+            ..Default::default()
+        });
+
+        // The second proof of submitter 1 is in block 11, which is left along with block 12
+        let (admitted, report) = policy.apply(vec![
+            proof(1, 1, 1, 10),
+            proof(1, 2, 2, 11),
+            proof(2, 3, 3, 11),
+            proof(1, 4, 1, 11),
+            proof(1, 5, 3, 12),
+        ]);
+        assert_eq!(admitted.len(), 1);
+        assert_eq!(admitted[0].block_number, 10);
+        assert!(report.excluded.is_empty());
+        assert_eq!(report.deferred_from_block, Some(11));
+        assert_eq!(report.deferred, 4);
+        assert!(report.excluded_by_reason().is_empty());
+    }
+
+    #[test]
+    fn proofs_over_the_submitter_limit_in_the_first_block_are_excluded() {
+        let policy = policy(AdmissionPolicyConfig {
+            max_proofs_per_submitter: Some(1),
+            ..Default::default()
+        });
+
+        // Deferring the first block would fetch the same proofs again
+        let (admitted, report) = policy.apply(vec![
+            proof(1, 1, 1, 10),
+            proof(1, 2, 1, 10),
+            proof(1, 3, 2, 10),
+            proof(1, 4, 2, 11),
+        ]);
+        assert_eq!(admitted.len(), 2);
+        assert_eq!(admitted[1].submitter, [2; 20]);
+        assert_eq!(report.excluded.len(), 1);
+        assert_eq!(
+            report.excluded[0].reason,
+            ExclusionReason::SubmitterLimitReached
+        );
+        assert_eq!(report.deferred_from_block, Some(11));
+        assert_eq!(report.deferred, 1);
+    }
+
+    #[test]
+    fn every_proof_is_admitted_by_default() {
+        let (admitted, report) = policy(AdmissionPolicyConfig::default()).apply(vec![
+            proof(1, 1, 1, 10),
+            proof(1, 1, 1, 10),
+            proof(2, 2, 1, 11),
+        ]);
+        assert_eq!(admitted.len(), 3);
+        assert_eq!(report.admitted, 3);
+        assert!(report.excluded.is_empty());
+        assert_eq!(report.deferred_from_block, None);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    checkpoint::write_file_atomically,
+    s3::{deserialize_aligned_batch, get_aligned_batch_bytes_from_s3, GetBatchProofsError},
+};
+use aligned_sdk::common::types::{
+    VerificationCommitmentBatch, VerificationData, VerificationDataCommitment,
+};
+use alloy::hex;
+use futures_util::{stream, Stream, StreamExt};
+use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
+use rayon::prelude::*;
+use tracing::warn;
+
+const DOWNLOAD_RETRY_BASE_DELAY_MS: u64 = 500;
+const DOWNLOAD_RETRY_MAX_DELAY_MS: u64 = 30_000;
+
+#[derive(Debug)]
+pub enum BatchDownloadError {
+    /// The storage answered with a client error, e.g. the batch doesn't exist,
+    /// or kept failing after all the retries
+    Unavailable(GetBatchProofsError),
+    /// The downloaded data is not a batch, retrying won't help
+    Deserialization(GetBatchProofsError),
+    /// The downloaded batch doesn't match the batch merkle root submitted on chain
+    MerkleRootMismatch,
+}
+
+/// Downloads batches concurrently, verifying them against their merkle root and caching the verified
+/// ones on disk, named by block and merkle root.
+///
+/// A batch holds the proofs of every engine, so the fetchers of all the engines share the same cache.
+pub struct BatchDownloader {
+    cache_dir: PathBuf,
+    max_concurrent_downloads: usize,
+    max_retries: u32,
+}
+
+impl BatchDownloader {
+    pub fn new(cache_dir: String, max_concurrent_downloads: usize, max_retries: u32) -> Self {
+        Self {
+            cache_dir: PathBuf::from(cache_dir),
+            max_concurrent_downloads: max_concurrent_downloads.max(1),
+            max_retries,
+        }
+    }
+
+    /// Downloads the batches given by their block, data pointer and merkle root, running up to
+    /// `max_concurrent_downloads` at once. Results are yielded in the same order as `batches`.
+    ///
+    /// Downloads only advance while the stream is polled, so dropping it once enough proofs
+    /// were taken leaves the rest of the batches undownloaded.
+    pub fn download<'a>(
+        &'a self,
+        batches: &'a [(u64, String, [u8; 32])],
+    ) -> impl Stream<Item = Result<Vec<VerificationData>, BatchDownloadError>> + 'a {
+        stream::iter(batches)
+            .map(|(block_number, data_pointer, batch_merkle_root)| {
+                self.download_batch(*block_number, data_pointer, *batch_merkle_root)
+            })
+            .buffered(self.max_concurrent_downloads)
+    }
+
+    /// Removes the cached batches up to `block`, once every engine aggregated their proofs
+    pub fn evict_up_to(&self, block: u64) {
+        let Ok(entries) = fs::read_dir(&self.cache_dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let batch_block = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split_once('_'))
+                .and_then(|(block, _)| block.parse::<u64>().ok());
+            if batch_block.is_some_and(|batch_block| batch_block <= block) {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("Could not remove cached batch {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    async fn download_batch(
+        &self,
+        block_number: u64,
+        data_pointer: &str,
+        batch_merkle_root: [u8; 32],
+    ) -> Result<Vec<VerificationData>, BatchDownloadError> {
+        let path = self.cache_path(block_number, &batch_merkle_root);
+        if let Ok(bytes) = fs::read(&path) {
+            match decode_and_verify(&bytes, batch_merkle_root) {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    warn!("Ignoring invalid cached batch {}: {:?}", path.display(), e);
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        let mut retries = 0;
+        let bytes = loop {
+            match get_aligned_batch_bytes_from_s3(data_pointer.to_string()).await {
+                Ok(bytes) => break bytes,
+                Err(e) if retries < self.max_retries && is_retryable(&e) => {
+                    let delay = download_retry_delay(retries);
+                    warn!(
+                        "Error while downloading batch from {}, retrying in {:?}. Err {:?}",
+                        data_pointer, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                Err(e) => return Err(BatchDownloadError::Unavailable(e)),
+            }
+        };
+
+        let data = decode_and_verify(&bytes, batch_merkle_root)?;
+        if let Err(e) = self.cache(&path, &bytes) {
+            warn!("Could not cache batch {}: {}", path.display(), e);
+        }
+
+        Ok(data)
+    }
+
+    fn cache(&self, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        fs::create_dir_all(&self.cache_dir)?;
+        write_file_atomically(path, bytes)
+    }
+
+    fn cache_path(&self, block_number: u64, batch_merkle_root: &[u8; 32]) -> PathBuf {
+        self.cache_dir.join(format!(
+            "{}_0x{}.cbor",
+            block_number,
+            hex::encode(batch_merkle_root)
+        ))
+    }
+}
+
+/// Client errors would be answered again, except for rate limits.
+fn is_retryable(e: &GetBatchProofsError) -> bool {
+    match e {
+        GetBatchProofsError::StatusFailed((status, _)) => {
+            !(400..500).contains(status) || *status == 429
+        }
+        _ => true,
+    }
+}
+
+/// Exponential backoff of the download retries, capped so a large `batch_download_max_retries` doesn't overflow.
+fn download_retry_delay(retries: u32) -> Duration {
+    let delay_ms = 2u64
+        .checked_pow(retries)
+        .and_then(|factor| DOWNLOAD_RETRY_BASE_DELAY_MS.checked_mul(factor))
+        .unwrap_or(u64::MAX)
+        .min(DOWNLOAD_RETRY_MAX_DELAY_MS);
+    Duration::from_millis(delay_ms)
+}
+
+/// Deserializes the batch and checks its verification data commits to `batch_merkle_root`,
+/// the same way the batcher built the merkle tree of the batch.
+fn decode_and_verify(
+    bytes: &[u8],
+    batch_merkle_root: [u8; 32],
+) -> Result<Vec<VerificationData>, BatchDownloadError> {
+    let data = deserialize_aligned_batch(bytes).map_err(BatchDownloadError::Deserialization)?;
+
+    let commitments: Vec<VerificationDataCommitment> = data
+        .par_iter()
+        .map(|verification_data| verification_data.clone().into())
+        .collect();
+    let root = MerkleTree::<VerificationCommitmentBatch>::build(&commitments).map(|tree| tree.root);
+    if root != Some(batch_merkle_root) {
+        return Err(BatchDownloadError::MerkleRootMismatch);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_and_verify, is_retryable, BatchDownloadError, BatchDownloader};
+    use crate::backend::s3::GetBatchProofsError;
+    use aligned_sdk::common::types::{
+        ProvingSystemId, VerificationCommitmentBatch, VerificationData, VerificationDataCommitment,
+    };
+    use alloy::primitives::Address;
+    use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
+
+    fn batch() -> (Vec<VerificationData>, [u8; 32]) {
+        let data: Vec<VerificationData> = (0..3u8)
+            .map(|i| VerificationData {
+                proving_system: ProvingSystemId::GnarkPlonkBn254,
+                proof: vec![i; 8],
+                pub_input: Some(vec![i]),
+                verification_key: Some(vec![i; 4]),
+                vm_program_code: None,
+                proof_generator_addr: Address::repeat_byte(i),
+            })
+            .collect();
+        let commitments: Vec<VerificationDataCommitment> =
+            data.iter().cloned().map(Into::into).collect();
+        let root = MerkleTree::<VerificationCommitmentBatch>::build(&commitments)
+            .unwrap()
+            .root;
+        (data, root)
+    }
+
+    fn encode(data: &[VerificationData]) -> Vec<u8> {
+        let mut bytes = vec![];
+        ciborium::into_writer(data, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decode_and_verify_returns_the_batch_matching_its_root() {
+        let (data, root) = batch();
+        let decoded = decode_and_verify(&encode(&data), root).unwrap();
+        assert_eq!(decoded.len(), data.len());
+        assert_eq!(decoded[2].proof, data[2].proof);
+    }
+
+    #[test]
+    fn decode_and_verify_rejects_a_batch_with_another_root() {
+        let (data, mut root) = batch();
+        root[0] ^= 1;
+        assert!(matches!(
+            decode_and_verify(&encode(&data), root),
+            Err(BatchDownloadError::MerkleRootMismatch)
+        ));
+
+        // Dropping a proof changes the root too
+        let (data, root) = batch();
+        assert!(matches!(
+            decode_and_verify(&encode(&data[..2]), root),
+            Err(BatchDownloadError::MerkleRootMismatch)
+        ));
+    }
+
+    #[test]
+    fn decode_and_verify_rejects_bytes_that_are_not_a_batch() {
+        let (_, root) = batch();
+        assert!(matches!(
+            decode_and_verify(b"not a cbor batch", root),
+            Err(BatchDownloadError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn client_errors_are_not_retried_except_rate_limits() {
+        let status = |code| GetBatchProofsError::StatusFailed((code, String::new()));
+        assert!(!is_retryable(&status(403)));
+        assert!(!is_retryable(&status(404)));
+        assert!(is_retryable(&status(429)));
+        assert!(is_retryable(&status(503)));
+        assert!(is_retryable(&GetBatchProofsError::FetchingS3Batch(
+            String::new()
+        )));
+    }
+
+    #[test]
+    fn download_retry_delay_is_capped() {
+        assert_eq!(super::download_retry_delay(0).as_millis(), 500);
+        assert_eq!(super::download_retry_delay(2).as_millis(), 2000);
+        assert_eq!(super::download_retry_delay(10).as_millis(), 30_000);
+        assert_eq!(super::download_retry_delay(u32::MAX).as_millis(), 30_000);
+    }
+
+    #[test]
+    fn cached_batches_are_evicted_up_to_a_block() {
+        let dir =
+            std::env::temp_dir().join(format!("aligned-aggregator-batches-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let downloader = BatchDownloader::new(dir.to_string_lossy().to_string(), 1, 0);
+        for block in [9, 10, 11] {
+            downloader
+                .cache(&downloader.cache_path(block, &[block as u8; 32]), b"batch")
+                .unwrap();
+        }
+
+        downloader.evict_up_to(10);
+        assert!(!downloader.cache_path(9, &[9; 32]).exists());
+        assert!(!downloader.cache_path(10, &[10; 32]).exists());
+        assert!(downloader.cache_path(11, &[11; 32]).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
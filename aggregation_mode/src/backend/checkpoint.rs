@@ -0,0 +1,418 @@
+use crate::aggregators::ZKVMEngine;
+use alloy::{hex, primitives::TxHash};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::{info, warn};
+
+const METADATA_FILE: &str = "metadata.json";
+const CONSUMED_BATCHES_FILE: &str = "consumed_batches.jsonl";
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    Serialization(String),
+}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Block range being aggregated and the batches it consumes.
+/// Also the record appended to the consumed batches file once the range is submitted.
+#[derive(Debug, Serialize, Deserialize)]
+struct CheckpointMetadata {
+    engine: String,
+    from_block: u64,
+    to_block: u64,
+    batch_merkle_roots: Vec<String>,
+    /// Nonce shared by the verification transactions sent for the range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<u64>,
+    /// Verification transactions sent for the range, each one a replacement of the previous
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sent_tx_hashes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkCheckpoint<P> {
+    leaves: Vec<[u8; 32]>,
+    proof: P,
+}
+
+/// Progress of the aggregation of a block range, persisted so an aggregator that dies
+/// while proving can resume from the chunks it already aggregated.
+///
+/// Each range gets its own directory `<engine>_<from_block>_<to_block>` under the checkpoints
+/// directory, holding the range metadata and one file per aggregated chunk. The metadata also holds
+/// the verification transactions sent for the range, so a run that dies while waiting for them
+/// can find them instead of sending the proof again.
+/// Once the aggregated proof is verified on chain the range is appended to `consumed_batches.jsonl`
+/// and its directory removed.
+pub struct AggregationCheckpoint {
+    base_dir: PathBuf,
+    dir: PathBuf,
+    metadata: CheckpointMetadata,
+}
+
+impl AggregationCheckpoint {
+    /// Returns the last block of the `engine` checkpoint starting at `from_block`, if any,
+    /// so the same proofs are fetched again and its chunks can be reused.
+    ///
+    /// Checkpoints of the engine starting at other blocks can't be resumed anymore and are removed.
+    pub fn find_resumable(
+        base_dir: &str,
+        engine: &ZKVMEngine,
+        from_block: u64,
+    ) -> Result<Option<u64>, CheckpointError> {
+        let base_dir = Path::new(base_dir);
+        if !base_dir.exists() {
+            return Ok(None);
+        }
+
+        let prefix = format!("{}_", engine.name());
+        let mut resumable = None;
+        for entry in fs::read_dir(base_dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(range) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            if !path.is_dir() {
+                continue;
+            }
+
+            let range = range
+                .split_once('_')
+                .and_then(|(from, to)| Some((from.parse::<u64>().ok()?, to.parse::<u64>().ok()?)));
+            match range {
+                Some((from, to)) if from == from_block && resumable.is_none() => {
+                    resumable = Some(to)
+                }
+                _ => {
+                    warn!("Removing stale aggregation checkpoint {}", path.display());
+                    fs::remove_dir_all(&path)?;
+                }
+            }
+        }
+
+        Ok(resumable)
+    }
+
+    /// Opens the checkpoint of the given block range, creating it if it doesn't exist.
+    /// The transactions sent by a previous run are kept if it consumed the same batches.
+    pub fn open(
+        base_dir: &str,
+        engine: &ZKVMEngine,
+        from_block: u64,
+        to_block: u64,
+        batch_merkle_roots: &[[u8; 32]],
+    ) -> Result<Self, CheckpointError> {
+        let base_dir = PathBuf::from(base_dir);
+        let dir = base_dir.join(format!("{}_{}_{}", engine.name(), from_block, to_block));
+        fs::create_dir_all(&dir)?;
+
+        let mut metadata = CheckpointMetadata {
+            engine: engine.name().to_string(),
+            from_block,
+            to_block,
+            batch_merkle_roots: batch_merkle_roots
+                .iter()
+                .map(|root| format!("0x{}", hex::encode(root)))
+                .collect(),
+            nonce: None,
+            sent_tx_hashes: vec![],
+            tx_hash: None,
+        };
+        let previous = fs::read(dir.join(METADATA_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_slice::<CheckpointMetadata>(&content).ok());
+        if let Some(previous) =
+            previous.filter(|previous| previous.batch_merkle_roots == metadata.batch_merkle_roots)
+        {
+            metadata.nonce = previous.nonce;
+            metadata.sent_tx_hashes = previous.sent_tx_hashes;
+        }
+
+        let checkpoint = Self {
+            base_dir,
+            dir,
+            metadata,
+        };
+        checkpoint.save_metadata()?;
+
+        Ok(checkpoint)
+    }
+
+    /// Verification transactions sent for the range, by this or a previous run, and the nonce they share.
+    pub fn sent_transactions(&self) -> (Option<u64>, Vec<TxHash>) {
+        let tx_hashes = self
+            .metadata
+            .sent_tx_hashes
+            .iter()
+            .filter_map(|tx_hash| TxHash::from_str(tx_hash).ok())
+            .collect();
+        (self.metadata.nonce, tx_hashes)
+    }
+
+    /// Persists a sent verification transaction before waiting for its receipt.
+    /// Transactions recorded with another nonce are dropped, they can no longer be included.
+    pub fn record_sent_transaction(
+        &mut self,
+        nonce: u64,
+        tx_hash: TxHash,
+    ) -> Result<(), CheckpointError> {
+        if self.metadata.nonce != Some(nonce) {
+            self.metadata.nonce = Some(nonce);
+            self.metadata.sent_tx_hashes.clear();
+        }
+        self.metadata.sent_tx_hashes.push(format!("{:?}", tx_hash));
+        self.save_metadata()
+    }
+
+    /// Forgets the sent verification transactions, e.g. once they are known to have reverted.
+    pub fn clear_sent_transactions(&mut self) -> Result<(), CheckpointError> {
+        self.metadata.nonce = None;
+        self.metadata.sent_tx_hashes.clear();
+        self.save_metadata()
+    }
+
+    fn save_metadata(&self) -> Result<(), CheckpointError> {
+        let content = serde_json::to_vec(&self.metadata)
+            .map_err(|e| CheckpointError::Serialization(e.to_string()))?;
+        write_file_atomically(&self.dir.join(METADATA_FILE), &content)?;
+
+        Ok(())
+    }
+
+    /// Loads the proof of the chunk `index`, only if it was aggregated from the same `leaves`.
+    pub(crate) fn load_chunk<P: DeserializeOwned>(
+        &self,
+        index: usize,
+        leaves: &[[u8; 32]],
+    ) -> Option<P> {
+        let path = self.chunk_path(index);
+        let content = fs::read(&path).ok()?;
+        let chunk: ChunkCheckpoint<P> = match bincode::deserialize(&content) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                warn!(
+                    "Ignoring corrupted chunk checkpoint {}: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        if chunk.leaves != leaves {
+            warn!(
+                "Ignoring chunk checkpoint {}, it was aggregated from other proofs",
+                path.display()
+            );
+            return None;
+        }
+
+        Some(chunk.proof)
+    }
+
+    pub(crate) fn save_chunk<P: Serialize>(
+        &self,
+        index: usize,
+        leaves: &[[u8; 32]],
+        proof: &P,
+    ) -> Result<(), CheckpointError> {
+        let chunk = ChunkCheckpoint {
+            leaves: leaves.to_vec(),
+            proof,
+        };
+        let content = bincode::serialize(&chunk)
+            .map_err(|e| CheckpointError::Serialization(e.to_string()))?;
+        write_file_atomically(&self.chunk_path(index), &content)?;
+
+        Ok(())
+    }
+
+    /// Records the batches of the range as consumed by the aggregated proof sent in `tx_hash`
+    /// and removes the range checkpoint.
+    pub fn complete(mut self, tx_hash: String) -> Result<(), CheckpointError> {
+        self.metadata.tx_hash = Some(tx_hash);
+        self.metadata.nonce = None;
+        self.metadata.sent_tx_hashes.clear();
+        let mut record = serde_json::to_vec(&self.metadata)
+            .map_err(|e| CheckpointError::Serialization(e.to_string()))?;
+        record.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.base_dir.join(CONSUMED_BATCHES_FILE))?;
+        file.write_all(&record)?;
+        file.sync_all()?;
+
+        fs::remove_dir_all(&self.dir)?;
+        info!(
+            "Aggregation checkpoint of blocks {} to {} completed",
+            self.metadata.from_block, self.metadata.to_block
+        );
+
+        Ok(())
+    }
+
+    fn chunk_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("chunk_{}.bin", index))
+    }
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it over `path`,
+/// so a crash never leaves `path` half written.
+pub(crate) fn write_file_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AggregationCheckpoint, CONSUMED_BATCHES_FILE};
+    use crate::aggregators::ZKVMEngine;
+    use alloy::primitives::TxHash;
+    use std::path::PathBuf;
+
+    fn checkpoints_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "aligned-aggregator-checkpoint-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn chunks_are_loaded_only_for_the_same_leaves() {
+        let dir = checkpoints_dir("chunks");
+        let base_dir = dir.to_str().unwrap();
+        let leaves = [[1u8; 32], [2u8; 32]];
+
+        let checkpoint =
+            AggregationCheckpoint::open(base_dir, &ZKVMEngine::SP1, 10, 20, &[[9u8; 32]]).unwrap();
+        checkpoint.save_chunk(0, &leaves, &vec![7u8; 4]).unwrap();
+
+        assert_eq!(
+            checkpoint.load_chunk::<Vec<u8>>(0, &leaves),
+            Some(vec![7u8; 4])
+        );
+        assert_eq!(checkpoint.load_chunk::<Vec<u8>>(0, &leaves[..1]), None);
+        assert_eq!(checkpoint.load_chunk::<Vec<u8>>(1, &leaves), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_checkpoint_starting_at_the_last_aggregated_block_is_resumable() {
+        let dir = checkpoints_dir("resumable");
+        let base_dir = dir.to_str().unwrap();
+
+        assert_eq!(
+            AggregationCheckpoint::find_resumable(base_dir, &ZKVMEngine::SP1, 10).unwrap(),
+            None
+        );
+
+        AggregationCheckpoint::open(base_dir, &ZKVMEngine::SP1, 5, 9, &[]).unwrap();
+        AggregationCheckpoint::open(base_dir, &ZKVMEngine::SP1, 10, 20, &[]).unwrap();
+        AggregationCheckpoint::open(base_dir, &ZKVMEngine::RISC0, 5, 9, &[]).unwrap();
+
+        assert_eq!(
+            AggregationCheckpoint::find_resumable(base_dir, &ZKVMEngine::SP1, 10).unwrap(),
+            Some(20)
+        );
+        // The stale checkpoint of the engine is removed, the ones of other engines are left alone
+        assert!(!dir.join("sp1_5_9").exists());
+        assert!(dir.join("sp1_10_20").exists());
+        assert!(dir.join("risc0_5_9").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sent_transactions_survive_reopening_the_same_batches() {
+        let dir = checkpoints_dir("sent-transactions");
+        let base_dir = dir.to_str().unwrap();
+        let tx_hash = TxHash::repeat_byte(0xab);
+
+        let mut checkpoint =
+            AggregationCheckpoint::open(base_dir, &ZKVMEngine::RISC0, 10, 20, &[[9u8; 32]])
+                .unwrap();
+        checkpoint.record_sent_transaction(3, tx_hash).unwrap();
+
+        let checkpoint =
+            AggregationCheckpoint::open(base_dir, &ZKVMEngine::RISC0, 10, 20, &[[9u8; 32]])
+                .unwrap();
+        assert_eq!(checkpoint.sent_transactions(), (Some(3), vec![tx_hash]));
+
+        // Other batches make for another aggregation, whose proof was never sent
+        let mut checkpoint =
+            AggregationCheckpoint::open(base_dir, &ZKVMEngine::RISC0, 10, 20, &[[8u8; 32]])
+                .unwrap();
+        assert_eq!(checkpoint.sent_transactions(), (None, vec![]));
+
+        // A new nonce means the previous transactions can't be included anymore
+        checkpoint.record_sent_transaction(3, tx_hash).unwrap();
+        checkpoint
+            .record_sent_transaction(4, TxHash::repeat_byte(0xcd))
+            .unwrap();
+        assert_eq!(
+            checkpoint.sent_transactions(),
+            (Some(4), vec![TxHash::repeat_byte(0xcd)])
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn completed_checkpoint_is_recorded_and_removed() {
+        let dir = checkpoints_dir("complete");
+        let base_dir = dir.to_str().unwrap();
+
+        let checkpoint =
+            AggregationCheckpoint::open(base_dir, &ZKVMEngine::SP1, 10, 20, &[[9u8; 32]]).unwrap();
+        checkpoint.complete("0x01".to_string()).unwrap();
+
+        assert!(!dir.join("sp1_10_20").exists());
+        let record: serde_json::Value = serde_json::from_str(
+            std::fs::read_to_string(dir.join(CONSUMED_BATCHES_FILE))
+                .unwrap()
+                .trim(),
+        )
+        .unwrap();
+        assert_eq!(record["engine"], "sp1");
+        assert_eq!(record["from_block"], 10);
+        assert_eq!(record["to_block"], 20);
+        assert_eq!(record["tx_hash"], "0x01");
+        assert_eq!(
+            record["batch_merkle_roots"][0],
+            format!("0x{}", "09".repeat(32))
+        );
+        assert_eq!(
+            AggregationCheckpoint::find_resumable(base_dir, &ZKVMEngine::SP1, 10).unwrap(),
+            None
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,10 @@
+use super::{
+    admission::AdmissionPolicyConfig, checkpoint::write_file_atomically, cost::CostPolicyConfig,
+    manifest::ManifestStorageConfig, submission::SubmissionConfig,
+};
+use crate::aggregators::{ProvingMode, ZKVMEngine};
 use serde::{Deserialize, Serialize};
-use std::{fs::File, fs::OpenOptions, io::Read, io::Write};
+use std::{collections::BTreeMap, fs::File, io::Read, path::Path};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ECDSAConfig {
@@ -7,9 +12,36 @@ pub struct ECDSAConfig {
     pub private_key_store_password: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Version of the last aggregated block file written by this aggregator.
+///
+/// Files without a version were written when the value was the block the next fetch started from,
+/// including it, instead of the last block whose batches were all consumed. They are migrated on read.
+const LAST_AGGREGATED_BLOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct LastAggregatedBlock {
+    #[serde(default)]
+    pub version: u32,
+    /// Lowest last aggregated block among the engines. Used for the engines without
+    /// their own entry, which is the case of files written before tracking it per engine.
     pub last_aggregated_block: u64,
+    /// Last aggregated block of each engine, keyed by engine name.
+    #[serde(default)]
+    pub engines: BTreeMap<String, u64>,
+}
+
+/// Settings of the long running mode. When absent from the config file the
+/// aggregator runs a single cycle and exits.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DaemonConfig {
+    /// Seconds to wait between checks of the triggers below.
+    pub poll_interval_secs: u64,
+    /// Run a cycle once at least this many proofs are waiting to be aggregated.
+    #[serde(default)]
+    pub min_pending_proofs: Option<u16>,
+    /// Run a cycle once at least this many blocks have elapsed since the last aggregated block.
+    #[serde(default)]
+    pub min_elapsed_blocks: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -20,9 +52,72 @@ pub struct Config {
     pub proof_aggregation_service_address: String,
     pub aligned_service_manager_address: String,
     pub last_aggregated_block_filepath: String,
+    /// Directory where the progress of the running aggregation is persisted.
+    /// Defaults to `<last_aggregated_block_filepath>.checkpoints`.
+    #[serde(default)]
+    pub checkpoint_dir: Option<String>,
     pub ecdsa: ECDSAConfig,
     pub proofs_per_chunk: u16,
     pub total_proofs_limit: u16,
+    /// Blocks queried per `eth_getLogs` call when fetching batches.
+    #[serde(default = "default_logs_block_window")]
+    pub logs_block_window: u64,
+    /// Retries of a failed `eth_getLogs` call, with an exponential backoff starting at 1 second, up to 1 minute.
+    #[serde(default = "default_logs_max_retries")]
+    pub logs_max_retries: u32,
+    /// Batches downloaded from S3 at the same time.
+    #[serde(default = "default_max_concurrent_batch_downloads")]
+    pub max_concurrent_batch_downloads: usize,
+    /// Retries of a failed batch download, with an exponential backoff starting at 500 milliseconds.
+    #[serde(default = "default_batch_download_max_retries")]
+    pub batch_download_max_retries: u32,
+    /// `real` or `mock`, defaults to `real` in binaries built with the `prove` feature and to `mock` otherwise.
+    #[serde(default)]
+    pub proving_mode: ProvingMode,
+    /// Which of the fetched proofs are aggregated, all of them when not set.
+    #[serde(default)]
+    pub admission_policy: AdmissionPolicyConfig,
+    /// Defers aggregations too expensive per proof, every aggregation is submitted when not set.
+    #[serde(default)]
+    pub cost_policy: Option<CostPolicyConfig>,
+    /// Receipt timeout and fee bumps of the verification transaction replacements.
+    #[serde(default)]
+    pub submission: SubmissionConfig,
+    /// Storage the manifest of each aggregation is published to, not published when not set.
+    #[serde(default)]
+    pub manifest_storage: Option<ManifestStorageConfig>,
+    /// Port to serve the prometheus metrics in `/metrics` on, not served when not set.
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    /// Directory where a JSON report of each run is written.
+    /// Defaults to `<last_aggregated_block_filepath>.reports`.
+    #[serde(default)]
+    pub reports_dir: Option<String>,
+    /// Run reports kept in `reports_dir`, older ones are removed.
+    #[serde(default = "default_run_reports_retention")]
+    pub run_reports_retention: usize,
+    #[serde(default)]
+    pub daemon: Option<DaemonConfig>,
+}
+
+fn default_logs_block_window() -> u64 {
+    5000
+}
+
+fn default_logs_max_retries() -> u32 {
+    5
+}
+
+fn default_max_concurrent_batch_downloads() -> usize {
+    8
+}
+
+fn default_batch_download_max_retries() -> u32 {
+    3
+}
+
+fn default_run_reports_retention() -> usize {
+    1000
 }
 
 impl Config {
@@ -34,31 +129,236 @@ impl Config {
         Ok(config)
     }
 
-    pub fn get_last_aggregated_block(&self) -> Result<u64, Box<dyn std::error::Error>> {
+    /// Path of the lock file preventing two aggregators from running a cycle
+    /// over the same last aggregated block file at once.
+    pub fn lock_filepath(&self) -> String {
+        format!("{}.lock", self.last_aggregated_block_filepath)
+    }
+
+    pub fn checkpoint_dir(&self) -> String {
+        self.checkpoint_dir
+            .clone()
+            .unwrap_or_else(|| format!("{}.checkpoints", self.last_aggregated_block_filepath))
+    }
+
+    pub fn reports_dir(&self) -> String {
+        self.reports_dir
+            .clone()
+            .unwrap_or_else(|| format!("{}.reports", self.last_aggregated_block_filepath))
+    }
+
+    fn read_last_aggregated_block(
+        &self,
+    ) -> Result<LastAggregatedBlock, Box<dyn std::error::Error>> {
         let mut file = File::open(&self.last_aggregated_block_filepath)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        let lab: LastAggregatedBlock = serde_json::from_str(&contents)?;
-        Ok(lab.last_aggregated_block)
+        let mut lab: LastAggregatedBlock = serde_json::from_str(&contents)?;
+        if lab.version < LAST_AGGREGATED_BLOCK_VERSION {
+            // Its batches may not have been consumed, fetch it again
+            lab.last_aggregated_block = lab.last_aggregated_block.saturating_sub(1);
+            lab.version = LAST_AGGREGATED_BLOCK_VERSION;
+        }
+        Ok(lab)
+    }
+
+    pub fn get_last_aggregated_block(
+        &self,
+        engine: &ZKVMEngine,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let lab = self.read_last_aggregated_block()?;
+        Ok(lab
+            .engines
+            .get(engine.name())
+            .copied()
+            .unwrap_or(lab.last_aggregated_block))
     }
 
     pub fn update_last_aggregated_block(
         &self,
+        engine: &ZKVMEngine,
         last_aggregated_block: u64,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let last_aggregated_block_struct = LastAggregatedBlock {
-            last_aggregated_block,
+        let mut last_aggregated_block_struct = match self.read_last_aggregated_block() {
+            // Engines without their own entry were following the global value,
+            // keep them there instead of moving them along with this engine
+            Ok(mut lab) => {
+                for other in [ZKVMEngine::SP1, ZKVMEngine::RISC0] {
+                    lab.engines
+                        .entry(other.name().to_string())
+                        .or_insert(lab.last_aggregated_block);
+                }
+                lab
+            }
+            Err(_) => LastAggregatedBlock {
+                version: LAST_AGGREGATED_BLOCK_VERSION,
+                ..Default::default()
+            },
         };
-
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(&self.last_aggregated_block_filepath)?;
+        last_aggregated_block_struct
+            .engines
+            .insert(engine.name().to_string(), last_aggregated_block);
+        last_aggregated_block_struct.last_aggregated_block = last_aggregated_block_struct
+            .engines
+            .values()
+            .copied()
+            .min()
+            .unwrap_or(last_aggregated_block);
 
         let content = serde_json::to_string(&last_aggregated_block_struct)?;
-        file.write_all(content.as_bytes())?;
+        write_file_atomically(
+            Path::new(&self.last_aggregated_block_filepath),
+            content.as_bytes(),
+        )?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::Config;
+    use crate::aggregators::ZKVMEngine;
+    use std::path::PathBuf;
+
+    /// A config with only the required fields, keeping its files in a fresh temporary directory.
+    pub(crate) fn test_config(name: &str) -> (Config, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "aligned-aggregator-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let yaml = format!(
+            r#"
+eth_rpc_url: "http://localhost:8545"
+eth_ws_url: "ws://localhost:8545"
+max_proofs_in_queue: 1000
+proof_aggregation_service_address: "0xFD471836031dc5108809D173A067e8486B9047A3"
+aligned_service_manager_address: "0x1613beB3B2C4f22Ee086B2b38C1476A3cE7f78E8"
+last_aggregated_block_filepath: "{}"
+proofs_per_chunk: 512
+total_proofs_limit: 3968
+ecdsa:
+  private_key_store_path: "key.json"
+  private_key_store_password: ""
+"#,
+            dir.join("last_aggregated_block.json").display()
+        );
+        (serde_yaml::from_str(&yaml).unwrap(), dir)
+    }
+
+    #[test]
+    fn engines_last_aggregated_blocks_are_updated_independently() {
+        let (config, dir) = test_config("engines-last-block");
+
+        config
+            .update_last_aggregated_block(&ZKVMEngine::SP1, 100)
+            .unwrap();
+        config
+            .update_last_aggregated_block(&ZKVMEngine::RISC0, 40)
+            .unwrap();
+        config
+            .update_last_aggregated_block(&ZKVMEngine::SP1, 120)
+            .unwrap();
+
+        assert_eq!(
+            config.get_last_aggregated_block(&ZKVMEngine::SP1).unwrap(),
+            120
+        );
+        assert_eq!(
+            config
+                .get_last_aggregated_block(&ZKVMEngine::RISC0)
+                .unwrap(),
+            40
+        );
+        // The global value is the lowest one, so older aggregators don't skip any engine's proofs
+        let lab = config.read_last_aggregated_block().unwrap();
+        assert_eq!(lab.last_aggregated_block, 40);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_last_aggregated_block_is_used_by_every_engine() {
+        let (config, dir) = test_config("legacy-last-block");
+        std::fs::write(
+            &config.last_aggregated_block_filepath,
+            r#"{"version":1,"last_aggregated_block":50}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_last_aggregated_block(&ZKVMEngine::SP1).unwrap(),
+            50
+        );
+        assert_eq!(
+            config
+                .get_last_aggregated_block(&ZKVMEngine::RISC0)
+                .unwrap(),
+            50
+        );
+
+        // Updating an engine keeps the other one on the legacy value
+        config
+            .update_last_aggregated_block(&ZKVMEngine::SP1, 80)
+            .unwrap();
+        assert_eq!(
+            config.get_last_aggregated_block(&ZKVMEngine::SP1).unwrap(),
+            80
+        );
+        assert_eq!(
+            config
+                .get_last_aggregated_block(&ZKVMEngine::RISC0)
+                .unwrap(),
+            50
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unversioned_last_aggregated_block_is_fetched_again() {
+        let (config, dir) = test_config("unversioned-last-block");
+        std::fs::write(
+            &config.last_aggregated_block_filepath,
+            r#"{"last_aggregated_block":50}"#,
+        )
+        .unwrap();
+
+        // The next fetch used to start at block 50, so its batches may not be consumed yet
+        assert_eq!(
+            config.get_last_aggregated_block(&ZKVMEngine::SP1).unwrap(),
+            49
+        );
+
+        config
+            .update_last_aggregated_block(&ZKVMEngine::SP1, 60)
+            .unwrap();
+        let lab = config.read_last_aggregated_block().unwrap();
+        assert_eq!(lab.last_aggregated_block, 49);
+        assert_eq!(
+            config
+                .get_last_aggregated_block(&ZKVMEngine::RISC0)
+                .unwrap(),
+            49
+        );
+        // Written with the current version, so it is not migrated again
+        let content = std::fs::read_to_string(&config.last_aggregated_block_filepath).unwrap();
+        assert!(content.contains(r#""version":1"#));
+
+        std::fs::write(
+            &config.last_aggregated_block_filepath,
+            r#"{"last_aggregated_block":0}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.get_last_aggregated_block(&ZKVMEngine::SP1).unwrap(),
+            0
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
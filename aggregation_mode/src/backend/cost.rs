@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::checkpoint::write_file_atomically;
+use crate::aggregators::ZKVMEngine;
+use alloy::eips::eip4844::DATA_GAS_PER_BLOB;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+const VERIFICATION_GAS_FILE: &str = "verification_gas.json";
+
+/// When submitting an aggregated proof is worth its cost. Without it every aggregation is submitted.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CostPolicyConfig {
+    /// Submit only when the estimated cost per aggregated proof, in wei, is at most this much.
+    pub max_cost_per_proof_wei: u128,
+    /// Submit regardless of the cost once the oldest pending proof is this many blocks old.
+    #[serde(default)]
+    pub max_wait_blocks: Option<u64>,
+    /// Gas of the `verifySP1`/`verifyRisc0` call until a simulation of it gives a better estimate,
+    /// see [`VerificationGasEstimates`].
+    #[serde(default = "default_verification_gas_estimate")]
+    pub verification_gas_estimate: u64,
+}
+
+fn default_verification_gas_estimate() -> u64 {
+    400_000
+}
+
+/// Fees of the latest block.
+#[derive(Debug, Clone, Copy)]
+pub struct NetworkFees {
+    pub base_fee: u128,
+    pub priority_fee: u128,
+    pub blob_base_fee: u128,
+}
+
+/// Cost of sending the verification of an aggregated proof, at the current fees.
+#[derive(Debug, Clone)]
+pub struct SubmissionCost {
+    pub verification_gas: u64,
+    /// Base fee plus priority fee
+    pub gas_price: u128,
+    pub blob_base_fee: u128,
+    pub blobs: usize,
+}
+
+impl SubmissionCost {
+    pub fn total_wei(&self) -> u128 {
+        self.verification_gas as u128 * self.gas_price
+            + self.blobs as u128 * DATA_GAS_PER_BLOB as u128 * self.blob_base_fee
+    }
+
+    pub fn per_proof_wei(&self, proofs: usize) -> u128 {
+        self.total_wei() / proofs.max(1) as u128
+    }
+}
+
+impl CostPolicyConfig {
+    /// Whether to submit `proofs` at `cost`, the oldest of them waiting since `oldest_proof_wait_blocks`.
+    pub fn should_submit(
+        &self,
+        cost: &SubmissionCost,
+        proofs: usize,
+        oldest_proof_wait_blocks: u64,
+    ) -> bool {
+        cost.per_proof_wei(proofs) <= self.max_cost_per_proof_wei
+            || self
+                .max_wait_blocks
+                .is_some_and(|max_wait_blocks| oldest_proof_wait_blocks >= max_wait_blocks)
+    }
+}
+
+/// Gas of the last simulated `verifySP1`/`verifyRisc0` call of each engine, persisted to the checkpoint
+/// directory so aggregators running a single cycle per process estimate the cost with it too.
+pub struct VerificationGasEstimates {
+    filepath: PathBuf,
+    estimates: BTreeMap<String, u64>,
+}
+
+impl VerificationGasEstimates {
+    /// Loads the estimates persisted in `checkpoint_dir`, none if they can't be read.
+    pub fn load(checkpoint_dir: &str) -> Self {
+        let filepath = Path::new(checkpoint_dir).join(VERIFICATION_GAS_FILE);
+        let estimates = match fs::read(&filepath) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|e| {
+                warn!("Ignoring invalid verification gas estimates: {}", e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            filepath,
+            estimates,
+        }
+    }
+
+    pub fn get(&self, engine: &ZKVMEngine) -> Option<u64> {
+        self.estimates.get(engine.name()).copied()
+    }
+
+    pub fn record(&mut self, engine: &ZKVMEngine, verification_gas: u64) -> std::io::Result<()> {
+        self.estimates
+            .insert(engine.name().to_string(), verification_gas);
+        if let Some(dir) = self.filepath.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_file_atomically(&self.filepath, &serde_json::to_vec(&self.estimates)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CostPolicyConfig, SubmissionCost, VerificationGasEstimates};
+    use crate::aggregators::ZKVMEngine;
+    use alloy::eips::eip4844::DATA_GAS_PER_BLOB;
+
+    fn cost(verification_gas: u64, gas_price: u128, blob_base_fee: u128) -> SubmissionCost {
+        SubmissionCost {
+            verification_gas,
+            gas_price,
+            blob_base_fee,
+            blobs: 1,
+        }
+    }
+
+    fn policy(max_cost_per_proof_wei: u128, max_wait_blocks: Option<u64>) -> CostPolicyConfig {
+        CostPolicyConfig {
+            max_cost_per_proof_wei,
+            max_wait_blocks,
+            verification_gas_estimate: 400_000,
+        }
+    }
+
+    #[test]
+    fn cost_includes_the_verification_gas_and_the_blob_gas() {
+        let cost = cost(400_000, 10, 2);
+        assert_eq!(cost.total_wei(), 4_000_000 + DATA_GAS_PER_BLOB as u128 * 2);
+        assert_eq!(cost.per_proof_wei(4), cost.total_wei() / 4);
+        assert_eq!(cost.per_proof_wei(0), cost.total_wei());
+    }
+
+    #[test]
+    fn aggregations_are_submitted_up_to_the_max_cost_per_proof() {
+        let cost = cost(100_000, 1, 0);
+        let policy = policy(1_000, None);
+
+        assert!(policy.should_submit(&cost, 100, 0));
+        assert!(!policy.should_submit(&cost, 99, 0));
+        assert!(!policy.should_submit(&cost, 1, u64::MAX));
+    }
+
+    #[test]
+    fn aggregations_over_the_max_cost_are_submitted_once_the_oldest_proof_waited_enough() {
+        let cost = cost(100_000, 1, 0);
+        let policy = policy(1_000, Some(50));
+
+        assert!(!policy.should_submit(&cost, 1, 49));
+        assert!(policy.should_submit(&cost, 1, 50));
+    }
+
+    #[test]
+    fn verification_gas_estimates_are_persisted_per_engine() {
+        let dir = std::env::temp_dir().join(format!(
+            "aligned-aggregator-verification-gas-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let checkpoint_dir = dir.to_string_lossy().to_string();
+
+        let mut estimates = VerificationGasEstimates::load(&checkpoint_dir);
+        assert_eq!(estimates.get(&ZKVMEngine::SP1), None);
+        estimates.record(&ZKVMEngine::SP1, 350_000).unwrap();
+        estimates.record(&ZKVMEngine::RISC0, 300_000).unwrap();
+        estimates.record(&ZKVMEngine::SP1, 360_000).unwrap();
+
+        let estimates = VerificationGasEstimates::load(&checkpoint_dir);
+        assert_eq!(estimates.get(&ZKVMEngine::SP1), Some(360_000));
+        assert_eq!(estimates.get(&ZKVMEngine::RISC0), Some(300_000));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,39 +1,129 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, path::Path, pin::pin, str::FromStr, time::Duration};
 
 use super::{
+    admission::{ExcludedProof, ExclusionReason},
+    batch_downloader::BatchDownloader,
+    checkpoint::write_file_atomically,
     config::Config,
     types::{AlignedLayerServiceManager, AlignedLayerServiceManagerContract, RPCProvider},
 };
-use crate::{
-    aggregators::{
-        risc0_aggregator::Risc0ProofReceiptAndImageId, sp1_aggregator::SP1ProofWithPubValuesAndElf,
-        AlignedProof, ZKVMEngine,
+use crate::aggregators::{
+    groth16::{
+        groth16_proof_from_verification_data, is_groth16_bn254, AlignedGroth16VerificationError,
     },
-    backend::s3::get_aligned_batch_from_s3,
+    risc0_aggregator::{AlignedRisc0VerificationError, Risc0ProofReceiptAndImageId},
+    sp1_aggregator::{AlignedSP1VerificationError, SP1ProofWithPubValuesAndElf},
+    AlignedProof, ZKVMEngine,
 };
-use aligned_sdk::common::types::ProvingSystemId;
+use aligned_sdk::common::types::{ProvingSystemId, VerificationData};
 use alloy::{
+    hex,
     primitives::Address,
     providers::{Provider, ProviderBuilder},
 };
+use futures_util::{stream, StreamExt};
 use rayon::prelude::*;
 use risc0_zkvm::Receipt;
-use tracing::{error, info};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
 #[derive(Debug)]
 pub enum ProofsFetcherError {
     GetLogs(String),
     GetBlockNumber(String),
+    /// The proofs of a single block, holds its number, exceed the proofs limit.
+    /// Its batches can't be partially taken, so the limit has to be raised.
+    BlockExceedsLimit(u64),
 }
 
+const LOGS_RETRY_BASE_DELAY_MS: u64 = 1000;
+const LOGS_RETRY_MAX_DELAY_MS: u64 = 60_000;
+
+/// Fetches the proofs of one engine, keeping track of the last block it aggregated.
 pub struct ProofsFetcher {
+    engine: ZKVMEngine,
     rpc_provider: RPCProvider,
     aligned_service_manager: AlignedLayerServiceManagerContract,
     last_aggregated_block: u64,
+    /// Block and merkle root of the batches processed by the last fetch.
+    consumed_batches: Vec<(u64, [u8; 32])>,
+    logs_block_window: u64,
+    logs_max_retries: u32,
+    logs_scan_progress_filepath: String,
+    batch_downloader: BatchDownloader,
+    /// Batches skipped by the last fetch.
+    skipped_batches: Vec<SkippedBatch>,
+    /// Block and reason of the proofs of the engine left out by the last fetch, as they can't be aggregated,
+    /// and of the ones the admission policy excluded from the proofs kept by [`ProofsFetcher::keep_pending`].
+    excluded_proofs: Vec<(u64, ExclusionReason)>,
+    /// Blocks a batch has to be deep to be fetched, see [`AdmissionPolicyConfig`](super::admission::AdmissionPolicyConfig)
+    min_proof_age_blocks: u64,
+    /// Proofs of a fetch whose cycle was skipped, see [`ProofsFetcher::keep_pending`]
+    pending: Option<PendingProofs>,
+}
+
+/// Proofs fetched from the blocks after `from_block` up to `to_block`, along with the batches they come from.
+struct PendingProofs {
+    from_block: u64,
+    to_block: u64,
+    proofs: Vec<FetchedProof>,
+    consumed_batches: Vec<(u64, [u8; 32])>,
+    skipped_batches: Vec<SkippedBatch>,
+    excluded_proofs: Vec<(u64, ExclusionReason)>,
+}
+
+/// A `NewBatchV3` log found while scanning.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ScannedBatch {
+    block_number: u64,
+    batch_merkle_root: [u8; 32],
+    data_pointer: String,
+}
+
+/// Batches found scanning the logs from `from_block` up to `scanned_to_block`.
+#[derive(Debug, Serialize, Deserialize)]
+struct LogsScanProgress {
+    from_block: u64,
+    scanned_to_block: u64,
+    batches: Vec<ScannedBatch>,
+}
+
+/// A proof to aggregate, along with where it comes from.
+pub struct FetchedProof {
+    pub proof: AlignedProof,
+    /// Address of the proof generator that submitted the proof
+    pub submitter: [u8; 20],
+    /// Block of the batch the proof was submitted in
+    pub block_number: u64,
+}
+
+/// Proofs taken by a fetch and where the block being taken starts.
+#[derive(Default)]
+struct FetchedProofs {
+    proofs: Vec<FetchedProof>,
+    block: Option<u64>,
+    block_proofs_start: usize,
+    block_batches_start: usize,
+}
+
+impl FetchedProofs {
+    /// Drops the proofs and batches taken from the current block.
+    fn drop_block(&mut self, consumed_batches: &mut Vec<(u64, [u8; 32])>) {
+        self.proofs.truncate(self.block_proofs_start);
+        consumed_batches.truncate(self.block_batches_start);
+    }
+}
+
+/// A batch left out of aggregation because it can't be downloaded or its data is invalid.
+#[derive(Debug, Clone)]
+pub struct SkippedBatch {
+    pub block_number: u64,
+    pub batch_merkle_root: [u8; 32],
+    pub reason: String,
 }
 
 impl ProofsFetcher {
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config, engine: ZKVMEngine) -> Self {
         let rpc_url = config.eth_rpc_url.parse().expect("RPC URL should be valid");
         let rpc_provider = ProviderBuilder::new().connect_http(rpc_url);
         let aligned_service_manager = AlignedLayerServiceManager::new(
@@ -42,65 +132,223 @@ impl ProofsFetcher {
             rpc_provider.clone(),
         );
 
-        let last_aggregated_block = config.get_last_aggregated_block().unwrap();
+        let last_aggregated_block = config.get_last_aggregated_block(&engine).unwrap();
+        let logs_scan_progress_filepath = format!(
+            "{}/{}_logs_scan.json",
+            config.checkpoint_dir(),
+            engine.name()
+        );
 
         Self {
+            engine,
             rpc_provider,
             aligned_service_manager,
             last_aggregated_block,
+            consumed_batches: vec![],
+            logs_block_window: config.logs_block_window,
+            logs_max_retries: config.logs_max_retries,
+            logs_scan_progress_filepath,
+            batch_downloader: BatchDownloader::new(
+                format!("{}/batches", config.checkpoint_dir()),
+                config.max_concurrent_batch_downloads,
+                config.batch_download_max_retries,
+            ),
+            skipped_batches: vec![],
+            excluded_proofs: vec![],
+            min_proof_age_blocks: config.admission_policy.min_proof_age_blocks.unwrap_or(0),
+            pending: None,
         }
     }
 
     /// Retrieves batches from the aligned fast mode since the last processed block,
-    /// filtering for proofs compatible with the specified zkVM engine.
+    /// filtering for proofs compatible with the fetcher zkVM engine.
+    ///
+    /// Batches are fetched up to `up_to_block` when given, or up to the current block otherwise.
+    /// Fixing the upper block lets a checkpointed aggregation fetch the exact same proofs again.
+    ///
+    /// Logs are queried in windows of `logs_block_window` blocks, the batches found are persisted
+    /// after each window so a fetch starting at the same block doesn't query them again.
+    ///
+    /// Proofs kept by [`ProofsFetcher::keep_pending`] for the same last aggregated block are not
+    /// downloaded nor verified again, the fetch continues from the block after them.
     pub async fn fetch(
         &mut self,
-        engine: ZKVMEngine,
         limit: u16,
-    ) -> Result<Vec<AlignedProof>, ProofsFetcherError> {
+        up_to_block: Option<u64>,
+    ) -> Result<Vec<FetchedProof>, ProofsFetcherError> {
+        let pending = self.pending.take().filter(|pending| {
+            up_to_block.is_none() && pending.from_block == self.last_aggregated_block
+        });
+        let mut fetched = FetchedProofs::default();
+        let fetched_up_to_block = match pending {
+            Some(pending) => {
+                info!(
+                    "Continuing from {} pending {} proofs fetched up to block {}",
+                    pending.proofs.len(),
+                    self.engine,
+                    pending.to_block
+                );
+                fetched.proofs = pending.proofs;
+                self.consumed_batches = pending.consumed_batches;
+                self.skipped_batches = pending.skipped_batches;
+                self.excluded_proofs = pending.excluded_proofs;
+                pending.to_block
+            }
+            None => {
+                self.consumed_batches.clear();
+                self.skipped_batches.clear();
+                self.excluded_proofs.clear();
+                self.last_aggregated_block
+            }
+        };
+
         // Get current block
-        let current_block = self
-            .rpc_provider
-            .get_block_number()
-            .await
-            .map_err(|e| ProofsFetcherError::GetBlockNumber(e.to_string()))?;
+        let current_block = match up_to_block {
+            Some(block) => block,
+            None => {
+                let current_block = self
+                    .rpc_provider
+                    .get_block_number()
+                    .await
+                    .map_err(|e| ProofsFetcherError::GetBlockNumber(e.to_string()))?;
+                if current_block < self.last_aggregated_block {
+                    return Err(ProofsFetcherError::GetBlockNumber(
+                        "Invalid last processed block".to_string(),
+                    ));
+                }
+                // Batches younger than the minimum age are left for a later fetch
+                current_block.saturating_sub(self.min_proof_age_blocks)
+            }
+        };
 
-        if current_block < self.last_aggregated_block {
-            return Err(ProofsFetcherError::GetBlockNumber(
-                "Invalid last processed block".to_string(),
-            ));
+        // The last aggregated block had all its batches consumed
+        let from_block = self.last_aggregated_block + 1;
+        self.last_aggregated_block = fetched_up_to_block;
+        if fetched_up_to_block >= current_block {
+            info!("No new blocks to fetch {} proofs from", self.engine);
+            return Ok(fetched.proofs);
         }
 
         info!(
-            "Fetching proofs from batch logs starting from block number {} upto {}",
-            self.last_aggregated_block, current_block
+            "Fetching {} proofs from batch logs starting from block number {} upto {}",
+            self.engine, from_block, current_block
         );
 
-        // Subscribe to NewBatch event from AlignedServiceManager
-        let logs = self
-            .aligned_service_manager
-            .NewBatchV3_filter()
-            .from_block(self.last_aggregated_block)
-            .to_block(current_block)
-            .query()
-            .await
-            .map_err(|e| ProofsFetcherError::GetLogs(e.to_string()))?;
+        let mut scan = self.load_logs_scan_progress(from_block);
+        // The pending proofs were fetched from a scan that is no longer persisted
+        if scan.scanned_to_block < fetched_up_to_block {
+            scan = LogsScanProgress {
+                from_block: fetched_up_to_block + 1,
+                scanned_to_block: fetched_up_to_block,
+                batches: vec![],
+            };
+        }
 
-        info!("Logs collected {}", logs.len());
+        // Batches found by a previous fetch starting at the same block
+        let scanned_batches: Vec<ScannedBatch> = scan
+            .batches
+            .iter()
+            .filter(|batch| {
+                batch.block_number > fetched_up_to_block && batch.block_number <= current_block
+            })
+            .cloned()
+            .collect();
+        if !scanned_batches.is_empty() {
+            info!(
+                "Resuming from {} batches found up to block {}",
+                scanned_batches.len(),
+                scan.scanned_to_block
+            );
+        }
+        if self
+            .add_batches(&scanned_batches, &mut fetched, limit)
+            .await?
+        {
+            return Ok(fetched.proofs);
+        }
 
-        let mut proofs = vec![];
+        let window = self.logs_block_window.max(1);
+        let mut window_start = scan.scanned_to_block.min(current_block) + 1;
+        while window_start <= current_block {
+            let window_end = window_start.saturating_add(window - 1).min(current_block);
+            let batches = self.get_batches(window_start, window_end).await?;
+            info!(
+                "Logs collected {} from block {} to {}",
+                batches.len(),
+                window_start,
+                window_end
+            );
+
+            scan.scanned_to_block = window_end;
+            scan.batches.extend(batches.iter().cloned());
+            self.save_logs_scan_progress(&scan);
+
+            if self.add_batches(&batches, &mut fetched, limit).await? {
+                return Ok(fetched.proofs);
+            }
+
+            window_start = window_end + 1;
+        }
+
+        // Update last processed block after collecting logs
+        self.last_aggregated_block = current_block;
 
-        for (batch, log) in logs {
+        Ok(fetched.proofs)
+    }
+
+    /// Adds the compatible proofs of `batches` to `fetched` as long as they fit in the `limit`.
+    ///
+    /// Proofs are taken by whole blocks. When a batch doesn't fit, the batches of its block already taken
+    /// are dropped and the last aggregated block is set to the block before, so the next fetch starts
+    /// at the first batch left out. Returns whether the fetch has to stop.
+    ///
+    /// Batches are downloaded as they are taken, so the ones after the limit are not downloaded.
+    /// Batches that can't be aggregated, because they can't be downloaded or their data doesn't match
+    /// their merkle root, are skipped and recorded in [`ProofsFetcher::skipped_batches`].
+    async fn add_batches(
+        &mut self,
+        batches: &[ScannedBatch],
+        fetched: &mut FetchedProofs,
+        limit: u16,
+    ) -> Result<bool, ProofsFetcherError> {
+        let to_download: Vec<(u64, String, [u8; 32])> = batches
+            .iter()
+            .map(|batch| {
+                (
+                    batch.block_number,
+                    batch.data_pointer.clone(),
+                    batch.batch_merkle_root,
+                )
+            })
+            .collect();
+        let mut downloads =
+            pin!(stream::iter(batches).zip(self.batch_downloader.download(&to_download)));
+
+        while let Some((batch, download)) = downloads.next().await {
+            if fetched.block != Some(batch.block_number) {
+                fetched.block = Some(batch.block_number);
+                fetched.block_proofs_start = fetched.proofs.len();
+                fetched.block_batches_start = self.consumed_batches.len();
+            }
+
+            let batch_merkle_root = format!("0x{}", hex::encode(batch.batch_merkle_root));
             info!(
                 "New batch submitted, about to process. Batch merkle root {}...",
-                batch.batchMerkleRoot
+                batch_merkle_root
             );
 
-            // Download batch proofs from s3
-            let data = match get_aligned_batch_from_s3(batch.batchDataPointer).await {
+            let data = match download {
                 Ok(data) => data,
                 Err(err) => {
-                    error!("Error while downloading proofs from s3. Err {:?}", err);
+                    error!(
+                        "Skipping batch {} of block {}, its proofs won't be aggregated. Err {:?}",
+                        batch_merkle_root, batch.block_number, err
+                    );
+                    self.skipped_batches.push(SkippedBatch {
+                        block_number: batch.block_number,
+                        batch_merkle_root: batch.batch_merkle_root,
+                        reason: format!("{:?}", err),
+                    });
                     continue;
                 }
             };
@@ -108,83 +356,620 @@ impl ProofsFetcher {
             info!("Data downloaded from S3, number of proofs {}", data.len());
 
             // Filter compatible proofs to be aggregated and push to queue
-            let proofs_to_add: Vec<AlignedProof> = match engine {
-                ZKVMEngine::SP1 => data
-                    .into_par_iter()
-                    .filter_map(|p| {
-                        if p.proving_system != ProvingSystemId::SP1 {
-                            return None;
-                        };
-
-                        let elf = p.vm_program_code?;
-                        let proof_with_pub_values = bincode::deserialize(&p.proof).ok()?;
-                        let sp1_proof =
-                            SP1ProofWithPubValuesAndElf::new(proof_with_pub_values, elf);
-
-                        match sp1_proof {
-                            Ok(proof) => Some(AlignedProof::SP1(proof.into())),
-                            Err(err) => {
-                                error!("Could not add proof, verification failed: {:?}", err);
-                                None
-                            }
-                        }
-                    })
-                    .collect(),
-                ZKVMEngine::RISC0 => data
-                    .into_par_iter()
-                    .filter_map(|p| {
-                        if p.proving_system != ProvingSystemId::Risc0 {
-                            return None;
-                        };
-
-                        let mut image_id = [0u8; 32];
-                        image_id.copy_from_slice(p.vm_program_code?.as_slice());
-                        let public_inputs = p.pub_input?;
-                        let inner_receipt: risc0_zkvm::InnerReceipt =
-                            bincode::deserialize(&p.proof).ok()?;
-
-                        let receipt = Receipt::new(inner_receipt, public_inputs);
-                        let risc0_proof = Risc0ProofReceiptAndImageId::new(image_id, receipt);
-
-                        match risc0_proof {
-                            Ok(proof) => Some(AlignedProof::Risc0(proof.into())),
-                            Err(err) => {
-                                error!("Could not add proof, verification failed: {:?}", err);
-                                None
-                            }
-                        }
-                    })
-                    .collect(),
-            };
+            let (proofs_to_add, excluded) = self.filter_compatible_proofs(data, batch.block_number);
 
             info!(
                 "{} Proofs filtered, compatible proofs found {}",
-                engine,
+                self.engine,
                 proofs_to_add.len()
             );
 
-            if (proofs.len() + proofs_to_add.len()) > (limit as usize) {
-                let log_block_number = log.block_number.unwrap();
+            if (fetched.proofs.len() + proofs_to_add.len()) > (limit as usize) {
+                if fetched.block_proofs_start == 0 {
+                    return Err(ProofsFetcherError::BlockExceedsLimit(batch.block_number));
+                }
+
                 info!(
-                    "Limit of {} proofs reached, stopping at block number {}, which is {} from current block",
-                    limit, log_block_number, current_block - log_block_number
+                    "Limit of {} proofs reached, stopping before block number {}",
+                    limit, batch.block_number
                 );
-                // Update last processed block to this log block number
-                // So the next aggregation starts at this block
-                self.last_aggregated_block = log_block_number;
-                return Ok(proofs);
+                fetched.drop_block(&mut self.consumed_batches);
+                self.skipped_batches
+                    .retain(|skipped| skipped.block_number < batch.block_number);
+                self.excluded_proofs
+                    .retain(|(block_number, _)| *block_number < batch.block_number);
+                self.last_aggregated_block = batch.block_number - 1;
+                return Ok(true);
             }
 
-            proofs.extend(proofs_to_add);
+            fetched.proofs.extend(proofs_to_add);
+            self.excluded_proofs.extend(
+                excluded
+                    .into_iter()
+                    .map(|reason| (batch.block_number, reason)),
+            );
+            self.consumed_batches
+                .push((batch.block_number, batch.batch_merkle_root));
         }
 
-        // Update last processed block after collecting logs
-        self.last_aggregated_block = current_block;
+        Ok(false)
+    }
+
+    /// Queries the `NewBatchV3` logs between both blocks (inclusive), retrying with
+    /// an exponential backoff as RPC providers often fail or rate limit `eth_getLogs`.
+    async fn get_batches(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<ScannedBatch>, ProofsFetcherError> {
+        let mut retries = 0;
+        let logs = loop {
+            let res = self
+                .aligned_service_manager
+                .NewBatchV3_filter()
+                .from_block(from_block)
+                .to_block(to_block)
+                .query()
+                .await;
+
+            match res {
+                Ok(logs) => break logs,
+                Err(e) if retries < self.logs_max_retries => {
+                    let delay = logs_retry_delay(retries);
+                    warn!(
+                        "Querying logs from block {} to {} failed, retrying in {:?}: {}",
+                        from_block, to_block, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                Err(e) => return Err(ProofsFetcherError::GetLogs(e.to_string())),
+            }
+        };
+
+        logs.into_iter()
+            .map(|(batch, log)| {
+                Ok(ScannedBatch {
+                    block_number: log.block_number.ok_or_else(|| {
+                        ProofsFetcherError::GetLogs("Log without block number".to_string())
+                    })?,
+                    batch_merkle_root: batch.batchMerkleRoot.0,
+                    data_pointer: batch.batchDataPointer,
+                })
+            })
+            .collect()
+    }
+
+    fn load_logs_scan_progress(&self, from_block: u64) -> LogsScanProgress {
+        let progress = std::fs::read(&self.logs_scan_progress_filepath)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<LogsScanProgress>(&content).ok());
+
+        match progress {
+            // Also covers a previous fetch that stopped at the proofs limit before `from_block`,
+            // the batches it scanned from there on are still valid
+            Some(mut progress)
+                if progress.from_block <= from_block
+                    && from_block <= progress.scanned_to_block + 1 =>
+            {
+                progress.from_block = from_block;
+                progress
+                    .batches
+                    .retain(|batch| batch.block_number >= from_block);
+                progress
+            }
+            _ => LogsScanProgress {
+                from_block,
+                scanned_to_block: from_block - 1,
+                batches: vec![],
+            },
+        }
+    }
+
+    /// Progress is an optimization, failing to persist it only means scanning again
+    fn save_logs_scan_progress(&self, progress: &LogsScanProgress) {
+        let res = serde_json::to_vec(progress)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(dir) = Path::new(&self.logs_scan_progress_filepath).parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                write_file_atomically(Path::new(&self.logs_scan_progress_filepath), &content)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = res {
+            warn!("Could not persist logs scan progress: {}", e);
+        }
+    }
 
-        Ok(proofs)
+    /// Verifies the proofs of a batch, keeping the ones the fetcher engine can aggregate.
+    /// Returns them along with why the other proofs of the engine can't be aggregated.
+    fn filter_compatible_proofs(
+        &self,
+        data: Vec<VerificationData>,
+        block_number: u64,
+    ) -> (Vec<FetchedProof>, Vec<ExclusionReason>) {
+        let engine = &self.engine;
+        let results: Vec<Result<FetchedProof, ExclusionReason>> = data
+            .into_par_iter()
+            .filter_map(|p| {
+                let submitter = p.proof_generator_addr.0;
+                let proof = match engine {
+                    ZKVMEngine::SP1 => sp1_compatible_proof(p),
+                    ZKVMEngine::RISC0 => risc0_compatible_proof(p),
+                }?;
+
+                Some(proof.map(|proof| FetchedProof {
+                    proof,
+                    submitter,
+                    block_number,
+                }))
+            })
+            .collect();
+
+        let mut proofs = vec![];
+        let mut excluded = vec![];
+        for result in results {
+            match result {
+                Ok(proof) => proofs.push(proof),
+                Err(reason) => excluded.push(reason),
+            }
+        }
+        (proofs, excluded)
     }
 
     pub fn get_last_aggregated_block(&self) -> u64 {
         self.last_aggregated_block
     }
+
+    /// Keeps the proofs of the last fetch, which started after `from_block`, when its cycle is skipped.
+    /// The next fetch from the same block takes them instead of downloading and verifying their
+    /// batches again, so polling for pending proofs only processes the new blocks.
+    ///
+    /// The proofs `excluded` by the admission policy are not kept, but still reported by the next fetch.
+    pub fn keep_pending(
+        &mut self,
+        from_block: u64,
+        proofs: Vec<FetchedProof>,
+        excluded: &[ExcludedProof],
+    ) {
+        self.excluded_proofs.extend(
+            excluded
+                .iter()
+                .map(|excluded| (excluded.block_number, excluded.reason)),
+        );
+        self.pending = Some(PendingProofs {
+            from_block,
+            to_block: self.last_aggregated_block,
+            proofs,
+            consumed_batches: self.consumed_batches.clone(),
+            skipped_batches: self.skipped_batches.clone(),
+            excluded_proofs: self.excluded_proofs.clone(),
+        });
+    }
+
+    /// Rolls the fetcher back to `block`, so the proofs fetched after it are fetched again
+    /// in the next cycle. Used when a cycle fails or is skipped after fetching.
+    pub fn set_last_aggregated_block(&mut self, block: u64) {
+        self.last_aggregated_block = block;
+    }
+
+    /// Leaves the batches of the last fetch from `block` on for the next cycle, as if the fetch
+    /// stopped before it. Their proofs have to be dropped from the fetched ones by the caller.
+    pub fn leave_blocks_from(&mut self, block: u64) {
+        self.consumed_batches
+            .retain(|(block_number, _)| *block_number < block);
+        self.skipped_batches
+            .retain(|batch| batch.block_number < block);
+        self.excluded_proofs
+            .retain(|(block_number, _)| *block_number < block);
+        self.last_aggregated_block = self.last_aggregated_block.min(block - 1);
+    }
+
+    /// Number of blocks elapsed since the last aggregated block.
+    pub async fn elapsed_blocks(&self) -> Result<u64, ProofsFetcherError> {
+        let current_block = self
+            .rpc_provider
+            .get_block_number()
+            .await
+            .map_err(|e| ProofsFetcherError::GetBlockNumber(e.to_string()))?;
+
+        Ok(current_block.saturating_sub(self.last_aggregated_block))
+    }
+
+    pub fn consumed_batch_merkle_roots(&self) -> Vec<[u8; 32]> {
+        self.consumed_batches
+            .iter()
+            .map(|(_, batch_merkle_root)| *batch_merkle_root)
+            .collect()
+    }
+
+    pub fn skipped_batches(&self) -> &[SkippedBatch] {
+        &self.skipped_batches
+    }
+
+    /// Proofs of the engine the last fetch left out, by reason.
+    pub fn excluded_proofs_by_reason(&self) -> BTreeMap<&'static str, usize> {
+        let mut excluded_by_reason = BTreeMap::new();
+        for (_, reason) in &self.excluded_proofs {
+            *excluded_by_reason.entry(reason.name()).or_default() += 1;
+        }
+        excluded_by_reason
+    }
+
+    /// Removes the batches up to `block` from the downloads cache, shared by the fetchers of all the engines.
+    pub fn evict_cached_batches_up_to(&self, block: u64) {
+        self.batch_downloader.evict_up_to(block);
+    }
+
+    pub fn engine(&self) -> &ZKVMEngine {
+        &self.engine
+    }
+}
+
+/// Exponential backoff of the `eth_getLogs` retries, capped so a large `logs_max_retries` doesn't overflow.
+fn logs_retry_delay(retries: u32) -> Duration {
+    let delay_ms = 2u64
+        .checked_pow(retries)
+        .and_then(|factor| LOGS_RETRY_BASE_DELAY_MS.checked_mul(factor))
+        .unwrap_or(u64::MAX)
+        .min(LOGS_RETRY_MAX_DELAY_MS);
+    Duration::from_millis(delay_ms)
+}
+
+/// SP1 compressed and Groth16 BN254 proofs are aggregated by the SP1 aggregator.
+/// Returns `None` for proofs of other engines.
+fn sp1_compatible_proof(p: VerificationData) -> Option<Result<AlignedProof, ExclusionReason>> {
+    if is_groth16_bn254(p.proving_system) {
+        return Some(match groth16_proof_from_verification_data(p) {
+            Ok(proof) => Ok(AlignedProof::Groth16(proof.into())),
+            Err(err) => {
+                error!(
+                    "Could not add Groth16 proof, verification failed: {:?}",
+                    err
+                );
+                Err(match err {
+                    AlignedGroth16VerificationError::MissingData => {
+                        ExclusionReason::InvalidProofData
+                    }
+                    AlignedGroth16VerificationError::UnsupportedProvingSystem(_) => {
+                        ExclusionReason::UnsupportedProof
+                    }
+                    AlignedGroth16VerificationError::Verification(_) => {
+                        ExclusionReason::VerificationFailed
+                    }
+                })
+            }
+        });
+    }
+
+    if p.proving_system != ProvingSystemId::SP1 {
+        return None;
+    };
+
+    let (Some(elf), Ok(proof_with_pub_values)) =
+        (p.vm_program_code, bincode::deserialize(&p.proof))
+    else {
+        error!("Could not add SP1 proof, its elf is missing or the proof can't be deserialized");
+        return Some(Err(ExclusionReason::InvalidProofData));
+    };
+    let sp1_proof = SP1ProofWithPubValuesAndElf::new(proof_with_pub_values, elf);
+
+    Some(match sp1_proof {
+        Ok(proof) => Ok(AlignedProof::SP1(proof.into())),
+        Err(err) => {
+            error!("Could not add proof, verification failed: {:?}", err);
+            Err(match err {
+                AlignedSP1VerificationError::UnsupportedProof => ExclusionReason::UnsupportedProof,
+                AlignedSP1VerificationError::Verification(_) => ExclusionReason::VerificationFailed,
+            })
+        }
+    })
+}
+
+/// Returns `None` for proofs of other engines.
+fn risc0_compatible_proof(p: VerificationData) -> Option<Result<AlignedProof, ExclusionReason>> {
+    if p.proving_system != ProvingSystemId::Risc0 {
+        return None;
+    };
+
+    let image_id: Option<[u8; 32]> = p
+        .vm_program_code
+        .and_then(|image_id| image_id.try_into().ok());
+    let inner_receipt: Option<risc0_zkvm::InnerReceipt> = bincode::deserialize(&p.proof).ok();
+    let (Some(image_id), Some(public_inputs), Some(inner_receipt)) =
+        (image_id, p.pub_input, inner_receipt)
+    else {
+        error!("Could not add Risc0 proof, its image id or public inputs are missing or the proof can't be deserialized");
+        return Some(Err(ExclusionReason::InvalidProofData));
+    };
+
+    let receipt = Receipt::new(inner_receipt, public_inputs);
+    let risc0_proof = Risc0ProofReceiptAndImageId::new(image_id, receipt);
+
+    Some(match risc0_proof {
+        Ok(proof) => Ok(AlignedProof::Risc0(proof.into())),
+        Err(AlignedRisc0VerificationError::UnsupportedReceiptKind(kind)) => {
+            warn!(
+                "Skipping Risc0 proof with {} receipt, only composite and succinct receipts can be aggregated",
+                kind
+            );
+            Err(ExclusionReason::UnsupportedProof)
+        }
+        Err(err) => {
+            error!("Could not add proof, verification failed: {:?}", err);
+            Err(ExclusionReason::VerificationFailed)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FetchedProofs, LogsScanProgress, ProofsFetcher, ScannedBatch};
+    use crate::{aggregators::ZKVMEngine, backend::config::test::test_config};
+    use aligned_sdk::common::types::{
+        ProvingSystemId, VerificationCommitmentBatch, VerificationData, VerificationDataCommitment,
+    };
+    use alloy::{hex, primitives::Address};
+    use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    const RISC0_PROOF_PATH: &str =
+        "../scripts/test_files/risc_zero/fibonacci_proof_generator/risc_zero_fibonacci_2_2_0.proof";
+    const RISC0_IMAGE_ID_PATH: &str =
+        "../scripts/test_files/risc_zero/fibonacci_proof_generator/fibonacci_id_2_2_0.bin";
+    const RISC0_PUB_INPUT_PATH: &str =
+        "../scripts/test_files/risc_zero/fibonacci_proof_generator/risc_zero_fibonacci_2_2_0.pub";
+
+    fn risc0_fetcher(name: &str) -> (ProofsFetcher, PathBuf) {
+        let (mut config, dir) = test_config(name);
+        config.batch_download_max_retries = 0;
+        std::fs::write(
+            &config.last_aggregated_block_filepath,
+            r#"{"version":1,"last_aggregated_block":9}"#,
+        )
+        .unwrap();
+        (ProofsFetcher::new(&config, ZKVMEngine::RISC0), dir)
+    }
+
+    /// A batch with a Risc0 proof of `submitter`, already in the downloads cache of the fetcher in `dir`.
+    fn cached_batch(dir: &std::path::Path, block_number: u64, submitter: u8) -> ScannedBatch {
+        cache_batch(dir, block_number, vec![risc0_verification_data(submitter)])
+    }
+
+    fn risc0_verification_data(submitter: u8) -> VerificationData {
+        VerificationData {
+            proving_system: ProvingSystemId::Risc0,
+            proof: std::fs::read(RISC0_PROOF_PATH).unwrap(),
+            pub_input: Some(std::fs::read(RISC0_PUB_INPUT_PATH).unwrap()),
+            verification_key: None,
+            vm_program_code: Some(std::fs::read(RISC0_IMAGE_ID_PATH).unwrap()),
+            proof_generator_addr: Address::repeat_byte(submitter),
+        }
+    }
+
+    /// A batch with `data`, already in the downloads cache of the fetcher in `dir`.
+    fn cache_batch(
+        dir: &std::path::Path,
+        block_number: u64,
+        data: Vec<VerificationData>,
+    ) -> ScannedBatch {
+        let commitments: Vec<VerificationDataCommitment> =
+            data.iter().cloned().map(Into::into).collect();
+        let batch_merkle_root = MerkleTree::<VerificationCommitmentBatch>::build(&commitments)
+            .unwrap()
+            .root;
+
+        let mut bytes = vec![];
+        ciborium::into_writer(&data, &mut bytes).unwrap();
+        let cache_dir = dir.join("last_aggregated_block.json.checkpoints/batches");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        std::fs::write(
+            cache_dir.join(format!(
+                "{}_0x{}.cbor",
+                block_number,
+                hex::encode(batch_merkle_root)
+            )),
+            bytes,
+        )
+        .unwrap();
+
+        ScannedBatch {
+            block_number,
+            batch_merkle_root,
+            data_pointer: "http://127.0.0.1:9/not_cached".to_string(),
+        }
+    }
+
+    fn not_downloadable_batch(block_number: u64) -> ScannedBatch {
+        ScannedBatch {
+            block_number,
+            batch_merkle_root: [0xee; 32],
+            data_pointer: "http://127.0.0.1:9/not_cached".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_batches_takes_whole_blocks_up_to_the_limit() {
+        let (mut fetcher, dir) = risc0_fetcher("fetcher-limit");
+        let batches = vec![
+            cached_batch(&dir, 10, 1),
+            cached_batch(&dir, 11, 2),
+            cached_batch(&dir, 11, 3),
+        ];
+
+        // The second batch of block 11 doesn't fit, so the first one is left out too
+        let mut fetched = FetchedProofs::default();
+        assert!(fetcher
+            .add_batches(&batches, &mut fetched, 2)
+            .await
+            .unwrap());
+        assert_eq!(fetched.proofs.len(), 1);
+        assert_eq!(fetched.proofs[0].block_number, 10);
+        assert_eq!(fetched.proofs[0].submitter, [1; 20]);
+        assert_eq!(
+            fetcher.consumed_batch_merkle_roots(),
+            &[batches[0].batch_merkle_root]
+        );
+        assert_eq!(fetcher.get_last_aggregated_block(), 10);
+
+        fetcher.consumed_batches.clear();
+        let mut fetched = FetchedProofs::default();
+        assert!(!fetcher
+            .add_batches(&batches, &mut fetched, 3)
+            .await
+            .unwrap());
+        assert_eq!(fetched.proofs.len(), 3);
+        assert_eq!(fetcher.consumed_batch_merkle_roots().len(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_batches_skips_a_batch_that_cant_be_downloaded() {
+        let (mut fetcher, dir) = risc0_fetcher("fetcher-download");
+        let batches = vec![
+            cached_batch(&dir, 10, 1),
+            not_downloadable_batch(11),
+            cached_batch(&dir, 11, 2),
+        ];
+
+        let mut fetched = FetchedProofs::default();
+        assert!(!fetcher
+            .add_batches(&batches, &mut fetched, 100)
+            .await
+            .unwrap());
+        assert_eq!(fetched.proofs.len(), 2);
+        assert_eq!(
+            fetcher.consumed_batch_merkle_roots(),
+            &[batches[0].batch_merkle_root, batches[2].batch_merkle_root]
+        );
+        assert_eq!(fetcher.skipped_batches().len(), 1);
+        assert_eq!(fetcher.skipped_batches()[0].block_number, 11);
+        assert_eq!(fetcher.skipped_batches()[0].batch_merkle_root, [0xee; 32]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn proofs_that_cant_be_aggregated_are_excluded_by_reason() {
+        let (mut fetcher, dir) = risc0_fetcher("fetcher-excluded");
+        let mut no_image_id = risc0_verification_data(2);
+        no_image_id.vm_program_code = None;
+        let mut other_image_id = risc0_verification_data(3);
+        other_image_id.vm_program_code = Some(vec![0; 32]);
+        // Proofs of other engines are not excluded, they are left to their fetcher
+        let mut sp1 = risc0_verification_data(4);
+        sp1.proving_system = ProvingSystemId::SP1;
+        let batches = vec![cache_batch(
+            &dir,
+            10,
+            vec![risc0_verification_data(1), no_image_id, other_image_id, sp1],
+        )];
+
+        let mut fetched = FetchedProofs::default();
+        assert!(!fetcher
+            .add_batches(&batches, &mut fetched, 100)
+            .await
+            .unwrap());
+        assert_eq!(fetched.proofs.len(), 1);
+        assert_eq!(fetched.proofs[0].submitter, [1; 20]);
+        assert_eq!(
+            fetcher.excluded_proofs_by_reason(),
+            BTreeMap::from([("invalid_proof_data", 1), ("verification_failed", 1)])
+        );
+
+        // Excluded proofs of a block left for the next cycle are reported by that cycle
+        fetcher.leave_blocks_from(10);
+        assert!(fetcher.excluded_proofs_by_reason().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn blocks_left_for_the_next_cycle_are_not_consumed() {
+        let (mut fetcher, dir) = risc0_fetcher("fetcher-leave");
+        let batches = vec![
+            cached_batch(&dir, 10, 1),
+            cached_batch(&dir, 11, 2),
+            not_downloadable_batch(12),
+            cached_batch(&dir, 12, 3),
+        ];
+
+        let mut fetched = FetchedProofs::default();
+        assert!(!fetcher
+            .add_batches(&batches, &mut fetched, 100)
+            .await
+            .unwrap());
+        fetcher.set_last_aggregated_block(12);
+
+        fetcher.leave_blocks_from(11);
+        assert_eq!(
+            fetcher.consumed_batch_merkle_roots(),
+            &[batches[0].batch_merkle_root]
+        );
+        assert!(fetcher.skipped_batches().is_empty());
+        assert_eq!(fetcher.get_last_aggregated_block(), 10);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn logs_scan_progress_is_resumed_only_from_a_block_it_covers() {
+        let (fetcher, dir) = risc0_fetcher("fetcher-logs-scan");
+        let batch = |block_number| ScannedBatch {
+            block_number,
+            batch_merkle_root: [block_number as u8; 32],
+            data_pointer: String::new(),
+        };
+
+        // Nothing persisted yet
+        let progress = fetcher.load_logs_scan_progress(10);
+        assert_eq!((progress.from_block, progress.scanned_to_block), (10, 9));
+        assert!(progress.batches.is_empty());
+
+        fetcher.save_logs_scan_progress(&LogsScanProgress {
+            from_block: 10,
+            scanned_to_block: 20,
+            batches: vec![batch(12), batch(18)],
+        });
+
+        // A fetch that stopped at the limit before block 18 continues with the batches from there on
+        let progress = fetcher.load_logs_scan_progress(15);
+        assert_eq!((progress.from_block, progress.scanned_to_block), (15, 20));
+        assert_eq!(
+            progress
+                .batches
+                .iter()
+                .map(|batch| batch.block_number)
+                .collect::<Vec<_>>(),
+            vec![18]
+        );
+
+        // Right after the scanned blocks, scanning continues from there
+        let progress = fetcher.load_logs_scan_progress(21);
+        assert_eq!((progress.from_block, progress.scanned_to_block), (21, 20));
+        assert!(progress.batches.is_empty());
+
+        // Blocks before or beyond what was scanned are scanned from scratch
+        for from_block in [9, 22] {
+            let progress = fetcher.load_logs_scan_progress(from_block);
+            assert_eq!(
+                (progress.from_block, progress.scanned_to_block),
+                (from_block, from_block - 1)
+            );
+            assert!(progress.batches.is_empty());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn logs_retry_delay_is_capped() {
+        assert_eq!(super::logs_retry_delay(0).as_millis(), 1000);
+        assert_eq!(super::logs_retry_delay(3).as_millis(), 8000);
+        assert_eq!(super::logs_retry_delay(6).as_millis(), 60_000);
+        assert_eq!(super::logs_retry_delay(64).as_millis(), 60_000);
+        assert_eq!(super::logs_retry_delay(u32::MAX).as_millis(), 60_000);
+    }
 }
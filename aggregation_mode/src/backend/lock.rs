@@ -0,0 +1,107 @@
+use fs2::FileExt;
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+};
+
+#[derive(Debug)]
+pub enum CycleLockError {
+    /// Another aggregator process, identified by its pid if it was already written, is running a cycle
+    AlreadyLocked(Option<u32>),
+    Io(std::io::Error),
+}
+
+/// Guard over an exclusive `flock` on a lock file, which holds the pid of the aggregator running a cycle.
+///
+/// The lock is released by the kernel when the guard is dropped or the process dies, so an aggregator
+/// that crashed mid cycle doesn't leave it taken. The file itself is never removed: a process that
+/// opened it before the removal could still lock it while another one locks a new file at the same path.
+pub struct CycleLock {
+    file: File,
+}
+
+impl CycleLock {
+    pub fn acquire(path: &str) -> Result<Self, CycleLockError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(CycleLockError::Io)?;
+
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.kind() != ErrorKind::WouldBlock {
+                return Err(CycleLockError::Io(e));
+            }
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            return Err(CycleLockError::AlreadyLocked(pid.trim().parse().ok()));
+        }
+
+        file.set_len(0).map_err(CycleLockError::Io)?;
+        file.write_all(std::process::id().to_string().as_bytes())
+            .map_err(CycleLockError::Io)?;
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for CycleLock {
+    fn drop(&mut self) {
+        // The lock itself is released when the file is closed
+        if let Err(e) = self.file.set_len(0) {
+            tracing::warn!("Could not clear the pid of the lock file: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CycleLock, CycleLockError};
+
+    fn lock_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "aligned-aggregator-lock-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let path = lock_path("exclusive");
+
+        let lock = CycleLock::acquire(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+        match CycleLock::acquire(&path) {
+            Err(CycleLockError::AlreadyLocked(pid)) => assert_eq!(pid, Some(std::process::id())),
+            res => panic!("Expected the lock to be taken, got {:?}", res.err()),
+        }
+
+        drop(lock);
+        assert!(CycleLock::acquire(&path).is_ok());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn lock_file_left_by_a_dead_process_is_taken_over() {
+        let path = lock_path("leftover");
+        // Nobody holds a lock on it, as happens when its process died
+        std::fs::write(&path, "4194304").unwrap();
+
+        let _lock = CycleLock::acquire(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    checkpoint::write_file_atomically,
+    s3::{create_client, upload_json_object},
+};
+use aligned_sdk::{
+    aggregation_layer::AggregationManifest,
+    common::retry::{retry_function, RetryError},
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+const UPLOAD_RETRY_MIN_DELAY_MS: u64 = 500;
+const UPLOAD_RETRY_FACTOR: f32 = 2.0;
+const UPLOAD_RETRY_MAX_TIMES: usize = 3;
+const UPLOAD_RETRY_MAX_DELAY_SECS: u64 = 10;
+
+/// Directory of the checkpoint directory the manifests that failed to upload are kept in
+const PENDING_MANIFESTS_DIR: &str = "pending_manifests";
+
+/// Where the manifest of each aggregation is published. Credentials are read from the environment, as in the batcher.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManifestStorageConfig {
+    pub bucket_name: String,
+    /// Endpoint of a non-aws storage, e.g. `http://localhost:4566` for localstack.
+    #[serde(default)]
+    pub upload_endpoint: Option<String>,
+    /// Prefix of the manifest keys, which are `<key_prefix>0x<merkle root>.json`.
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+}
+
+fn default_key_prefix() -> String {
+    "aggregations/".to_string()
+}
+
+#[derive(Debug)]
+pub enum ManifestPublishError {
+    Serialization(String),
+    Upload(String),
+}
+
+/// Uploads `manifest` to the storage, returning its key.
+pub async fn publish_manifest(
+    config: &ManifestStorageConfig,
+    manifest: &AggregationManifest,
+) -> Result<String, ManifestPublishError> {
+    let content = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| ManifestPublishError::Serialization(e.to_string()))?;
+    let key = format!(
+        "{}{}",
+        config.key_prefix,
+        AggregationManifest::file_name(&manifest.merkle_root.0)
+    );
+
+    let client = create_client(config.upload_endpoint.clone()).await;
+    retry_function(
+        || async {
+            upload_json_object(&client, &config.bucket_name, content.clone(), &key)
+                .await
+                .map_err(|e| {
+                    warn!(
+                        "Error while uploading aggregation manifest {}: {:?}",
+                        key, e
+                    );
+                    RetryError::Transient(ManifestPublishError::Upload(e.to_string()))
+                })
+        },
+        UPLOAD_RETRY_MIN_DELAY_MS,
+        UPLOAD_RETRY_FACTOR,
+        UPLOAD_RETRY_MAX_TIMES,
+        UPLOAD_RETRY_MAX_DELAY_SECS,
+    )
+    .await
+    .map_err(RetryError::inner)?;
+
+    Ok(key)
+}
+
+/// Keeps a manifest that failed to upload in `checkpoint_dir`, to upload it again in the next cycle
+/// with [`publish_pending_manifests`].
+pub fn persist_pending_manifest(
+    checkpoint_dir: &str,
+    manifest: &AggregationManifest,
+) -> io::Result<()> {
+    let dir = Path::new(checkpoint_dir).join(PENDING_MANIFESTS_DIR);
+    fs::create_dir_all(&dir)?;
+    write_file_atomically(
+        &dir.join(AggregationManifest::file_name(&manifest.merkle_root.0)),
+        &serde_json::to_vec(manifest)?,
+    )
+}
+
+/// Manifests kept by [`persist_pending_manifest`], along with their path. Unreadable ones are skipped.
+fn pending_manifests(checkpoint_dir: &str) -> Vec<(PathBuf, AggregationManifest)> {
+    let Ok(entries) = fs::read_dir(Path::new(checkpoint_dir).join(PENDING_MANIFESTS_DIR)) else {
+        return vec![];
+    };
+
+    let mut manifests = vec![];
+    for path in entries.flatten().map(|entry| entry.path()) {
+        // Skips the temporary files of interrupted writes
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        match fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                serde_json::from_slice::<AggregationManifest>(&content).map_err(|e| e.to_string())
+            }) {
+            Ok(manifest) => manifests.push((path, manifest)),
+            Err(e) => warn!(
+                "Ignoring invalid pending manifest {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+    manifests
+}
+
+/// Uploads the manifests that failed to upload in previous cycles, removing the uploaded ones.
+/// Returns the keys they were published to.
+pub async fn publish_pending_manifests(
+    config: &ManifestStorageConfig,
+    checkpoint_dir: &str,
+) -> Vec<String> {
+    let mut keys = vec![];
+    for (path, manifest) in pending_manifests(checkpoint_dir) {
+        match publish_manifest(config, &manifest).await {
+            Ok(key) => {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!(
+                        "Could not remove pending manifest {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+                keys.push(key);
+            }
+            Err(err) => warn!(
+                "Could not publish pending manifest {}, retrying in the next cycle: {:?}",
+                path.display(),
+                err
+            ),
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pending_manifests, persist_pending_manifest};
+    use aligned_sdk::aggregation_layer::AggregationManifest;
+
+    fn manifest(merkle_root: u8) -> AggregationManifest {
+        AggregationManifest {
+            merkle_root: [merkle_root; 32].into(),
+            blob_versioned_hashes: vec![[1; 32].into()],
+            leaves: vec![[2; 32].into(), [3; 32].into()],
+            tx_hash: [4; 32].into(),
+            block_number: 10,
+        }
+    }
+
+    #[test]
+    fn manifests_that_failed_to_upload_are_kept_until_uploaded() {
+        let dir = std::env::temp_dir().join(format!(
+            "aligned-aggregator-pending-manifests-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let checkpoint_dir = dir.to_string_lossy().to_string();
+        assert!(pending_manifests(&checkpoint_dir).is_empty());
+
+        persist_pending_manifest(&checkpoint_dir, &manifest(0xaa)).unwrap();
+        persist_pending_manifest(&checkpoint_dir, &manifest(0xbb)).unwrap();
+        // Persisting the same aggregation again overwrites it
+        persist_pending_manifest(&checkpoint_dir, &manifest(0xaa)).unwrap();
+
+        let mut pending = pending_manifests(&checkpoint_dir);
+        pending.sort_by_key(|(_, manifest)| manifest.merkle_root.0);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].1.merkle_root.0, [0xaa; 32]);
+        assert_eq!(pending[0].1.leaves, manifest(0xaa).leaves);
+        assert_eq!(pending[1].1.merkle_root.0, [0xbb; 32]);
+
+        std::fs::remove_file(&pending[0].0).unwrap();
+        assert_eq!(pending_manifests(&checkpoint_dir).len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,10 @@
 use crate::aggregators::AlignedProof;
-use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
+use aggregation_merkle_tree::MerkleTree;
 
-/// Returns (merkle_root, leaves)
-pub fn compute_proofs_merkle_root(
-    proofs: &[AlignedProof],
-) -> Option<(MerkleTree<AlignedProof>, Vec<[u8; 32]>)> {
-    let merkle_tree: MerkleTree<AlignedProof> = MerkleTree::build(proofs)?;
+/// Returns (merkle_tree, leaves)
+pub fn compute_proofs_merkle_root(proofs: &[AlignedProof]) -> Option<(MerkleTree, Vec<[u8; 32]>)> {
     let leaves: Vec<[u8; 32]> = proofs.iter().map(|proof| proof.commitment()).collect();
+    let merkle_tree = MerkleTree::build(&leaves)?;
 
     Some((merkle_tree, leaves))
 }
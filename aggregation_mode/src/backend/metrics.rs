@@ -0,0 +1,301 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::report::{CycleOutcome, CycleReport};
+use prometheus::{
+    exponential_buckets, histogram_opts, opts, GaugeVec, HistogramVec, IntCounterVec, IntGaugeVec,
+    Registry,
+};
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone, Debug)]
+pub struct ProofAggregatorMetrics {
+    pub cycles: IntCounterVec,
+    pub failures: IntCounterVec,
+    pub proofs_fetched: IntCounterVec,
+    pub proofs_excluded: IntCounterVec,
+    pub proofs_aggregated: IntCounterVec,
+    pub skipped_batches: IntCounterVec,
+    pub chunks_proven: IntCounterVec,
+    pub chunks_restored: IntCounterVec,
+    pub chunk_proving_duration: HistogramVec,
+    pub final_aggregation_duration: HistogramVec,
+    pub blobs: IntGaugeVec,
+    pub blob_data_bytes: IntGaugeVec,
+    pub verify_gas_used: IntGaugeVec,
+    pub verify_gas_used_total: IntCounterVec,
+    pub verify_tx_replacements: IntCounterVec,
+    pub cost_per_proof: GaugeVec,
+    pub last_aggregated_block: IntGaugeVec,
+    pub last_submission_timestamp: IntGaugeVec,
+}
+
+impl ProofAggregatorMetrics {
+    /// Registers the metrics and serves them in `/metrics` on `metrics_port`.
+    pub fn start(metrics_port: u16) -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let cycles = IntCounterVec::new(
+            opts!("aggregator_cycles_count", "Cycles run, by outcome"),
+            &["engine", "status"],
+        )?;
+        let failures = IntCounterVec::new(
+            opts!("aggregator_failures_count", "Failed cycles, by error"),
+            &["engine", "error"],
+        )?;
+        let proofs_fetched = IntCounterVec::new(
+            opts!(
+                "aggregator_proofs_fetched_count",
+                "Proofs fetched from consumed batches, excluded ones included"
+            ),
+            &["engine"],
+        )?;
+        let proofs_excluded = IntCounterVec::new(
+            opts!(
+                "aggregator_proofs_excluded_count",
+                "Proofs excluded by the fetcher or the admission policy, by reason"
+            ),
+            &["engine", "reason"],
+        )?;
+        let proofs_aggregated = IntCounterVec::new(
+            opts!(
+                "aggregator_proofs_aggregated_count",
+                "Proofs aggregated and verified on chain"
+            ),
+            &["engine"],
+        )?;
+        let skipped_batches = IntCounterVec::new(
+            opts!(
+                "aggregator_skipped_batches_count",
+                "Batches skipped because they can't be downloaded or their data is invalid"
+            ),
+            &["engine"],
+        )?;
+        let chunks_proven = IntCounterVec::new(
+            opts!("aggregator_chunks_proven_count", "Chunks proven"),
+            &["engine"],
+        )?;
+        let chunks_restored = IntCounterVec::new(
+            opts!(
+                "aggregator_chunks_restored_count",
+                "Chunks restored from a checkpoint"
+            ),
+            &["engine"],
+        )?;
+        // From 1 second to about 9 hours
+        let chunk_proving_duration = HistogramVec::new(
+            histogram_opts!(
+                "aggregator_chunk_proving_duration_seconds",
+                "Proving time of a chunk",
+                exponential_buckets(1.0, 2.0, 16)?
+            ),
+            &["engine"],
+        )?;
+        let final_aggregation_duration = HistogramVec::new(
+            histogram_opts!(
+                "aggregator_final_aggregation_duration_seconds",
+                "Proving time of the aggregation of the chunks",
+                exponential_buckets(1.0, 2.0, 16)?
+            ),
+            &["engine"],
+        )?;
+        let blobs = IntGaugeVec::new(
+            opts!("aggregator_blobs", "Blobs of the last aggregated proof"),
+            &["engine"],
+        )?;
+        let blob_data_bytes = IntGaugeVec::new(
+            opts!(
+                "aggregator_blob_data_bytes",
+                "Bytes of leaves published in the blobs of the last aggregated proof"
+            ),
+            &["engine"],
+        )?;
+        let verify_gas_used = IntGaugeVec::new(
+            opts!(
+                "aggregator_verify_gas_used",
+                "Gas used by the last verifySP1/verifyRisc0 transaction"
+            ),
+            &["engine"],
+        )?;
+        let verify_gas_used_total = IntCounterVec::new(
+            opts!(
+                "aggregator_verify_gas_used_total",
+                "Gas used by the verifySP1/verifyRisc0 transactions"
+            ),
+            &["engine"],
+        )?;
+        let verify_tx_replacements = IntCounterVec::new(
+            opts!(
+                "aggregator_verify_tx_replacements_count",
+                "verifySP1/verifyRisc0 transactions replaced with bumped fees"
+            ),
+            &["engine"],
+        )?;
+        let cost_per_proof = GaugeVec::new(
+            opts!(
+                "aggregator_cost_per_proof_wei",
+                "Estimated verification cost per proof of the last aggregation"
+            ),
+            &["engine"],
+        )?;
+        let last_aggregated_block = IntGaugeVec::new(
+            opts!("aggregator_last_aggregated_block", "Last aggregated block"),
+            &["engine"],
+        )?;
+        let last_submission_timestamp = IntGaugeVec::new(
+            opts!(
+                "aggregator_last_submission_timestamp_seconds",
+                "Unix timestamp of the last aggregated proof verified on chain"
+            ),
+            &["engine"],
+        )?;
+
+        registry.register(Box::new(cycles.clone()))?;
+        registry.register(Box::new(failures.clone()))?;
+        registry.register(Box::new(proofs_fetched.clone()))?;
+        registry.register(Box::new(proofs_excluded.clone()))?;
+        registry.register(Box::new(proofs_aggregated.clone()))?;
+        registry.register(Box::new(skipped_batches.clone()))?;
+        registry.register(Box::new(chunks_proven.clone()))?;
+        registry.register(Box::new(chunks_restored.clone()))?;
+        registry.register(Box::new(chunk_proving_duration.clone()))?;
+        registry.register(Box::new(final_aggregation_duration.clone()))?;
+        registry.register(Box::new(blobs.clone()))?;
+        registry.register(Box::new(blob_data_bytes.clone()))?;
+        registry.register(Box::new(verify_gas_used.clone()))?;
+        registry.register(Box::new(verify_gas_used_total.clone()))?;
+        registry.register(Box::new(verify_tx_replacements.clone()))?;
+        registry.register(Box::new(cost_per_proof.clone()))?;
+        registry.register(Box::new(last_aggregated_block.clone()))?;
+        registry.register(Box::new(last_submission_timestamp.clone()))?;
+
+        let metrics_route = warp::path!("metrics")
+            .and(warp::any().map(move || registry.clone()))
+            .and_then(ProofAggregatorMetrics::metrics_handler);
+
+        tokio::task::spawn(async move {
+            warp::serve(metrics_route)
+                .run(([0, 0, 0, 0], metrics_port))
+                .await;
+        });
+
+        Ok(Self {
+            cycles,
+            failures,
+            proofs_fetched,
+            proofs_excluded,
+            proofs_aggregated,
+            skipped_batches,
+            chunks_proven,
+            chunks_restored,
+            chunk_proving_duration,
+            final_aggregation_duration,
+            blobs,
+            blob_data_bytes,
+            verify_gas_used,
+            verify_gas_used_total,
+            verify_tx_replacements,
+            cost_per_proof,
+            last_aggregated_block,
+            last_submission_timestamp,
+        })
+    }
+
+    pub async fn metrics_handler(registry: Registry) -> Result<impl Reply, Rejection> {
+        use prometheus::Encoder;
+        let encoder = prometheus::TextEncoder::new();
+
+        let mut buffer = Vec::new();
+        if let Err(e) = encoder.encode(&registry.gather(), &mut buffer) {
+            tracing::error!("Could not encode prometheus metrics: {}", e);
+        };
+        let res = String::from_utf8(buffer)
+            .inspect_err(|e| tracing::error!("Prometheus metrics could not be parsed: {}", e))
+            .unwrap_or_default();
+
+        Ok(res)
+    }
+
+    pub fn record_cycle(&self, report: &CycleReport) {
+        let engine = report.engine.name();
+        let stats = &report.stats;
+
+        self.cycles
+            .with_label_values(&[engine, report.outcome.status()])
+            .inc();
+        if let CycleOutcome::Failed(_) = report.outcome {
+            let error = stats.error.unwrap_or("unknown");
+            self.failures.with_label_values(&[engine, error]).inc();
+        }
+
+        // The proofs and batches of a range not consumed are fetched again by the next cycle
+        if report.outcome.consumed_range() {
+            self.proofs_fetched
+                .with_label_values(&[engine])
+                .inc_by(stats.proofs_fetched as u64);
+            for (reason, count) in &stats.proofs_excluded {
+                self.proofs_excluded
+                    .with_label_values(&[engine, *reason])
+                    .inc_by(*count as u64);
+            }
+            self.skipped_batches
+                .with_label_values(&[engine])
+                .inc_by(report.skipped_batches.len() as u64);
+        }
+
+        let aggregation = &stats.aggregation;
+        self.chunks_proven
+            .with_label_values(&[engine])
+            .inc_by(aggregation.chunks_proven as u64);
+        self.chunks_restored
+            .with_label_values(&[engine])
+            .inc_by(aggregation.chunks_restored as u64);
+        for duration in &aggregation.chunk_proving_durations {
+            self.chunk_proving_duration
+                .with_label_values(&[engine])
+                .observe(duration.as_secs_f64());
+        }
+
+        self.verify_tx_replacements
+            .with_label_values(&[engine])
+            .inc_by(stats.tx_replacements as u64);
+
+        self.last_aggregated_block
+            .with_label_values(&[engine])
+            .set(report.to_block as i64);
+        if let Some(cost_per_proof_wei) = stats.cost_per_proof_wei {
+            self.cost_per_proof
+                .with_label_values(&[engine])
+                .set(cost_per_proof_wei as f64);
+        }
+
+        if let CycleOutcome::Submitted { proofs, .. } = report.outcome {
+            self.proofs_aggregated
+                .with_label_values(&[engine])
+                .inc_by(proofs as u64);
+            self.final_aggregation_duration
+                .with_label_values(&[engine])
+                .observe(aggregation.final_aggregation_duration.as_secs_f64());
+            self.blobs
+                .with_label_values(&[engine])
+                .set(stats.blobs as i64);
+            self.blob_data_bytes
+                .with_label_values(&[engine])
+                .set(stats.blob_data_bytes as i64);
+            if let Some(gas_used) = stats.gas_used {
+                self.verify_gas_used
+                    .with_label_values(&[engine])
+                    .set(gas_used as i64);
+                self.verify_gas_used_total
+                    .with_label_values(&[engine])
+                    .inc_by(gas_used);
+            }
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default();
+            self.last_submission_timestamp
+                .with_label_values(&[engine])
+                .set(now as i64);
+        }
+    }
+}
@@ -1,26 +1,59 @@
+pub mod admission;
+mod batch_downloader;
+pub mod checkpoint;
 pub mod config;
+pub mod cost;
 pub mod fetcher;
+mod lock;
+pub mod manifest;
 mod merkle_tree;
+mod metrics;
+pub mod report;
 mod s3;
+pub mod submission;
 mod types;
 
-use crate::aggregators::{AlignedProof, ProofAggregationError, ZKVMEngine};
+use crate::aggregators::{
+    risc0_aggregator, AlignedProof, ProofAggregationError, ProvingMode, ZKVMEngine,
+};
 
+use admission::AdmissionPolicy;
+use aligned_sdk::{
+    aggregation_layer::AggregationManifest,
+    common::retry::{retry_function, RetryError},
+};
 use alloy::{
     consensus::BlobTransactionSidecar,
-    eips::eip4844::BYTES_PER_BLOB,
+    eips::{eip4844::BYTES_PER_BLOB, BlockNumberOrTag},
     hex,
     network::EthereumWallet,
-    primitives::Address,
-    providers::{PendingTransactionError, ProviderBuilder},
-    rpc::types::TransactionReceipt,
+    primitives::{Address, TxHash},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{TransactionReceipt, TransactionRequest},
     signers::local::LocalSigner,
+    transports::TransportError,
 };
-use config::Config;
+use checkpoint::AggregationCheckpoint;
+use config::{Config, DaemonConfig};
+use cost::{CostPolicyConfig, NetworkFees, SubmissionCost, VerificationGasEstimates};
 use fetcher::{ProofsFetcher, ProofsFetcherError};
+use lock::CycleLock;
+use manifest::{persist_pending_manifest, publish_manifest, publish_pending_manifests};
 use merkle_tree::compute_proofs_merkle_root;
+use metrics::ProofAggregatorMetrics;
+use report::{CycleOutcome, CycleReport, CycleStats, RunReport};
+
 use risc0_ethereum_contracts::encode_seal;
-use std::str::FromStr;
+use std::{
+    future::Future,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
+};
+use submission::{PendingVerification, TransactionFees};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
 use tracing::{error, info, warn};
 use types::{AlignedProofAggregationService, AlignedProofAggregationServiceContract};
 
@@ -31,28 +64,175 @@ pub enum AggregatedProofSubmissionError {
     BuildingBlobVersionedHash,
     Risc0EncodingSeal(String),
     SendVerifyAggregatedProofTransaction(alloy::contract::Error),
-    ReceiptError(PendingTransactionError),
+    FetchingNonce(TransportError),
+    /// None of the sent transactions, holds their hashes, was included before running out of replacements
+    TransactionNotIncluded(Vec<TxHash>),
+    /// The verification transaction was included but reverted
+    VerificationReverted(TxHash),
     FetchingProofs(ProofsFetcherError),
     ZKVMAggregation(ProofAggregationError),
     BuildingMerkleRoot,
     MerkleRootMisMatch,
+    UnsupportedAggregatedProof,
+    /// The leaves, holds their count, don't fit in a blob
+    TooManyLeavesForBlob(usize),
+    ReadingVerifierAddress(alloy::contract::Error),
+    /// Mocked proofs would be rejected, the engine verifier is not `VERIFIER_MOCK_ADDRESS`
+    MockProofsWithRealVerifier(ZKVMEngine),
+    FetchingChainId(TransportError),
+    /// Proofs are only mocked in devnet, holds the chain id of the network
+    MockProofsOutsideDevnet(u64),
+    FetchingFees(String),
+    /// The simulated `verifySP1`/`verifyRisc0` call failed, the transaction would revert
+    SimulatingVerification(TransportError),
+    /// Reading whether the merkle root of a resumed aggregation was already verified failed
+    CheckingAggregatedProof(alloy::contract::Error),
+}
+
+impl AggregatedProofSubmissionError {
+    /// Name of the variant, used to label failures in metrics and run reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BuildingBlobCommitment => "building_blob_commitment",
+            Self::BuildingBlobProof => "building_blob_proof",
+            Self::BuildingBlobVersionedHash => "building_blob_versioned_hash",
+            Self::Risc0EncodingSeal(_) => "risc0_encoding_seal",
+            Self::SendVerifyAggregatedProofTransaction(_) => {
+                "send_verify_aggregated_proof_transaction"
+            }
+            Self::FetchingNonce(_) => "fetching_nonce",
+            Self::TransactionNotIncluded(_) => "transaction_not_included",
+            Self::VerificationReverted(_) => "verification_reverted",
+            Self::FetchingProofs(_) => "fetching_proofs",
+            Self::ZKVMAggregation(_) => "zkvm_aggregation",
+            Self::BuildingMerkleRoot => "building_merkle_root",
+            Self::MerkleRootMisMatch => "merkle_root_mismatch",
+            Self::UnsupportedAggregatedProof => "unsupported_aggregated_proof",
+            Self::TooManyLeavesForBlob(_) => "too_many_leaves_for_blob",
+            Self::ReadingVerifierAddress(_) => "reading_verifier_address",
+            Self::MockProofsWithRealVerifier(_) => "mock_proofs_with_real_verifier",
+            Self::FetchingChainId(_) => "fetching_chain_id",
+            Self::MockProofsOutsideDevnet(_) => "mock_proofs_outside_devnet",
+            Self::FetchingFees(_) => "fetching_fees",
+            Self::SimulatingVerification(_) => "simulating_verification",
+            Self::CheckingAggregatedProof(_) => "checking_aggregated_proof",
+        }
+    }
+
+    /// Whether the error may not happen again, like a failed request to the RPC or a transaction
+    /// not included in time. Fatal errors, like a reverted verification, would fail the same way.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::SendVerifyAggregatedProofTransaction(alloy::contract::Error::TransportError(
+                e,
+            ))
+            | Self::ReadingVerifierAddress(alloy::contract::Error::TransportError(e))
+            | Self::CheckingAggregatedProof(alloy::contract::Error::TransportError(e))
+            | Self::FetchingNonce(e)
+            | Self::FetchingChainId(e)
+            | Self::SimulatingVerification(e) => is_retryable_rpc_error(e),
+            Self::FetchingProofs(ProofsFetcherError::BlockExceedsLimit(_)) => false,
+            Self::FetchingProofs(_) | Self::FetchingFees(_) | Self::TransactionNotIncluded(_) => {
+                true
+            }
+            Self::BuildingBlobCommitment
+            | Self::BuildingBlobProof
+            | Self::BuildingBlobVersionedHash
+            | Self::Risc0EncodingSeal(_)
+            | Self::SendVerifyAggregatedProofTransaction(_)
+            | Self::ReadingVerifierAddress(_)
+            | Self::CheckingAggregatedProof(_)
+            | Self::ZKVMAggregation(_)
+            | Self::BuildingMerkleRoot
+            | Self::MerkleRootMisMatch
+            | Self::UnsupportedAggregatedProof
+            | Self::TooManyLeavesForBlob(_)
+            | Self::MockProofsWithRealVerifier(_)
+            | Self::MockProofsOutsideDevnet(_)
+            | Self::VerificationReverted(_) => false,
+        }
+    }
+
+    /// The node rejected the transaction because a pending one with the same nonce pays more.
+    fn is_underpriced_replacement(&self) -> bool {
+        match self {
+            Self::SendVerifyAggregatedProofTransaction(alloy::contract::Error::TransportError(
+                e,
+            )) => e
+                .as_error_resp()
+                .is_some_and(|resp| resp.message.contains("underpriced")),
+            _ => false,
+        }
+    }
+}
+
+impl From<AggregatedProofSubmissionError> for RetryError<AggregatedProofSubmissionError> {
+    fn from(e: AggregatedProofSubmissionError) -> Self {
+        if e.is_retryable() {
+            RetryError::Transient(e)
+        } else {
+            RetryError::Permanent(e)
+        }
+    }
+}
+
+/// Errors the node answered with, like a revert or a rejected transaction, would be answered again.
+/// The rest come from reaching the node, e.g. a connection error or a rate limit.
+fn is_retryable_rpc_error(e: &TransportError) -> bool {
+    e.as_error_resp().is_none()
 }
 
+// Retries of the RPC calls made while submitting, with an exponential backoff
+const RPC_RETRY_MIN_DELAY_MS: u64 = 500;
+const RPC_RETRY_FACTOR: f32 = 2.0;
+const RPC_RETRY_MAX_TIMES: usize = 5;
+const RPC_RETRY_MAX_DELAY_SECS: u64 = 30;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Chain id of the local anvil network, the only one proofs can be mocked in.
+const DEVNET_CHAIN_ID: u64 = 31337;
+
+/// Leaves that fit in a blob, see [`ProofAggregator::construct_blob`].
+pub const PROOFS_PER_BLOB: usize = BYTES_PER_BLOB / 32 * 31 / 32;
+
+/// Most proofs in an aggregation, its leaves are published in a single blob.
+pub const MAX_TOTAL_PROOFS_LIMIT: u16 = {
+    assert!(PROOFS_PER_BLOB <= u16::MAX as usize);
+    PROOFS_PER_BLOB as u16
+};
+
 pub struct ProofAggregator {
-    engine: ZKVMEngine,
     proof_aggregation_service: AlignedProofAggregationServiceContract,
-    fetcher: ProofsFetcher,
+    /// Sender of the verification transactions
+    submitter_address: Address,
+    /// One fetcher per engine, each aggregates and submits its proofs independently.
+    fetchers: Vec<ProofsFetcher>,
+    admission_policy: AdmissionPolicy,
+    metrics: Option<ProofAggregatorMetrics>,
+    /// Gas of the last simulated verification of each engine
+    verification_gas_estimates: VerificationGasEstimates,
     config: Config,
 }
 
 impl ProofAggregator {
-    pub fn new(config: Config) -> Self {
+    pub fn new(mut config: Config) -> Self {
+        let total_proofs_limit = capped_total_proofs_limit(config.total_proofs_limit);
+        if total_proofs_limit != config.total_proofs_limit {
+            warn!(
+                "total_proofs_limit {} doesn't fit in a blob, capping it to {}",
+                config.total_proofs_limit, total_proofs_limit
+            );
+            config.total_proofs_limit = total_proofs_limit;
+        }
+
         let rpc_url = config.eth_rpc_url.parse().expect("RPC URL should be valid");
         let signer = LocalSigner::decrypt_keystore(
             config.ecdsa.private_key_store_path.clone(),
             config.ecdsa.private_key_store_password.clone(),
         )
         .expect("Keystore signer should be `cast wallet` compliant");
+        let submitter_address = signer.address();
         let wallet = EthereumWallet::from(signer);
         let rpc_provider = ProviderBuilder::new().wallet(wallet).connect_http(rpc_url);
         let proof_aggregation_service = AlignedProofAggregationService::new(
@@ -61,61 +241,466 @@ impl ProofAggregator {
             rpc_provider,
         );
 
-        let engine =
-            ZKVMEngine::from_env().expect("AGGREGATOR env variable to be set to one of sp1|risc0");
-        let fetcher = ProofsFetcher::new(&config);
+        let engines = ZKVMEngine::from_env()
+            .expect("AGGREGATOR env variable to be set to one or more of sp1|risc0");
+        let fetchers = engines
+            .into_iter()
+            .map(|engine| ProofsFetcher::new(&config, engine))
+            .collect();
+
+        if config.proving_mode == ProvingMode::Mock {
+            warn!("Proving mode is mock, aggregated proofs are only accepted by a mock verifier");
+            assert!(
+                risc0_aggregator::is_dev_mode_enabled(),
+                "Mock proving needs the Risc0 dev mode, enable it with `risc0_aggregator::enable_dev_mode` before starting the runtime"
+            );
+        }
+
+        let admission_policy = AdmissionPolicy::new(&config.admission_policy)
+            .expect("Admission policy program ids should be 32 bytes hex strings");
+
+        let metrics = config.metrics_port.map(|metrics_port| {
+            info!("Starting metrics server on port {}", metrics_port);
+            ProofAggregatorMetrics::start(metrics_port).expect("Failed to start metrics server")
+        });
 
         Self {
-            engine,
             proof_aggregation_service,
-            fetcher,
+            submitter_address,
+            fetchers,
+            admission_policy,
+            metrics,
+            verification_gas_estimates: VerificationGasEstimates::load(&config.checkpoint_dir()),
             config,
         }
     }
 
+    /// Runs a single cycle for each engine and returns, scheduling is left to the caller (e.g. cron).
     pub async fn start(&mut self) {
         info!("Starting proof aggregator service");
+        self.run_cycles(None, None).await;
+    }
+
+    /// Runs cycles until a SIGINT or SIGTERM is received. Every `poll_interval_secs` each engine
+    /// runs a cycle if any of the configured triggers is met, or unconditionally if none is set.
+    ///
+    /// A signal received in the middle of a cycle lets it finish, so no proof is left half submitted.
+    pub async fn start_daemon(&mut self, daemon: DaemonConfig) {
+        info!(
+            "Starting proof aggregator service in daemon mode, polling every {}s",
+            daemon.poll_interval_secs
+        );
+
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        tokio::spawn(async move {
+            wait_for_shutdown_signal().await;
+            info!("Shutdown signal received, stopping after the running cycle");
+            let _ = shutdown_tx.send(true);
+        });
+
+        let poll_interval = Duration::from_secs(daemon.poll_interval_secs);
+        while !*shutdown_rx.borrow() {
+            self.run_cycles(Some(&daemon), Some(&shutdown_rx)).await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(poll_interval) => {}
+                _ = shutdown_rx.changed() => {}
+            }
+        }
+
+        info!("Proof aggregator service stopped");
+    }
+
+    /// Runs a cycle for each engine, one after the other. An engine failing doesn't stop the others
+    /// and its last aggregated block is not updated, so its proofs are retried in the next cycle.
+    async fn run_cycles(
+        &mut self,
+        daemon: Option<&DaemonConfig>,
+        shutdown: Option<&watch::Receiver<bool>>,
+    ) -> Vec<CycleReport> {
+        // Prevents overlapping with another aggregator working on the same last aggregated block file
+        let _lock = match CycleLock::acquire(&self.config.lock_filepath()) {
+            Ok(lock) => lock,
+            Err(err) => {
+                error!(
+                    "Could not acquire aggregator lock, skipping cycle: {:?}",
+                    err
+                );
+                return vec![];
+            }
+        };
+
+        if let Some(manifest_storage) = &self.config.manifest_storage {
+            for key in
+                publish_pending_manifests(manifest_storage, &self.config.checkpoint_dir()).await
+            {
+                info!(
+                    "Aggregation manifest of a previous cycle published to {}",
+                    key
+                );
+            }
+        }
+
+        let started_at = SystemTime::now();
+        let mut reports = Vec::with_capacity(self.fetchers.len());
+        for i in 0..self.fetchers.len() {
+            if shutdown.is_some_and(|shutdown| *shutdown.borrow()) {
+                break;
+            }
+
+            let report = self.run_cycle(i, daemon).await;
+            report.log();
+            if let Some(metrics) = &self.metrics {
+                metrics.record_cycle(&report);
+            }
+            reports.push(report);
+        }
+
+        if let Err(err) = RunReport::new(started_at, &reports).write(
+            &self.config.reports_dir(),
+            self.config.run_reports_retention,
+        ) {
+            warn!("Could not write run report: {:?}", err);
+        }
+
+        reports
+    }
+
+    async fn run_cycle(
+        &mut self,
+        fetcher_index: usize,
+        daemon: Option<&DaemonConfig>,
+    ) -> CycleReport {
+        let started_at = Instant::now();
+        let engine = self.fetchers[fetcher_index].engine().clone();
+        let mut stats = CycleStats::default();
 
-        info!("About to aggregate and submit proof to be verified on chain");
-        let res = self.aggregate_and_submit_proofs_on_chain().await;
+        // Read under the lock on every cycle, another aggregator may have advanced it since the last one
+        let from_block = match self.config.get_last_aggregated_block(&engine) {
+            Ok(block) => block,
+            Err(err) => {
+                stats.error = Some("read_last_aggregated_block");
+                let block = self.fetchers[fetcher_index].get_last_aggregated_block();
+                return CycleReport {
+                    engine,
+                    outcome: CycleOutcome::Failed(format!(
+                        "Could not read last aggregated block: {:?}",
+                        err
+                    )),
+                    from_block: block,
+                    to_block: block,
+                    skipped_batches: vec![],
+                    stats,
+                    duration: started_at.elapsed(),
+                };
+            }
+        };
+        self.fetchers[fetcher_index].set_last_aggregated_block(from_block);
+        info!(
+            "About to aggregate and submit {} proof to be verified on chain",
+            engine
+        );
 
-        match res {
-            Ok(()) => {
-                self.config
-                    .update_last_aggregated_block(self.fetcher.get_last_aggregated_block())
-                    .unwrap();
-                info!("Process finished successfully");
+        let outcome = match self
+            .aggregate_and_submit_proofs_on_chain(fetcher_index, daemon, &mut stats)
+            .await
+        {
+            Ok(outcome @ (CycleOutcome::Skipped | CycleOutcome::Deferred { .. })) => {
+                self.fetchers[fetcher_index].set_last_aggregated_block(from_block);
+                outcome
+            }
+            Ok(outcome) => {
+                let fetcher = &self.fetchers[fetcher_index];
+                match self
+                    .config
+                    .update_last_aggregated_block(&engine, fetcher.get_last_aggregated_block())
+                {
+                    Ok(()) => {
+                        // The cache is shared, a batch is only evicted once every engine went past it
+                        let aggregated_by_all_engines = self
+                            .fetchers
+                            .iter()
+                            .map(|fetcher| fetcher.get_last_aggregated_block())
+                            .min()
+                            .unwrap_or_default();
+                        fetcher.evict_cached_batches_up_to(aggregated_by_all_engines);
+                        // Only done once the last aggregated block is persisted, a crash before
+                        // leaves a checkpoint behind that is discarded as stale on the next run
+                        if let CycleOutcome::Submitted { tx_hash, .. } = &outcome {
+                            let completed = AggregationCheckpoint::open(
+                                &self.config.checkpoint_dir(),
+                                &engine,
+                                from_block,
+                                fetcher.get_last_aggregated_block(),
+                                &fetcher.consumed_batch_merkle_roots(),
+                            )
+                            .and_then(|checkpoint| checkpoint.complete(tx_hash.clone()));
+                            if let Err(err) = completed {
+                                warn!("Could not complete aggregation checkpoint: {:?}", err);
+                            }
+                        }
+                        outcome
+                    }
+                    Err(err) => {
+                        stats.error = Some("update_last_aggregated_block");
+                        CycleOutcome::Failed(format!(
+                            "Could not update last aggregated block: {:?}",
+                            err
+                        ))
+                    }
+                }
             }
             Err(err) => {
-                error!("Error while aggregating and submitting proofs: {:?}", err);
+                self.fetchers[fetcher_index].set_last_aggregated_block(from_block);
+                stats.error = Some(err.name());
+                CycleOutcome::Failed(format!("{:?}", err))
             }
+        };
+
+        CycleReport {
+            engine,
+            outcome,
+            from_block,
+            to_block: self.fetchers[fetcher_index].get_last_aggregated_block(),
+            skipped_batches: self.fetchers[fetcher_index].skipped_batches().to_vec(),
+            stats,
+            duration: started_at.elapsed(),
         }
     }
 
     async fn aggregate_and_submit_proofs_on_chain(
         &mut self,
-    ) -> Result<(), AggregatedProofSubmissionError> {
-        let proofs = self
-            .fetcher
-            .fetch(self.engine.clone(), self.config.total_proofs_limit)
+        fetcher_index: usize,
+        daemon: Option<&DaemonConfig>,
+        stats: &mut CycleStats,
+    ) -> Result<CycleOutcome, AggregatedProofSubmissionError> {
+        self.check_verifier(self.fetchers[fetcher_index].engine())
+            .await?;
+        self.settle_pending_verification(&mut stats.tx_replacements)
+            .await?;
+
+        let fetcher = &mut self.fetchers[fetcher_index];
+        let engine = fetcher.engine().clone();
+        let from_block = fetcher.get_last_aggregated_block();
+        let checkpoint_dir = self.config.checkpoint_dir();
+
+        // A checkpoint starting at this block means a previous run died while aggregating,
+        // fetch up to the same block to get the same proofs and reuse its aggregated chunks
+        let resume_up_to_block =
+            match AggregationCheckpoint::find_resumable(&checkpoint_dir, &engine, from_block) {
+                Ok(to_block) => to_block,
+                Err(err) => {
+                    warn!("Could not look for aggregation checkpoints: {:?}", err);
+                    None
+                }
+            };
+        if let Some(to_block) = resume_up_to_block {
+            info!(
+                "Resuming {} aggregation of blocks {} to {} from checkpoint",
+                engine, from_block, to_block
+            );
+        }
+
+        // Triggers were already met by the run that created the checkpoint
+        let (min_elapsed_blocks, min_pending_proofs) = match (daemon, resume_up_to_block) {
+            (Some(daemon), None) => (daemon.min_elapsed_blocks, daemon.min_pending_proofs),
+            _ => (None, None),
+        };
+
+        let elapsed_blocks_reached = match min_elapsed_blocks {
+            Some(min_elapsed_blocks) => {
+                fetcher
+                    .elapsed_blocks()
+                    .await
+                    .map_err(AggregatedProofSubmissionError::FetchingProofs)?
+                    >= min_elapsed_blocks
+            }
+            None => false,
+        };
+        // Pending proofs can only be counted by fetching them, avoid it if it can't trigger the cycle
+        if min_elapsed_blocks.is_some() && !elapsed_blocks_reached && min_pending_proofs.is_none() {
+            return Ok(CycleOutcome::Skipped);
+        }
+
+        let fetched_proofs = fetcher
+            .fetch(self.config.total_proofs_limit, resume_up_to_block)
             .await
             .map_err(AggregatedProofSubmissionError::FetchingProofs)?;
+        let fetched_proofs_count = fetched_proofs.len();
+        let (admitted_proofs, admission_report) = self.admission_policy.apply(fetched_proofs);
+        admission_report.log(&engine);
+        if let Some(block) = admission_report.deferred_from_block {
+            // Fetched again by the next cycle, along with the batches of their blocks
+            fetcher.leave_blocks_from(block);
+        }
+        // Proofs the fetcher couldn't aggregate count as fetched and excluded too,
+        // so the fetched proofs of a range are the aggregated plus the excluded ones
+        stats.proofs_excluded = fetcher.excluded_proofs_by_reason();
+        let excluded_by_fetcher: usize = stats.proofs_excluded.values().sum();
+        for (reason, count) in admission_report.excluded_by_reason() {
+            *stats.proofs_excluded.entry(reason).or_default() += count;
+        }
+        stats.proofs_fetched =
+            fetched_proofs_count - admission_report.deferred + excluded_by_fetcher;
+
+        if let Some(min_pending_proofs) = min_pending_proofs {
+            if !elapsed_blocks_reached && admitted_proofs.len() < min_pending_proofs as usize {
+                info!(
+                    "{} pending {} proofs, waiting for at least {} to aggregate",
+                    admitted_proofs.len(),
+                    engine,
+                    min_pending_proofs
+                );
+                // Admitting them again along with the next ones gives the same result, the excluded
+                // ones would be excluded again, so only the admitted ones are kept
+                fetcher.keep_pending(from_block, admitted_proofs, &admission_report.excluded);
+                return Ok(CycleOutcome::Skipped);
+            }
+        }
+
+        let oldest_proof_block = admitted_proofs.iter().map(|p| p.block_number).min();
+        let proofs: Vec<AlignedProof> = admitted_proofs.into_iter().map(|p| p.proof).collect();
 
         if proofs.is_empty() {
-            warn!("No proofs collected, skipping aggregation...");
-            return Ok(());
+            warn!("No {} proofs collected, skipping aggregation...", engine);
+            return Ok(CycleOutcome::NoProofs);
+        }
+        let proofs_count = proofs.len();
+
+        // A resumed aggregation already passed the check, and its chunks are already paid for
+        if let (Some(cost_policy), None) = (&self.config.cost_policy, resume_up_to_block) {
+            let blobs = proofs_count.div_ceil(PROOFS_PER_BLOB);
+            let cost = self.submission_cost(&engine, blobs, cost_policy).await?;
+            let cost_per_proof_wei = cost.per_proof_wei(proofs_count);
+            stats.cost_per_proof_wei = Some(cost_per_proof_wei);
+
+            let current_block = self
+                .proof_aggregation_service
+                .provider()
+                .get_block_number()
+                .await
+                .map_err(|e| AggregatedProofSubmissionError::FetchingFees(e.to_string()))?;
+            let oldest_proof_wait_blocks =
+                current_block.saturating_sub(oldest_proof_block.unwrap_or(current_block));
+
+            if !cost_policy.should_submit(&cost, proofs_count, oldest_proof_wait_blocks) {
+                info!(
+                    "{} aggregation of {} proofs deferred, cost per proof {} wei is over {} wei and the oldest proof waits for {} blocks",
+                    engine,
+                    proofs_count,
+                    cost_per_proof_wei,
+                    cost_policy.max_cost_per_proof_wei,
+                    oldest_proof_wait_blocks
+                );
+                return Ok(CycleOutcome::Deferred { cost_per_proof_wei });
+            }
         }
 
+        let fetcher = &self.fetchers[fetcher_index];
+        let mut checkpoint = match AggregationCheckpoint::open(
+            &checkpoint_dir,
+            &engine,
+            from_block,
+            fetcher.get_last_aggregated_block(),
+            &fetcher.consumed_batch_merkle_roots(),
+        ) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(err) => {
+                warn!(
+                    "Could not open aggregation checkpoint, aggregating without it: {:?}",
+                    err
+                );
+                None
+            }
+        };
+
         info!("Proofs fetched, constructing merkle root...");
         let (merkle_tree, leaves) = compute_proofs_merkle_root(&proofs)
             .ok_or(AggregatedProofSubmissionError::BuildingMerkleRoot)?;
-        let merkle_root = merkle_tree.root;
+        let merkle_root = merkle_tree.root();
         info!("Merkle root constructed: 0x{}", hex::encode(merkle_root));
 
+        info!("Constructing blob...");
+        stats.blob_data_bytes = leaves.len() * 32;
+        let (blob, blob_versioned_hash) = self.construct_blob(&leaves).await?;
+        stats.blobs = blob.blobs.len();
+        let blob_versioned_hashes: Vec<[u8; 32]> = (0..blob.blobs.len())
+            .filter_map(|i| blob.versioned_hash_for_blob(i))
+            .map(|hash| hash.0)
+            .collect();
+        info!(
+            "Blob constructed, versioned hash: {}",
+            hex::encode(blob_versioned_hash)
+        );
+
+        // A previous run may have died after sending the verification, it must not be proven and sent again
+        if let (Some(checkpoint), Some(_)) = (checkpoint.as_mut(), resume_up_to_block) {
+            let (_, sent_tx_hashes) = checkpoint.sent_transactions();
+            match self.find_receipt(&sent_tx_hashes).await {
+                Some(receipt) if receipt.status() => {
+                    info!(
+                        "Verification sent by a previous run was included, tx hash {:?}",
+                        receipt.transaction_hash
+                    );
+                    return Ok(self
+                        .finish_submission(
+                            receipt,
+                            proofs_count,
+                            merkle_root,
+                            blob_versioned_hashes,
+                            leaves,
+                            stats,
+                        )
+                        .await);
+                }
+                Some(receipt) => {
+                    warn!(
+                        "Verification sent by a previous run reverted, sending it again: {:?}",
+                        receipt.transaction_hash
+                    );
+                    if let Err(err) = checkpoint.clear_sent_transactions() {
+                        warn!("Could not clear the checkpoint transactions: {:?}", err);
+                    }
+                }
+                None => {}
+            }
+
+            // Covers a run that died before persisting the transaction it sent
+            let proof_aggregation_service = &self.proof_aggregation_service;
+            let verified = retry_rpc_call(move || async move {
+                proof_aggregation_service
+                    .aggregatedProofs(merkle_root.into())
+                    .call()
+                    .await
+                    .map_err(AggregatedProofSubmissionError::CheckingAggregatedProof)
+            })
+            .await?;
+            if verified {
+                let tx_hash = sent_tx_hashes
+                    .last()
+                    .map(|tx_hash| format!("{:?}", tx_hash))
+                    .unwrap_or_else(|| "unknown".to_string());
+                warn!(
+                    "Merkle root 0x{} was already verified by a previous run, tx hash {}",
+                    hex::encode(merkle_root),
+                    tx_hash
+                );
+                return Ok(CycleOutcome::Submitted {
+                    proofs: proofs_count,
+                    tx_hash,
+                });
+            }
+        }
+
         info!("Starting proof aggregation program...");
-        let (aggregated_proof, zkvm_merkle_root) = self
-            .engine
-            .aggregate_proofs(proofs, self.config.proofs_per_chunk)
+        let (aggregated_proof, zkvm_merkle_root) = engine
+            .aggregate_proofs(
+                proofs,
+                self.config.proofs_per_chunk,
+                checkpoint.as_ref(),
+                self.config.proving_mode,
+                &mut stats.aggregation,
+            )
             .map_err(AggregatedProofSubmissionError::ZKVMAggregation)?;
         info!("Proof aggregation program finished");
 
@@ -128,63 +713,507 @@ impl ProofAggregator {
         }
         info!("Merkle root verification successful: roots match");
 
-        info!("Constructing blob...");
-        let (blob, blob_versioned_hash) = self.construct_blob(leaves).await?;
-        info!(
-            "Blob constructed, versioned hash: {}",
-            hex::encode(blob_versioned_hash)
-        );
+        info!("Simulating the verification transaction...");
+        let tx = self.verify_transaction_request(blob, blob_versioned_hash, aggregated_proof)?;
+        let verification_gas = self
+            .proof_aggregation_service
+            .provider()
+            .estimate_gas(tx.clone())
+            .await
+            .map_err(AggregatedProofSubmissionError::SimulatingVerification)?;
+        info!("Verification simulated, estimated gas {}", verification_gas);
+        if let Err(err) = self
+            .verification_gas_estimates
+            .record(&engine, verification_gas)
+        {
+            warn!("Could not persist verification gas estimate: {:?}", err);
+        }
+
+        if let Some(cost_policy) = &self.config.cost_policy {
+            let cost = self
+                .submission_cost(&engine, stats.blobs, cost_policy)
+                .await?;
+            let cost_per_proof_wei = cost.per_proof_wei(proofs_count);
+            stats.cost_per_proof_wei = Some(cost_per_proof_wei);
+            // The proving is already paid for, deferring now would waste it
+            if cost_per_proof_wei > cost_policy.max_cost_per_proof_wei {
+                warn!(
+                    "Cost per proof rose to {} wei after proving, over {} wei, submitting anyway",
+                    cost_per_proof_wei, cost_policy.max_cost_per_proof_wei
+                );
+            }
+        }
 
         info!("Sending proof to ProofAggregationService contract...");
         let receipt = self
-            .send_proof_to_verify_on_chain(blob, blob_versioned_hash, aggregated_proof)
+            .send_proof_to_verify_on_chain(tx, checkpoint.as_mut(), &mut stats.tx_replacements)
             .await?;
         info!(
             "Proof sent and verified, tx hash {:?}",
             receipt.transaction_hash
         );
 
-        Ok(())
+        Ok(self
+            .finish_submission(
+                receipt,
+                proofs_count,
+                merkle_root,
+                blob_versioned_hashes,
+                leaves,
+                stats,
+            )
+            .await)
     }
 
-    async fn send_proof_to_verify_on_chain(
+    /// Publishes the manifest of a verified aggregation, if configured, and reports it as submitted.
+    async fn finish_submission(
         &self,
-        blob: BlobTransactionSidecar,
-        blob_versioned_hash: [u8; 32],
-        aggregated_proof: AlignedProof,
-    ) -> Result<TransactionReceipt, AggregatedProofSubmissionError> {
-        let res = match aggregated_proof {
-            AlignedProof::SP1(proof) => {
+        receipt: TransactionReceipt,
+        proofs_count: usize,
+        merkle_root: [u8; 32],
+        blob_versioned_hashes: Vec<[u8; 32]>,
+        leaves: Vec<[u8; 32]>,
+        stats: &mut CycleStats,
+    ) -> CycleOutcome {
+        stats.gas_used = Some(receipt.gas_used);
+
+        // Lets users get the leaves once the blobs are pruned, the proofs are verified either way
+        if let Some(manifest_storage) = &self.config.manifest_storage {
+            let manifest = AggregationManifest {
+                merkle_root: merkle_root.into(),
+                blob_versioned_hashes: blob_versioned_hashes.into_iter().map(Into::into).collect(),
+                leaves: leaves.into_iter().map(Into::into).collect(),
+                tx_hash: receipt.transaction_hash.0.into(),
+                block_number: receipt.block_number.unwrap_or_default(),
+            };
+            match publish_manifest(manifest_storage, &manifest).await {
+                Ok(key) => {
+                    info!("Aggregation manifest published to {}", key);
+                    stats.manifest_key = Some(key);
+                }
+                Err(err) => {
+                    warn!(
+                        "Could not publish aggregation manifest, retrying in the next cycle: {:?}",
+                        err
+                    );
+                    if let Err(err) =
+                        persist_pending_manifest(&self.config.checkpoint_dir(), &manifest)
+                    {
+                        error!(
+                            "Could not keep aggregation manifest to publish it later: {:?}",
+                            err
+                        );
+                    }
+                }
+            }
+        }
+
+        CycleOutcome::Submitted {
+            proofs: proofs_count,
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+        }
+    }
+
+    /// Checks the proving mode against the verifier `AlignedProofAggregationService` uses for the engine,
+    /// so mocked proofs are not proven and sent to a contract that would reject them.
+    /// Mocked proofs are refused outside devnet, even if its verifier is the mock one.
+    async fn check_verifier(
+        &self,
+        engine: &ZKVMEngine,
+    ) -> Result<(), AggregatedProofSubmissionError> {
+        if self.config.proving_mode == ProvingMode::Mock {
+            let chain_id = self
+                .proof_aggregation_service
+                .provider()
+                .get_chain_id()
+                .await
+                .map_err(AggregatedProofSubmissionError::FetchingChainId)?;
+            if chain_id != DEVNET_CHAIN_ID {
+                return Err(AggregatedProofSubmissionError::MockProofsOutsideDevnet(
+                    chain_id,
+                ));
+            }
+        }
+
+        let verifier_address = match engine {
+            ZKVMEngine::SP1 => {
                 self.proof_aggregation_service
-                    .verifySP1(
-                        blob_versioned_hash.into(),
-                        proof.proof_with_pub_values.public_values.to_vec().into(),
-                        proof.proof_with_pub_values.bytes().into(),
-                    )
-                    .sidecar(blob)
-                    .send()
+                    .sp1VerifierAddress()
+                    .call()
+                    .await
+            }
+            ZKVMEngine::RISC0 => {
+                self.proof_aggregation_service
+                    .risc0VerifierAddress()
+                    .call()
                     .await
             }
+        }
+        .map_err(AggregatedProofSubmissionError::ReadingVerifierAddress)?;
+        let mock_verifier_address = self
+            .proof_aggregation_service
+            .VERIFIER_MOCK_ADDRESS()
+            .call()
+            .await
+            .map_err(AggregatedProofSubmissionError::ReadingVerifierAddress)?;
+
+        match self.config.proving_mode {
+            ProvingMode::Mock if verifier_address != mock_verifier_address => Err(
+                AggregatedProofSubmissionError::MockProofsWithRealVerifier(engine.clone()),
+            ),
+            ProvingMode::Real if verifier_address == mock_verifier_address => {
+                warn!(
+                    "{} verifier is the mock verifier, aggregated proofs are not verified on chain",
+                    engine
+                );
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Cost of verifying an aggregated proof of `engine` in `blobs` blobs at the current fees.
+    async fn submission_cost(
+        &self,
+        engine: &ZKVMEngine,
+        blobs: usize,
+        cost_policy: &CostPolicyConfig,
+    ) -> Result<SubmissionCost, AggregatedProofSubmissionError> {
+        let fees = self.network_fees().await?;
+
+        Ok(SubmissionCost {
+            verification_gas: self
+                .verification_gas_estimates
+                .get(engine)
+                .unwrap_or(cost_policy.verification_gas_estimate),
+            gas_price: fees.base_fee + fees.priority_fee,
+            blob_base_fee: fees.blob_base_fee,
+            blobs,
+        })
+    }
+
+    async fn network_fees(&self) -> Result<NetworkFees, AggregatedProofSubmissionError> {
+        let provider = self.proof_aggregation_service.provider();
+        let block = provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await
+            .map_err(|e| AggregatedProofSubmissionError::FetchingFees(e.to_string()))?
+            .ok_or_else(|| {
+                AggregatedProofSubmissionError::FetchingFees("Latest block not found".to_string())
+            })?;
+        let base_fee = block.header.base_fee_per_gas.unwrap_or_default() as u128;
+        let priority_fee = provider
+            .get_max_priority_fee_per_gas()
+            .await
+            .map_err(|e| AggregatedProofSubmissionError::FetchingFees(e.to_string()))?;
+        let blob_base_fee = provider
+            .get_blob_base_fee()
+            .await
+            .map_err(|e| AggregatedProofSubmissionError::FetchingFees(e.to_string()))?;
+
+        Ok(NetworkFees {
+            base_fee,
+            priority_fee,
+            blob_base_fee,
+        })
+    }
+
+    fn verify_transaction_request(
+        &self,
+        blob: BlobTransactionSidecar,
+        blob_versioned_hash: [u8; 32],
+        aggregated_proof: AlignedProof,
+    ) -> Result<TransactionRequest, AggregatedProofSubmissionError> {
+        match aggregated_proof {
+            AlignedProof::SP1(proof) => Ok(self
+                .proof_aggregation_service
+                .verifySP1(
+                    blob_versioned_hash.into(),
+                    proof.proof_with_pub_values.public_values.to_vec().into(),
+                    proof.proof_with_pub_values.bytes().into(),
+                )
+                .sidecar(blob)
+                .into_transaction_request()),
             AlignedProof::Risc0(proof) => {
                 let encoded_seal = encode_seal(&proof.receipt).map_err(|e| {
                     AggregatedProofSubmissionError::Risc0EncodingSeal(e.to_string())
                 })?;
-                self.proof_aggregation_service
+                Ok(self
+                    .proof_aggregation_service
                     .verifyRisc0(
                         blob_versioned_hash.into(),
                         encoded_seal.into(),
                         proof.receipt.journal.bytes.into(),
                     )
                     .sidecar(blob)
-                    .send()
+                    .into_transaction_request())
+            }
+            // Groth16 proofs are aggregated by the SP1 aggregator, they are never the result
+            AlignedProof::Groth16(_) => {
+                Err(AggregatedProofSubmissionError::UnsupportedAggregatedProof)
+            }
+        }
+    }
+
+    /// Sends the verification transaction and waits up to `receipt_timeout_secs` for it to be included.
+    /// When it is not, it is replaced by one with the same nonce and bumped fees, up to `max_replacements` times.
+    ///
+    /// Only one of the transactions sharing the nonce can be included, so the receipts of all of them are polled:
+    /// a replaced transaction may still be included before its replacement. Each sent transaction is recorded
+    /// in the `checkpoint` before waiting for it, and the ones a previous run sent with the same nonce are polled too.
+    async fn send_proof_to_verify_on_chain(
+        &self,
+        mut tx: TransactionRequest,
+        checkpoint: Option<&mut AggregationCheckpoint>,
+        replacements: &mut usize,
+    ) -> Result<TransactionReceipt, AggregatedProofSubmissionError> {
+        // The mined nonce, a verification left pending by a previous cycle was settled at the start of this one
+        let nonce = self.mined_nonce().await?;
+        tx.nonce = Some(nonce);
+
+        let sent_tx_hashes: Vec<TxHash> = match checkpoint.as_deref() {
+            Some(checkpoint) => match checkpoint.sent_transactions() {
+                (Some(sent_nonce), tx_hashes) if sent_nonce == nonce => tx_hashes,
+                _ => vec![],
+            },
+            None => vec![],
+        };
+        let receipt = self
+            .send_until_included(tx, sent_tx_hashes, None, checkpoint, replacements)
+            .await?;
+        check_receipt_status(receipt)
+    }
+
+    /// A verification transaction left pending by a previous cycle, e.g. one that ran out of replacements
+    /// or a run that died while waiting for it, holds the nonce the next verification would be sent with.
+    /// It is waited for up to `receipt_timeout_secs` and, if it is still not included, replaced with bumped
+    /// fees like any other verification. Its nonce is free once this returns.
+    async fn settle_pending_verification(
+        &self,
+        replacements: &mut usize,
+    ) -> Result<(), AggregatedProofSubmissionError> {
+        let checkpoint_dir = self.config.checkpoint_dir();
+        let Some(pending) = PendingVerification::load(&checkpoint_dir) else {
+            return Ok(());
+        };
+
+        let receipt = if self.mined_nonce().await? > pending.nonce {
+            self.find_receipt(&pending.tx_hashes).await
+        } else {
+            info!(
+                "Waiting for the verification transaction sent by a previous cycle with nonce {}",
+                pending.nonce
+            );
+            let receipt_timeout = Duration::from_secs(self.config.submission.receipt_timeout_secs);
+            match self
+                .wait_for_receipt(&pending.tx_hashes, receipt_timeout)
+                .await
+            {
+                Some(receipt) => Some(receipt),
+                None => {
+                    warn!(
+                        "Verification transaction sent by a previous cycle not included, replacing it"
+                    );
+                    Some(
+                        self.send_until_included(
+                            pending.tx,
+                            pending.tx_hashes,
+                            Some(pending.fees),
+                            None,
+                            replacements,
+                        )
+                        .await?,
+                    )
+                }
+            }
+        };
+
+        match receipt {
+            Some(receipt) if receipt.status() => info!(
+                "Verification transaction sent by a previous cycle was included, tx hash {:?}",
+                receipt.transaction_hash
+            ),
+            Some(receipt) => warn!(
+                "Verification transaction sent by a previous cycle reverted, tx hash {:?}",
+                receipt.transaction_hash
+            ),
+            None => warn!(
+                "Nonce {} of the verification sent by a previous cycle was taken by another transaction",
+                pending.nonce
+            ),
+        }
+        self.clear_pending_verification();
+
+        Ok(())
+    }
+
+    /// Sends `tx` with its nonce until one of the transactions sent with it is included, see
+    /// [`Self::send_proof_to_verify_on_chain`]. `sent_tx_hashes` were already sent with the nonce, and the
+    /// first transaction sent is bumped over `fees` when given.
+    ///
+    /// Every sent transaction is persisted as the [`PendingVerification`] before waiting for it,
+    /// which is cleared once one of them is included.
+    async fn send_until_included(
+        &self,
+        mut tx: TransactionRequest,
+        mut sent_tx_hashes: Vec<TxHash>,
+        mut fees: Option<TransactionFees>,
+        mut checkpoint: Option<&mut AggregationCheckpoint>,
+        replacements: &mut usize,
+    ) -> Result<TransactionReceipt, AggregatedProofSubmissionError> {
+        let submission = &self.config.submission;
+        let provider = self.proof_aggregation_service.provider();
+        let checkpoint_dir = self.config.checkpoint_dir();
+        let receipt_timeout = Duration::from_secs(submission.receipt_timeout_secs);
+        let nonce = tx.nonce.unwrap_or_default();
+
+        for _ in 0..=submission.max_replacements {
+            let network_fees = retry_rpc_call(move || self.network_fees()).await?;
+            let attempt_fees = match fees {
+                Some(fees) => fees.bumped(submission, &network_fees),
+                None => TransactionFees::from_network(&network_fees, submission),
+            };
+            fees = Some(attempt_fees);
+            tx.max_fee_per_gas = Some(attempt_fees.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(attempt_fees.max_priority_fee_per_gas);
+            tx.max_fee_per_blob_gas = Some(attempt_fees.max_fee_per_blob_gas);
+
+            let tx_request = &tx;
+            let sent = retry_rpc_call(move || async move {
+                provider
+                    .send_transaction(tx_request.clone())
                     .await
+                    .map(|pending_tx| *pending_tx.tx_hash())
+                    .map_err(|e| {
+                        AggregatedProofSubmissionError::SendVerifyAggregatedProofTransaction(
+                            e.into(),
+                        )
+                    })
+            })
+            .await;
+
+            match sent {
+                Ok(tx_hash) => {
+                    if !sent_tx_hashes.is_empty() {
+                        *replacements += 1;
+                    }
+                    info!(
+                        "Verification transaction sent with nonce {}, max fee per gas {}, max priority fee per gas {}, max fee per blob gas {}: {:?}",
+                        nonce,
+                        attempt_fees.max_fee_per_gas,
+                        attempt_fees.max_priority_fee_per_gas,
+                        attempt_fees.max_fee_per_blob_gas,
+                        tx_hash
+                    );
+                    sent_tx_hashes.push(tx_hash);
+                    let pending = PendingVerification {
+                        nonce,
+                        tx_hashes: sent_tx_hashes.clone(),
+                        fees: attempt_fees,
+                        tx: tx.clone(),
+                    };
+                    if let Err(err) = pending.save(&checkpoint_dir) {
+                        warn!("Could not persist the pending verification: {:?}", err);
+                    }
+                    if let Some(checkpoint) = checkpoint.as_deref_mut() {
+                        if let Err(err) = checkpoint.record_sent_transaction(nonce, tx_hash) {
+                            warn!(
+                                "Could not record the sent transaction in the checkpoint: {:?}",
+                                err
+                            );
+                        }
+                    }
+                }
+                // A pending transaction with the nonce pays more, bump again over it
+                Err(err) if err.is_underpriced_replacement() => {
+                    warn!(
+                        "Verification transaction underpriced, bumping its fees: {:?}",
+                        err
+                    );
+                    if sent_tx_hashes.is_empty() {
+                        continue;
+                    }
+                }
+                // The nonce may have been taken by one of the sent transactions since the last poll
+                Err(err) => {
+                    return match self.find_receipt(&sent_tx_hashes).await {
+                        Some(receipt) => {
+                            self.clear_pending_verification();
+                            Ok(receipt)
+                        }
+                        None => Err(err),
+                    };
+                }
+            }
+
+            if let Some(receipt) = self
+                .wait_for_receipt(&sent_tx_hashes, receipt_timeout)
+                .await
+            {
+                self.clear_pending_verification();
+                return Ok(receipt);
             }
+            warn!(
+                "Verification transaction not included after {}s",
+                submission.receipt_timeout_secs
+            );
         }
-        .map_err(AggregatedProofSubmissionError::SendVerifyAggregatedProofTransaction)?;
 
-        res.get_receipt()
-            .await
-            .map_err(AggregatedProofSubmissionError::ReceiptError)
+        Err(AggregatedProofSubmissionError::TransactionNotIncluded(
+            sent_tx_hashes,
+        ))
+    }
+
+    /// Called once the nonce of the pending verification is taken.
+    fn clear_pending_verification(&self) {
+        if let Err(err) = PendingVerification::clear(&self.config.checkpoint_dir()) {
+            warn!("Could not clear the pending verification: {:?}", err);
+        }
+    }
+
+    /// Nonce of the next transaction of the submitter, counting only the mined ones.
+    async fn mined_nonce(&self) -> Result<u64, AggregatedProofSubmissionError> {
+        let provider = self.proof_aggregation_service.provider();
+        let submitter_address = self.submitter_address;
+        retry_rpc_call(move || async move {
+            provider
+                .get_transaction_count(submitter_address)
+                .await
+                .map_err(AggregatedProofSubmissionError::FetchingNonce)
+        })
+        .await
+    }
+
+    /// Polls the receipts of `tx_hashes` until one of them is found or `timeout` elapses.
+    async fn wait_for_receipt(
+        &self,
+        tx_hashes: &[TxHash],
+        timeout: Duration,
+    ) -> Option<TransactionReceipt> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(receipt) = self.find_receipt(tx_hashes).await {
+                return Some(receipt);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL.min(remaining)).await;
+        }
+    }
+
+    async fn find_receipt(&self, tx_hashes: &[TxHash]) -> Option<TransactionReceipt> {
+        let provider = self.proof_aggregation_service.provider();
+        for tx_hash in tx_hashes {
+            match provider.get_transaction_receipt(*tx_hash).await {
+                Ok(Some(receipt)) => return Some(receipt),
+                Ok(None) => {}
+                // Polled again later, until the receipt timeout
+                Err(e) => warn!("Could not get receipt of {:?}: {:?}", tx_hash, e),
+            }
+        }
+        None
     }
 
     /// ### Blob capacity
@@ -212,11 +1241,20 @@ impl ProofAggregator {
     ///
     /// `4096 * 31 = 126976 bytes`
     ///
-    /// Meaning that we can send as much as 126976 / 32 = 3968 proofs per blob
+    /// Meaning that we can send as much as 126976 / 32 = 3968 proofs per blob ([`PROOFS_PER_BLOB`]).
+    ///
+    /// More leaves don't fit, [`MAX_TOTAL_PROOFS_LIMIT`] caps the proofs fetched for an aggregation
+    /// so the rest are carried to the next cycle.
     async fn construct_blob(
         &self,
-        leaves: Vec<[u8; 32]>,
+        leaves: &[[u8; 32]],
     ) -> Result<(BlobTransactionSidecar, [u8; 32]), AggregatedProofSubmissionError> {
+        if leaves.len() > PROOFS_PER_BLOB {
+            return Err(AggregatedProofSubmissionError::TooManyLeavesForBlob(
+                leaves.len(),
+            ));
+        }
+
         let data: Vec<u8> = leaves.iter().flat_map(|arr| arr.iter().copied()).collect();
         let mut blob_data: [u8; BYTES_PER_BLOB] = [0u8; BYTES_PER_BLOB];
 
@@ -233,11 +1271,11 @@ impl ProofAggregator {
             offset += 32;
         }
 
-        // calculate kzg commitments for blob
-
         // This parameter is the optimal balance between performance and memory usage to load the trusted setup
         // Source: https://github.com/ethereum/c-kzg-4844?tab=readme-ov-file#precompute
         let settings = c_kzg::ethereum_kzg_settings(8);
+
+        // calculate kzg commitments for blob
         let blob = c_kzg::Blob::new(blob_data);
         let commitment = settings
             .blob_to_kzg_commitment(&blob)
@@ -259,3 +1297,78 @@ impl ProofAggregator {
         Ok((blob, blob_versioned_hash))
     }
 }
+
+/// Leftover proofs are carried to the next cycle by the fetcher,
+/// so never fetch more proofs than the blob can hold.
+fn capped_total_proofs_limit(total_proofs_limit: u16) -> u16 {
+    total_proofs_limit.min(MAX_TOTAL_PROOFS_LIMIT)
+}
+
+fn check_receipt_status(
+    receipt: TransactionReceipt,
+) -> Result<TransactionReceipt, AggregatedProofSubmissionError> {
+    if receipt.status() {
+        Ok(receipt)
+    } else {
+        Err(AggregatedProofSubmissionError::VerificationReverted(
+            receipt.transaction_hash,
+        ))
+    }
+}
+
+/// Calls `function` until it succeeds or fails with an error that is not retryable, see
+/// [`AggregatedProofSubmissionError::is_retryable`], with an exponential backoff.
+async fn retry_rpc_call<FutureFn, Fut, T>(
+    mut function: FutureFn,
+) -> Result<T, AggregatedProofSubmissionError>
+where
+    Fut: Future<Output = Result<T, AggregatedProofSubmissionError>>,
+    FutureFn: FnMut() -> Fut,
+{
+    retry_function(
+        || {
+            let call = function();
+            async move {
+                call.await.map_err(|e| {
+                    if e.is_retryable() {
+                        warn!("Retrying failed call: {:?}", e);
+                    }
+                    RetryError::from(e)
+                })
+            }
+        },
+        RPC_RETRY_MIN_DELAY_MS,
+        RPC_RETRY_FACTOR,
+        RPC_RETRY_MAX_TIMES,
+        RPC_RETRY_MAX_DELAY_SECS,
+    )
+    .await
+    .map_err(RetryError::inner)
+}
+
+async fn wait_for_shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("SIGTERM handler to be installed");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{capped_total_proofs_limit, MAX_TOTAL_PROOFS_LIMIT, PROOFS_PER_BLOB};
+
+    #[test]
+    fn proofs_per_blob_fit_in_a_blob() {
+        assert_eq!(PROOFS_PER_BLOB, 3968);
+    }
+
+    #[test]
+    fn total_proofs_limit_is_capped_to_the_blob_capacity() {
+        assert_eq!(MAX_TOTAL_PROOFS_LIMIT, 3968);
+        assert_eq!(capped_total_proofs_limit(3968), 3968);
+        assert_eq!(capped_total_proofs_limit(5000), 3968);
+        assert_eq!(capped_total_proofs_limit(u16::MAX), 3968);
+        assert_eq!(capped_total_proofs_limit(100), 100);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{checkpoint::write_file_atomically, fetcher::SkippedBatch};
+use crate::aggregators::{AggregationStats, ZKVMEngine};
+use alloy::hex;
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+const LATEST_RUN_REPORT_FILE: &str = "latest.json";
+
+/// Result of a fetch, aggregate and submit cycle of one engine.
+#[derive(Debug)]
+pub enum CycleOutcome {
+    /// The aggregated proof was sent and verified on chain
+    Submitted {
+        proofs: usize,
+        tx_hash: String,
+    },
+    /// There were no proofs to aggregate in the fetched blocks
+    NoProofs,
+    /// None of the daemon triggers was met, proofs are left for a later cycle
+    Skipped,
+    /// Submitting is over the configured cost per proof, proofs are left for a later cycle
+    Deferred {
+        cost_per_proof_wei: u128,
+    },
+    Failed(String),
+}
+
+impl CycleOutcome {
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Submitted { .. } => "submitted",
+            Self::NoProofs => "no_proofs",
+            Self::Skipped => "skipped",
+            Self::Deferred { .. } => "deferred",
+            Self::Failed(_) => "failed",
+        }
+    }
+
+    /// Whether the last aggregated block was advanced past the fetched blocks,
+    /// otherwise their proofs are fetched again by the next cycle.
+    pub fn consumed_range(&self) -> bool {
+        matches!(self, Self::Submitted { .. } | Self::NoProofs)
+    }
+}
+
+/// What a cycle went through, filled in as it advances so a failed cycle reports up to where it got.
+#[derive(Debug, Default)]
+pub struct CycleStats {
+    /// Proofs of the engine in the consumed batches, the excluded ones included
+    pub proofs_fetched: usize,
+    /// Proofs left out by the fetcher, as they can't be aggregated, or by the admission policy, by reason
+    pub proofs_excluded: BTreeMap<&'static str, usize>,
+    pub aggregation: AggregationStats,
+    pub blobs: usize,
+    /// Bytes of the leaves published in the blobs
+    pub blob_data_bytes: usize,
+    /// Gas used by the `verifySP1`/`verifyRisc0` transaction
+    pub gas_used: Option<u64>,
+    /// Times the verification transaction was replaced with bumped fees while not included
+    pub tx_replacements: usize,
+    /// Key the aggregation manifest was published to
+    pub manifest_key: Option<String>,
+    /// Estimated cost per proof of the verification, when a cost policy is set
+    pub cost_per_proof_wei: Option<u128>,
+    /// Name of the `AggregatedProofSubmissionError` that failed the cycle,
+    /// or `read_last_aggregated_block`/`update_last_aggregated_block` when the cycle failed reading or persisting its progress
+    pub error: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct CycleReport {
+    pub engine: ZKVMEngine,
+    pub outcome: CycleOutcome,
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Batches left out of the aggregation because their data is invalid
+    pub skipped_batches: Vec<SkippedBatch>,
+    pub stats: CycleStats,
+    pub duration: Duration,
+}
+
+impl CycleReport {
+    pub fn log(&self) {
+        let engine = self.engine.name();
+        let status = self.outcome.status();
+        let duration_secs = self.duration.as_secs();
+        let skipped_batches = self.skipped_batches.len();
+        let proofs_fetched = self.stats.proofs_fetched;
+        match &self.outcome {
+            CycleOutcome::Submitted { proofs, tx_hash } => info!(
+                engine,
+                status,
+                proofs,
+                tx_hash,
+                from_block = self.from_block,
+                to_block = self.to_block,
+                proofs_fetched,
+                skipped_batches,
+                chunks_proven = self.stats.aggregation.chunks_proven,
+                blobs = self.stats.blobs,
+                gas_used = self.stats.gas_used,
+                tx_replacements = self.stats.tx_replacements,
+                duration_secs,
+                "Cycle finished"
+            ),
+            CycleOutcome::NoProofs => info!(
+                engine,
+                status,
+                from_block = self.from_block,
+                to_block = self.to_block,
+                proofs_fetched,
+                skipped_batches,
+                duration_secs,
+                "Cycle finished"
+            ),
+            CycleOutcome::Skipped => info!(
+                engine,
+                status,
+                from_block = self.from_block,
+                duration_secs,
+                "Cycle finished"
+            ),
+            CycleOutcome::Deferred { cost_per_proof_wei } => info!(
+                engine,
+                status,
+                from_block = self.from_block,
+                proofs_fetched,
+                cost_per_proof_wei = cost_per_proof_wei.to_string(),
+                duration_secs,
+                "Cycle finished"
+            ),
+            CycleOutcome::Failed(reason) => error!(
+                engine,
+                status,
+                reason,
+                error = self.stats.error,
+                tx_replacements = self.stats.tx_replacements,
+                from_block = self.from_block,
+                duration_secs,
+                "Cycle finished"
+            ),
+        }
+
+        for batch in &self.skipped_batches {
+            warn!(
+                engine,
+                block_number = batch.block_number,
+                batch_merkle_root = format!("0x{}", hex::encode(batch.batch_merkle_root)),
+                reason = batch.reason,
+                "Batch skipped"
+            );
+        }
+    }
+}
+
+/// Report of a run, i.e. the cycles of every engine, written as JSON to the reports directory.
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    /// Unix timestamps in seconds
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub cycles: Vec<CycleSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycleSummary {
+    pub engine: ZKVMEngine,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    pub from_block: u64,
+    /// Last aggregated block of the engine after the cycle
+    pub to_block: u64,
+    pub proofs_fetched: usize,
+    pub proofs_excluded: BTreeMap<&'static str, usize>,
+    pub proofs_aggregated: usize,
+    pub skipped_batches: Vec<SkippedBatchSummary>,
+    pub chunks_proven: usize,
+    pub chunks_restored: usize,
+    pub chunk_proving_secs: Vec<f64>,
+    pub final_aggregation_secs: f64,
+    pub blobs: usize,
+    pub blob_data_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<u64>,
+    pub tx_replacements: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_per_proof_wei: Option<u128>,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedBatchSummary {
+    pub block_number: u64,
+    pub batch_merkle_root: String,
+    pub reason: String,
+}
+
+impl From<&CycleReport> for CycleSummary {
+    fn from(report: &CycleReport) -> Self {
+        let (reason, tx_hash, proofs_aggregated) = match &report.outcome {
+            CycleOutcome::Submitted { proofs, tx_hash } => (None, Some(tx_hash.clone()), *proofs),
+            CycleOutcome::Failed(reason) => (Some(reason.clone()), None, 0),
+            CycleOutcome::NoProofs | CycleOutcome::Skipped | CycleOutcome::Deferred { .. } => {
+                (None, None, 0)
+            }
+        };
+        let aggregation = &report.stats.aggregation;
+
+        Self {
+            engine: report.engine.clone(),
+            status: report.outcome.status(),
+            reason,
+            error: report.stats.error,
+            tx_hash,
+            from_block: report.from_block,
+            to_block: report.to_block,
+            proofs_fetched: report.stats.proofs_fetched,
+            proofs_excluded: report.stats.proofs_excluded.clone(),
+            proofs_aggregated,
+            skipped_batches: report
+                .skipped_batches
+                .iter()
+                .map(|batch| SkippedBatchSummary {
+                    block_number: batch.block_number,
+                    batch_merkle_root: format!("0x{}", hex::encode(batch.batch_merkle_root)),
+                    reason: batch.reason.clone(),
+                })
+                .collect(),
+            chunks_proven: aggregation.chunks_proven,
+            chunks_restored: aggregation.chunks_restored,
+            chunk_proving_secs: aggregation
+                .chunk_proving_durations
+                .iter()
+                .map(Duration::as_secs_f64)
+                .collect(),
+            final_aggregation_secs: aggregation.final_aggregation_duration.as_secs_f64(),
+            blobs: report.stats.blobs,
+            blob_data_bytes: report.stats.blob_data_bytes,
+            gas_used: report.stats.gas_used,
+            tx_replacements: report.stats.tx_replacements,
+            manifest_key: report.stats.manifest_key.clone(),
+            cost_per_proof_wei: report.stats.cost_per_proof_wei,
+            duration_secs: report.duration.as_secs_f64(),
+        }
+    }
+}
+
+impl RunReport {
+    pub fn new(started_at: SystemTime, reports: &[CycleReport]) -> Self {
+        Self {
+            started_at: unix_secs(started_at),
+            finished_at: unix_secs(SystemTime::now()),
+            cycles: reports.iter().map(CycleSummary::from).collect(),
+        }
+    }
+
+    /// Writes the report to `run_<started_at>.json` and `latest.json` in `reports_dir`,
+    /// keeping the newest `retention` run reports.
+    pub fn write(&self, reports_dir: &str, retention: usize) -> std::io::Result<()> {
+        let reports_dir = Path::new(reports_dir);
+        fs::create_dir_all(reports_dir)?;
+
+        let content = serde_json::to_vec_pretty(self)?;
+        write_file_atomically(
+            &reports_dir.join(format!("run_{}.json", self.started_at)),
+            &content,
+        )?;
+        write_file_atomically(&reports_dir.join(LATEST_RUN_REPORT_FILE), &content)?;
+
+        // Names hold the start timestamp, so sorting them sorts the runs
+        let mut run_reports: Vec<_> = fs::read_dir(reports_dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("run_") && name.ends_with(".json"))
+            })
+            .collect();
+        run_reports.sort();
+        let stale = run_reports.len().saturating_sub(retention);
+        for path in &run_reports[..stale] {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
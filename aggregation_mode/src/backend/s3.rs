@@ -1,4 +1,12 @@
 use aligned_sdk::common::types::VerificationData;
+use aws_config::{meta::region::RegionProviderChain, BehaviorVersion};
+use aws_sdk_s3::{
+    error::SdkError,
+    operation::put_object::{PutObjectError, PutObjectOutput},
+    primitives::ByteStream,
+    Client,
+};
+use tracing::info;
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -13,9 +21,8 @@ pub enum GetBatchProofsError {
 // needed to make S3 bucket work
 const DEFAULT_USER_AGENT: &str = "proof-aggregator/aligned-layer";
 
-pub async fn get_aligned_batch_from_s3(
-    url: String,
-) -> Result<Vec<VerificationData>, GetBatchProofsError> {
+/// Downloads the raw, CBOR encoded, batch. See [`deserialize_aligned_batch`].
+pub async fn get_aligned_batch_bytes_from_s3(url: String) -> Result<Vec<u8>, GetBatchProofsError> {
     let client = reqwest::Client::builder()
         .user_agent(DEFAULT_USER_AGENT)
         .build()
@@ -41,10 +48,45 @@ pub async fn get_aligned_batch_from_s3(
         .bytes()
         .await
         .map_err(|e| GetBatchProofsError::EmptyBody(e.to_string()))?;
-    let bytes: &[u8] = bytes.iter().as_slice();
 
-    let data: Vec<VerificationData> = ciborium::from_reader(bytes)
-        .map_err(|e| GetBatchProofsError::Deserialization(e.to_string()))?;
+    Ok(bytes.to_vec())
+}
+
+/// Client to upload the aggregation manifests. `endpoint_url` points it to a non-aws storage.
+pub async fn create_client(endpoint_url: Option<String>) -> Client {
+    let region_provider = RegionProviderChain::default_provider().or_else("us-east-2");
+    let mut config = aws_config::defaults(BehaviorVersion::latest()).region(region_provider);
+    if let Some(endpoint_url) = &endpoint_url {
+        info!("Using custom endpoint: {}", endpoint_url);
+        config = config.endpoint_url(endpoint_url);
+    }
+    let config = config.load().await;
+
+    let mut s3_config_builder = aws_sdk_s3::config::Builder::from(&config);
+    if endpoint_url.is_some() {
+        s3_config_builder = s3_config_builder.force_path_style(true);
+    }
+    Client::from_conf(s3_config_builder.build())
+}
+
+pub async fn upload_json_object(
+    client: &Client,
+    bucket_name: &str,
+    bytes: Vec<u8>,
+    key: &str,
+) -> Result<PutObjectOutput, SdkError<PutObjectError>> {
+    client
+        .put_object()
+        .bucket(bucket_name)
+        .key(key)
+        .content_type("application/json")
+        .body(ByteStream::from(bytes))
+        .send()
+        .await
+}
 
-    Ok(data)
+pub fn deserialize_aligned_batch(
+    bytes: &[u8],
+) -> Result<Vec<VerificationData>, GetBatchProofsError> {
+    ciborium::from_reader(bytes).map_err(|e| GetBatchProofsError::Deserialization(e.to_string()))
 }
@@ -0,0 +1,259 @@
+use std::{fs, io, path::Path};
+
+use super::{checkpoint::write_file_atomically, cost::NetworkFees};
+use alloy::{primitives::TxHash, rpc::types::TransactionRequest};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+const PENDING_VERIFICATION_FILE: &str = "pending_verification.json";
+
+/// How the verification transaction is sent and replaced while it is not included.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubmissionConfig {
+    /// Seconds to wait for the transaction to be included before replacing it.
+    #[serde(default = "default_receipt_timeout_secs")]
+    pub receipt_timeout_secs: u64,
+    /// Percentage every fee of a replacement is raised over the replaced transaction.
+    /// Nodes reject blob transaction replacements bumped by less than 100%.
+    #[serde(default = "default_fee_bump_percentage")]
+    pub fee_bump_percentage: u64,
+    /// Replacements sent before giving up on the cycle, its proofs are retried in the next one.
+    #[serde(default = "default_max_replacements")]
+    pub max_replacements: usize,
+    /// Minimum priority fee per gas, in wei, of the transaction and its replacements.
+    /// A zero priority fee would stay zero however much it is bumped.
+    #[serde(default = "default_min_priority_fee_per_gas")]
+    pub min_priority_fee_per_gas: u128,
+}
+
+impl Default for SubmissionConfig {
+    fn default() -> Self {
+        Self {
+            receipt_timeout_secs: default_receipt_timeout_secs(),
+            fee_bump_percentage: default_fee_bump_percentage(),
+            max_replacements: default_max_replacements(),
+            min_priority_fee_per_gas: default_min_priority_fee_per_gas(),
+        }
+    }
+}
+
+fn default_receipt_timeout_secs() -> u64 {
+    180
+}
+
+fn default_fee_bump_percentage() -> u64 {
+    100
+}
+
+fn default_max_replacements() -> usize {
+    3
+}
+
+fn default_min_priority_fee_per_gas() -> u128 {
+    // 1 gwei
+    1_000_000_000
+}
+
+/// Fees the verification transaction is sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransactionFees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_blob_gas: u128,
+}
+
+impl TransactionFees {
+    /// Fees that keep the transaction includable if the base fees double before it is,
+    /// with a priority fee of at least `min_priority_fee_per_gas`.
+    pub fn from_network(fees: &NetworkFees, config: &SubmissionConfig) -> Self {
+        let priority_fee = fees.priority_fee.max(config.min_priority_fee_per_gas);
+        Self {
+            max_fee_per_gas: 2 * fees.base_fee + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+            max_fee_per_blob_gas: 2 * fees.blob_base_fee,
+        }
+    }
+
+    /// Fees to replace a transaction sent with these ones: each of them raised by `fee_bump_percentage`,
+    /// as nodes require to accept the replacement, and never under the ones of `current` network fees.
+    pub fn bumped(&self, config: &SubmissionConfig, current: &NetworkFees) -> Self {
+        let current = Self::from_network(current, config);
+        let bump = |previous: u128, current: u128| {
+            (previous * (100 + config.fee_bump_percentage as u128))
+                .div_ceil(100)
+                .max(current)
+        };
+
+        Self {
+            max_fee_per_gas: bump(self.max_fee_per_gas, current.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(
+                self.max_priority_fee_per_gas,
+                current.max_priority_fee_per_gas,
+            ),
+            max_fee_per_blob_gas: bump(self.max_fee_per_blob_gas, current.max_fee_per_blob_gas),
+        }
+    }
+}
+
+/// Verification transaction sent and not known to be included yet, persisted to the checkpoint directory
+/// before waiting for it. While it is pending its nonce can't be used by the next verification, so the next
+/// cycle waits for it or replaces it first, see `ProofAggregator::settle_pending_verification`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PendingVerification {
+    pub nonce: u64,
+    /// Hashes of every transaction sent with the nonce, any of them may be the one included
+    pub tx_hashes: Vec<TxHash>,
+    /// Fees of the last sent transaction, a replacement must be bumped over them
+    pub fees: TransactionFees,
+    /// The last sent transaction, along with its blobs
+    pub tx: TransactionRequest,
+}
+
+impl PendingVerification {
+    /// Loads the pending verification persisted in `checkpoint_dir`, none if there isn't one or it can't be read.
+    pub fn load(checkpoint_dir: &str) -> Option<Self> {
+        let content = fs::read(Path::new(checkpoint_dir).join(PENDING_VERIFICATION_FILE)).ok()?;
+        serde_json::from_slice(&content)
+            .inspect_err(|e| warn!("Ignoring invalid pending verification: {}", e))
+            .ok()
+    }
+
+    pub fn save(&self, checkpoint_dir: &str) -> io::Result<()> {
+        fs::create_dir_all(checkpoint_dir)?;
+        write_file_atomically(
+            &Path::new(checkpoint_dir).join(PENDING_VERIFICATION_FILE),
+            &serde_json::to_vec(self)?,
+        )
+    }
+
+    /// Removes the pending verification once its nonce is taken.
+    pub fn clear(checkpoint_dir: &str) -> io::Result<()> {
+        match fs::remove_file(Path::new(checkpoint_dir).join(PENDING_VERIFICATION_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PendingVerification, SubmissionConfig, TransactionFees};
+    use crate::backend::cost::NetworkFees;
+    use alloy::{primitives::TxHash, rpc::types::TransactionRequest};
+
+    fn network_fees(base_fee: u128, priority_fee: u128, blob_base_fee: u128) -> NetworkFees {
+        NetworkFees {
+            base_fee,
+            priority_fee,
+            blob_base_fee,
+        }
+    }
+
+    fn fees(
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+        max_fee_per_blob_gas: u128,
+    ) -> TransactionFees {
+        TransactionFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            max_fee_per_blob_gas,
+        }
+    }
+
+    fn config(fee_bump_percentage: u64, min_priority_fee_per_gas: u128) -> SubmissionConfig {
+        SubmissionConfig {
+            fee_bump_percentage,
+            min_priority_fee_per_gas,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fees_cover_twice_the_base_fees_and_the_min_priority_fee() {
+        let config = config(100, 5);
+        assert_eq!(
+            TransactionFees::from_network(&network_fees(100, 10, 3), &config),
+            fees(210, 10, 6)
+        );
+        assert_eq!(
+            TransactionFees::from_network(&network_fees(100, 0, 3), &config),
+            fees(205, 5, 6)
+        );
+    }
+
+    #[test]
+    fn bumped_fees_are_raised_by_the_percentage() {
+        let previous = fees(1_000, 100, 50);
+        let idle_network = network_fees(0, 0, 0);
+
+        assert_eq!(
+            previous.bumped(&config(100, 0), &idle_network),
+            fees(2_000, 200, 100)
+        );
+        // Rounded up, so a replacement is never under the required bump
+        assert_eq!(
+            fees(3, 3, 3).bumped(&config(10, 0), &idle_network),
+            fees(4, 4, 4)
+        );
+    }
+
+    #[test]
+    fn bumped_fees_are_never_under_the_current_network_fees() {
+        let previous = fees(1_000, 100, 50);
+        assert_eq!(
+            previous.bumped(&config(100, 0), &network_fees(1_000, 150, 10)),
+            fees(2_150, 200, 100)
+        );
+    }
+
+    #[test]
+    fn bumped_fees_of_a_zero_priority_fee_get_the_min_priority_fee() {
+        let previous = fees(100, 0, 2);
+        let bumped = previous.bumped(&config(100, 7), &network_fees(10, 0, 1));
+        assert_eq!(bumped, fees(200, 7, 4));
+        assert!(bumped.max_fee_per_gas >= bumped.max_priority_fee_per_gas);
+
+        // Bumped over the previous one from then on
+        assert_eq!(
+            bumped.bumped(&config(100, 7), &network_fees(10, 0, 1)),
+            fees(400, 14, 8)
+        );
+    }
+
+    #[test]
+    fn pending_verification_is_persisted_until_cleared() {
+        let dir = std::env::temp_dir().join(format!(
+            "aligned-aggregator-pending-verification-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let checkpoint_dir = dir.to_string_lossy().to_string();
+        assert!(PendingVerification::load(&checkpoint_dir).is_none());
+
+        PendingVerification {
+            nonce: 7,
+            tx_hashes: vec![TxHash::repeat_byte(1), TxHash::repeat_byte(2)],
+            fees: fees(200, 20, 10),
+            tx: TransactionRequest::default().nonce(7),
+        }
+        .save(&checkpoint_dir)
+        .unwrap();
+
+        let pending = PendingVerification::load(&checkpoint_dir).unwrap();
+        assert_eq!(pending.nonce, 7);
+        assert_eq!(
+            pending.tx_hashes,
+            vec![TxHash::repeat_byte(1), TxHash::repeat_byte(2)]
+        );
+        assert_eq!(pending.fees, fees(200, 20, 10));
+        assert_eq!(pending.tx.nonce, Some(7));
+
+        PendingVerification::clear(&checkpoint_dir).unwrap();
+        assert!(PendingVerification::load(&checkpoint_dir).is_none());
+        // Clearing it again is a no-op
+        PendingVerification::clear(&checkpoint_dir).unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 use std::env;
 
-use proof_aggregator::backend::{config::Config, ProofAggregator};
+use proof_aggregator::{
+    aggregators::{risc0_aggregator, ProvingMode},
+    backend::{config::Config, ProofAggregator},
+};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 fn read_config_filepath_from_args() -> String {
@@ -15,8 +18,7 @@ fn read_config_filepath_from_args() -> String {
     args[1].clone()
 }
 
-#[tokio::main]
-async fn main() {
+fn main() {
     // ignore sp1_cuda info logs
     let filter = EnvFilter::new("info,sp1_cuda=warn");
     let subscriber = FmtSubscriber::builder().with_env_filter(filter).finish();
@@ -25,9 +27,24 @@ async fn main() {
     // load config
     let config_file_path = read_config_filepath_from_args();
     tracing::info!("Loading config from {}...", config_file_path);
-    let config = Config::from_file(&config_file_path).expect("Config is valid");
+    let mut config = Config::from_file(&config_file_path).expect("Config is valid");
     tracing::info!("Config loaded");
 
-    let mut proof_aggregator = ProofAggregator::new(config);
-    proof_aggregator.start().await;
+    // Setting env variables is only sound before other threads are spawned, i.e. before the runtime starts
+    if config.proving_mode == ProvingMode::Mock {
+        risc0_aggregator::enable_dev_mode();
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
+    runtime.block_on(async {
+        let daemon_config = config.daemon.take();
+        let mut proof_aggregator = ProofAggregator::new(config);
+        match daemon_config {
+            Some(daemon_config) => proof_aggregator.start_daemon(daemon_config).await,
+            None => proof_aggregator.start().await,
+        }
+    });
 }
@@ -0,0 +1,54 @@
+#!/bin/bash
+
+# ENV VARIABLES
+#
+# MULTISIG=true|false whether the contract is deployed under a multisig account
+#
+# PROOF_AGGREGATOR_OUTPUT_PATH: Path to the proof aggregator output file
+#   - Holesky Stage: ./script/output/holesky/proof_aggregation_service_deployment_output.stage.json
+#   - Holesky Prod: ./script/output/holesky/proof_aggregation_service_deployment_output.json
+#
+# RPC_URL: The RPC URL to connect to the Ethereum network
+#
+# PRIVATE_KEY: The private key of the contract owner
+#
+# Sets the chunk aggregator program ids of aggregation_mode/programs_ids.json in the
+# AlignedProofAggregationService contract
+
+if [ -z "$MULTISIG" ]; then
+  echo "Missing MULTISIG env variable"
+  exit 1
+fi
+
+# cd to the directory of this script so that this can be run from anywhere
+parent_path=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+
+cd "$parent_path"
+
+cd ../
+
+PROGRAM_IDS_PATH=../aggregation_mode/programs_ids.json
+
+proof_aggregator_service_proxy=$(jq -r '.addresses.alignedProofAggregationService' $PROOF_AGGREGATOR_OUTPUT_PATH)
+sp1_vk_hash=$(jq -r '.sp1_chunk_aggregator_vk_hash' $PROGRAM_IDS_PATH)
+risc0_image_id=$(jq -r '.risc0_chunk_aggregator_image_id' $PROGRAM_IDS_PATH)
+
+echo "SP1 aggregator program vk hash: $sp1_vk_hash"
+echo "Risc0 aggregator program image id: $risc0_image_id"
+
+sp1_data=$(cast calldata "setSP1AggregatorProgramVKHash(bytes32)" $sp1_vk_hash)
+risc0_data=$(cast calldata "setRisc0AggregatorProgramImageId(bytes32)" $risc0_image_id)
+
+if [ "$MULTISIG" = false ]; then
+  echo "Executing program ids update transactions"
+  cast send $proof_aggregator_service_proxy $sp1_data \
+    --rpc-url $RPC_URL \
+    --private-key $PRIVATE_KEY
+  cast send $proof_aggregator_service_proxy $risc0_data \
+    --rpc-url $RPC_URL \
+    --private-key $PRIVATE_KEY
+else
+  echo "You can propose the program ids update transactions with the multisig using this calldata"
+  echo $sp1_data
+  echo $risc0_data
+fi
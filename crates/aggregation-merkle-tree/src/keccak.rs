@@ -0,0 +1,28 @@
+#[cfg(not(any(feature = "sha3", feature = "tiny-keccak")))]
+compile_error!("one of the `sha3` or `tiny-keccak` features must be enabled");
+
+/// Keccak256 of the concatenation of `chunks`.
+#[cfg(feature = "tiny-keccak")]
+pub fn keccak256(chunks: &[&[u8]]) -> [u8; 32] {
+    use tiny_keccak::{Hasher, Keccak};
+
+    let mut hasher = Keccak::v256();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Keccak256 of the concatenation of `chunks`.
+#[cfg(all(feature = "sha3", not(feature = "tiny-keccak")))]
+pub fn keccak256(chunks: &[&[u8]]) -> [u8; 32] {
+    use sha3::{Digest, Keccak256};
+
+    let mut hasher = Keccak256::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    hasher.finalize().into()
+}
@@ -0,0 +1,351 @@
+//! Merkle tree of the proofs aggregated in aggregation mode.
+//!
+//! Shared by the aggregation programs, the proof aggregator and the SDK, so the root
+//! committed by the programs, the one verified on chain and the ones rebuilt by users match.
+//!
+//! Each leaf is the commitment of a proof, `keccak(program_id || public_inputs)`, and
+//! parents are the commutative keccak of their children, as OpenZeppelin's `MerkleProof`
+//! which `AlignedProofAggregationService.verifyProofInclusion` checks the paths with.
+#![no_std]
+
+extern crate alloc;
+
+mod keccak;
+
+use alloc::vec::Vec;
+
+pub use keccak::keccak256;
+
+/// Signature of the function of the `AlignedProofAggregationService` that verifies a proof was aggregated.
+pub const VERIFY_PROOF_INCLUSION_SIGNATURE: &str = "verifyProofInclusion(bytes32[],bytes32,bytes)";
+
+const ROOT: usize = 0;
+
+/// Leaf of a proof: the keccak of its program id, i.e. the verification key hash in SP1,
+/// the image ID in RISC Zero or the keccak of the verification key file for Groth16 proofs,
+/// followed by its public inputs.
+pub fn commitment(program_id: &[u8; 32], public_inputs: &[u8]) -> [u8; 32] {
+    keccak256(&[program_id, public_inputs])
+}
+
+/// Computes a commutative Keccak256 hash, ensuring H(a, b) == H(b, a).
+///
+/// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#Hashes
+///
+/// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/Hashes.sol#L17-L19
+pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a < b {
+        keccak256(&[a, b])
+    } else {
+        keccak256(&[b, a])
+    }
+}
+
+/// Root of the tree `leaf` is in with the merkle path `path`.
+///
+/// Compliant with OpenZeppelin's `processProof` and `processProofCalldata` functions from MerkleProof.sol.
+///
+/// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#MerkleProof
+///
+/// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/MerkleProof.sol#L114-L128
+pub fn process_proof(path: &[[u8; 32]], leaf: &[u8; 32]) -> [u8; 32] {
+    path.iter()
+        .fold(*leaf, |computed_hash, node| hash_pair(&computed_hash, node))
+}
+
+/// Whether `leaf` is in the tree of `root` with the merkle path `path`.
+pub fn verify(path: &[[u8; 32]], root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+    process_proof(path, leaf) == *root
+}
+
+/// Merkle tree over the commitments of the aggregated proofs.
+///
+/// The leaves are padded to a power of two by repeating the last one, and the nodes are stored
+/// from the root down, with the children of the node `i` in `2i + 1` and `2i + 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    nodes: Vec<[u8; 32]>,
+    leaves_len: usize,
+}
+
+impl MerkleTree {
+    /// Builds the tree of `leaves`, `None` if there are none.
+    pub fn build(leaves: &[[u8; 32]]) -> Option<Self> {
+        let last_leaf = *leaves.last()?;
+        let padded_len = leaves.len().next_power_of_two();
+
+        // Inner nodes are written below, the first leaf is just a placeholder
+        let mut nodes = alloc::vec![leaves[0]; padded_len - 1];
+        nodes.extend_from_slice(leaves);
+        nodes.resize(2 * padded_len - 1, last_leaf);
+
+        for parent in (0..padded_len - 1).rev() {
+            nodes[parent] = hash_pair(&nodes[2 * parent + 1], &nodes[2 * parent + 2]);
+        }
+
+        Some(Self {
+            nodes,
+            leaves_len: leaves.len(),
+        })
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes[ROOT]
+    }
+
+    /// Leaves the tree was built from, without the padding.
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        let first_leaf = self.nodes.len() / 2;
+        &self.nodes[first_leaf..first_leaf + self.leaves_len]
+    }
+
+    /// Merkle path of the leaf at `index`, `None` if it is out of bounds.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaves_len {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut node = self.nodes.len() / 2 + index;
+        while node != ROOT {
+            // Left children have odd positions
+            let sibling = if node % 2 == 1 { node + 1 } else { node - 1 };
+            path.push(self.nodes[sibling]);
+            node = (node - 1) / 2;
+        }
+
+        Some(MerkleProof {
+            leaf_index: index,
+            path,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    /// Siblings of the nodes from the leaf up to the root
+    pub path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    pub fn root(&self, leaf: &[u8; 32]) -> [u8; 32] {
+        process_proof(&self.path, leaf)
+    }
+
+    pub fn verify(&self, root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+        verify(&self.path, root, leaf)
+    }
+
+    /// ABI encoded call to `verifyProofInclusion(bytes32[] merklePath, bytes32 programId, bytes publicInputs)`
+    /// of the `AlignedProofAggregationService` for the proof of `program_id` and `public_inputs`.
+    pub fn verify_proof_inclusion_calldata(
+        &self,
+        program_id: &[u8; 32],
+        public_inputs: &[u8],
+    ) -> Vec<u8> {
+        // Head: offset of the path, program id and offset of the public inputs
+        let head_len = 3 * 32;
+        let path_len = 32 + 32 * self.path.len();
+        let public_inputs_padded_len = public_inputs.len().div_ceil(32) * 32;
+
+        let mut calldata =
+            Vec::with_capacity(4 + head_len + path_len + 32 + public_inputs_padded_len);
+        calldata.extend_from_slice(&keccak256(&[VERIFY_PROOF_INCLUSION_SIGNATURE.as_bytes()])[..4]);
+        calldata.extend_from_slice(&abi_word(head_len));
+        calldata.extend_from_slice(program_id);
+        calldata.extend_from_slice(&abi_word(head_len + path_len));
+
+        calldata.extend_from_slice(&abi_word(self.path.len()));
+        for node in &self.path {
+            calldata.extend_from_slice(node);
+        }
+
+        calldata.extend_from_slice(&abi_word(public_inputs.len()));
+        calldata.extend_from_slice(public_inputs);
+        calldata.resize(
+            calldata.len() + public_inputs_padded_len - public_inputs.len(),
+            0,
+        );
+
+        calldata
+    }
+}
+
+fn abi_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+// Expected values were computed with an implementation of keccak and of OpenZeppelin's
+// `Hashes.commutativeKeccak256` and `MerkleProof.processProof` independent of this crate.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn h(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    // keccak(i) for i in 0..5
+    fn leaves() -> Vec<[u8; 32]> {
+        (0..5u8).map(|i| keccak256(&[&[i]])).collect()
+    }
+
+    #[test]
+    fn keccak_matches_the_evm_one() {
+        assert_eq!(
+            keccak256(&[]),
+            h("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            keccak256(&[&[0]]),
+            h("bc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a")
+        );
+    }
+
+    #[test]
+    fn commitment_hashes_program_id_and_public_inputs() {
+        assert_eq!(
+            commitment(&[0xaa; 32], &[1, 2, 3]),
+            h("db0497e7d366973d5525ff1c75298d5bb00d3f7a26b8cbe9669dbd14f2e6bdc9")
+        );
+    }
+
+    #[test]
+    fn hash_pair_is_commutative() {
+        let leaves = leaves();
+        let expected = h("b2521d64679bc4720dabfbae7ce17947a5d373d987d3b0cc1e3042ba2054da4a");
+        assert_eq!(hash_pair(&leaves[0], &leaves[1]), expected);
+        assert_eq!(hash_pair(&leaves[1], &leaves[0]), expected);
+    }
+
+    #[test]
+    fn empty_tree_is_not_built() {
+        assert_eq!(MerkleTree::build(&[]), None);
+    }
+
+    #[test]
+    fn single_leaf_is_the_root() {
+        let leaf = leaves()[0];
+        let tree = MerkleTree::build(&[leaf]).unwrap();
+        assert_eq!(tree.root(), leaf);
+
+        let proof = tree.proof(0).unwrap();
+        assert!(proof.path.is_empty());
+        assert!(proof.verify(&leaf, &leaf));
+    }
+
+    #[test]
+    fn two_leaves_root_is_their_pair_hash() {
+        let leaves = leaves();
+        let tree = MerkleTree::build(&leaves[..2]).unwrap();
+        assert_eq!(
+            tree.root(),
+            h("b2521d64679bc4720dabfbae7ce17947a5d373d987d3b0cc1e3042ba2054da4a")
+        );
+    }
+
+    #[test]
+    fn tree_is_padded_with_the_last_leaf() {
+        let leaves = leaves();
+        let tree = MerkleTree::build(&leaves).unwrap();
+        assert_eq!(
+            tree.root(),
+            h("dab316343e5c1a072596e3f220be979a86177fa524914e940ac6df4af42fc205")
+        );
+        assert_eq!(tree.leaves(), leaves.as_slice());
+
+        let mut padded = leaves.clone();
+        padded.extend([leaves[4]; 3]);
+        assert_eq!(MerkleTree::build(&padded).unwrap().root(), tree.root());
+    }
+
+    #[test]
+    fn paths_match_openzeppelin_process_proof() {
+        let leaves = leaves();
+        let tree = MerkleTree::build(&leaves).unwrap();
+
+        let expected_paths = [
+            (
+                0,
+                [
+                    "5fe7f977e71dba2ea1a68e21057beebb9be2ac30c6410aa38d4f3fbe41dcffd2",
+                    "c144ad52449a5832e51e7d4daca4c86a9aafc33d89ef15ff7908956d0edb977d",
+                    "b59b4a8d6dbd4f09172f1b5252ca1d2a34c107fa5234f03ed9cd7e01ea1ca144",
+                ],
+            ),
+            (
+                2,
+                [
+                    "69c322e3248a5dfc29d73c5b0553b0185a35cd5bb6386747517ef7e53b15e287",
+                    "b2521d64679bc4720dabfbae7ce17947a5d373d987d3b0cc1e3042ba2054da4a",
+                    "b59b4a8d6dbd4f09172f1b5252ca1d2a34c107fa5234f03ed9cd7e01ea1ca144",
+                ],
+            ),
+            (
+                4,
+                [
+                    "f343681465b9efe82c933c3e8748c70cb8aa06539c361de20f72eac04e766393",
+                    "05b12c4140ef46512f78e7d2435f3fe904b875c9da40e070eb960d34b2e58155",
+                    "fecce4ac8ed6fc57f4d880d6af2b443418d564df8f5d52c6782e952564ed79eb",
+                ],
+            ),
+        ];
+        for (index, expected_path) in expected_paths {
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(proof.leaf_index, index);
+            assert_eq!(proof.path, expected_path.map(h));
+            assert_eq!(proof.root(&leaves[index]), tree.root());
+        }
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            assert!(tree.proof(index).unwrap().verify(&tree.root(), leaf));
+        }
+        assert!(!tree.proof(0).unwrap().verify(&tree.root(), &leaves[1]));
+        assert_eq!(tree.proof(5), None);
+    }
+
+    #[test]
+    fn calldata_encodes_verify_proof_inclusion() {
+        let tree = MerkleTree::build(&leaves()).unwrap();
+        let calldata = tree
+            .proof(4)
+            .unwrap()
+            .verify_proof_inclusion_calldata(&[0xaa; 32], &[1, 2, 3]);
+
+        let expected = [
+            "5bea7ad6",
+            "0000000000000000000000000000000000000000000000000000000000000060",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "00000000000000000000000000000000000000000000000000000000000000e0",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "f343681465b9efe82c933c3e8748c70cb8aa06539c361de20f72eac04e766393",
+            "05b12c4140ef46512f78e7d2435f3fe904b875c9da40e070eb960d34b2e58155",
+            "fecce4ac8ed6fc57f4d880d6af2b443418d564df8f5d52c6782e952564ed79eb",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "0102030000000000000000000000000000000000000000000000000000000000",
+        ];
+        assert_eq!(hex::encode(calldata), expected.concat());
+    }
+
+    #[test]
+    fn calldata_of_a_single_leaf_tree_has_an_empty_path() {
+        let leaf = leaves()[0];
+        let calldata = MerkleTree::build(&[leaf])
+            .unwrap()
+            .proof(0)
+            .unwrap()
+            .verify_proof_inclusion_calldata(&[0xaa; 32], &[]);
+
+        let mut expected = vec![0x5b, 0xea, 0x7a, 0xd6];
+        expected.extend(abi_word(0x60));
+        expected.extend([0xaa; 32]);
+        expected.extend(abi_word(0x80));
+        expected.extend(abi_word(0));
+        expected.extend(abi_word(0));
+        assert_eq!(calldata, expected);
+    }
+}
@@ -1 +1,3 @@
+#[cfg(feature = "native-circom-verifier")]
+pub mod native;
 pub mod verifier;
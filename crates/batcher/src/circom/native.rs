@@ -0,0 +1,259 @@
+//! Pure Rust verifier of snarkjs Groth16 proofs over BN254 (`bn128` in snarkjs).
+//!
+//! Takes the same inputs as the FFI verifier: the `proof.json`, `public.json` and
+//! `verification_key.json` files generated by snarkjs.
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{One, PrimeField, Zero};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use log::warn;
+use num_bigint::BigUint;
+use serde::Deserialize;
+
+const PROTOCOL: &str = "groth16";
+const CURVE: &str = "bn128";
+
+#[derive(Deserialize)]
+struct SnarkjsProof {
+    pi_a: Vec<String>,
+    pi_b: Vec<Vec<String>>,
+    pi_c: Vec<String>,
+    protocol: String,
+    curve: String,
+}
+
+#[derive(Deserialize)]
+struct SnarkjsVerificationKey {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: usize,
+    vk_alpha_1: Vec<String>,
+    vk_beta_2: Vec<Vec<String>>,
+    vk_gamma_2: Vec<Vec<String>>,
+    vk_delta_2: Vec<Vec<String>>,
+    #[serde(rename = "IC")]
+    ic: Vec<Vec<String>>,
+}
+
+pub fn verify_circom_groth16_bn254(
+    proof: &[u8],
+    public_input: &[u8],
+    verification_key: &[u8],
+) -> bool {
+    match verify(proof, public_input, verification_key) {
+        Ok(is_valid) => is_valid,
+        Err(e) => {
+            warn!("Could not verify Circom Groth16 proof: {}", e);
+            false
+        }
+    }
+}
+
+fn verify(proof: &[u8], public_input: &[u8], verification_key: &[u8]) -> Result<bool, String> {
+    let proof: SnarkjsProof =
+        serde_json::from_slice(proof).map_err(|e| format!("invalid proof: {}", e))?;
+    let public_signals: Vec<String> = serde_json::from_slice(public_input)
+        .map_err(|e| format!("invalid public signals: {}", e))?;
+    let verification_key: SnarkjsVerificationKey = serde_json::from_slice(verification_key)
+        .map_err(|e| format!("invalid verification key: {}", e))?;
+
+    if proof.protocol != PROTOCOL || verification_key.protocol != PROTOCOL {
+        return Err("protocol is not groth16".to_string());
+    }
+    if proof.curve != CURVE || verification_key.curve != CURVE {
+        return Err("curve is not bn128".to_string());
+    }
+    if public_signals.len() != verification_key.n_public
+        || verification_key.ic.len() != verification_key.n_public + 1
+    {
+        return Err(format!(
+            "expected {} public signals, got {}",
+            verification_key.n_public,
+            public_signals.len()
+        ));
+    }
+
+    let proof = Proof::<Bn254> {
+        a: parse_g1(&proof.pi_a)?,
+        b: parse_g2(&proof.pi_b)?,
+        c: parse_g1(&proof.pi_c)?,
+    };
+    let verification_key = VerifyingKey::<Bn254> {
+        alpha_g1: parse_g1(&verification_key.vk_alpha_1)?,
+        beta_g2: parse_g2(&verification_key.vk_beta_2)?,
+        gamma_g2: parse_g2(&verification_key.vk_gamma_2)?,
+        delta_g2: parse_g2(&verification_key.vk_delta_2)?,
+        gamma_abc_g1: verification_key
+            .ic
+            .iter()
+            .map(|point| parse_g1(point))
+            .collect::<Result<_, _>>()?,
+    };
+    let public_signals = public_signals
+        .iter()
+        .map(|signal| parse_field::<Fr>(signal))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let prepared_verification_key = PreparedVerifyingKey::from(verification_key);
+    Groth16::<Bn254>::verify_proof(&prepared_verification_key, &proof, &public_signals)
+        .map_err(|e| e.to_string())
+}
+
+/// Parses a field element in decimal, rejecting values that are not reduced.
+fn parse_field<F: PrimeField>(value: &str) -> Result<F, String> {
+    let value = value
+        .parse::<BigUint>()
+        .map_err(|_| format!("invalid field element {}", value))?;
+    if value >= F::MODULUS.into() {
+        return Err(format!("field element {} is not reduced", value));
+    }
+    Ok(F::from(value))
+}
+
+/// Parses a G1 point in projective coordinates `[x, y, z]`, as output by snarkjs.
+/// snarkjs normalizes points, so `z` is `1`, or `0` for the point at infinity.
+fn parse_g1(point: &[String]) -> Result<G1Affine, String> {
+    let [x, y, z] = point else {
+        return Err("G1 point must have 3 coordinates".to_string());
+    };
+    let z: Fq = parse_field(z)?;
+    if z.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    if !z.is_one() {
+        return Err("G1 point is not normalized".to_string());
+    }
+    let point = G1Affine::new_unchecked(parse_field(x)?, parse_field(y)?);
+    // BN254 G1 has cofactor 1, so every point on the curve is in the subgroup
+    if !point.is_on_curve() {
+        return Err("G1 point is not on the curve".to_string());
+    }
+    Ok(point)
+}
+
+/// Parses a G2 point in projective coordinates `[[x.c0, x.c1], [y.c0, y.c1], [z.c0, z.c1]]`.
+fn parse_g2(point: &[Vec<String>]) -> Result<G2Affine, String> {
+    let [x, y, z] = point else {
+        return Err("G2 point must have 3 coordinates".to_string());
+    };
+    let z = parse_fq2(z)?;
+    if z.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    if !z.is_one() {
+        return Err("G2 point is not normalized".to_string());
+    }
+    let point = G2Affine::new_unchecked(parse_fq2(x)?, parse_fq2(y)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("G2 point is not in the subgroup".to_string());
+    }
+    Ok(point)
+}
+
+fn parse_fq2(coordinate: &[String]) -> Result<Fq2, String> {
+    let [c0, c1] = coordinate else {
+        return Err("G2 coordinate must have 2 elements".to_string());
+    };
+    Ok(Fq2::new(parse_field(c0)?, parse_field(c1)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_circom_groth16_bn254;
+    use crate::circom::verifier::verify_circom;
+    use aligned_sdk::common::types::ProvingSystemId;
+
+    const FIXTURES_DIR: &str = "../../scripts/test_files/circom_groth16_bn256_script";
+
+    fn read_fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{}/{}", FIXTURES_DIR, name)).unwrap()
+    }
+
+    /// Checks that the native and the FFI verifiers agree on the result
+    fn verify_both(proof: &Vec<u8>, public_input: &Vec<u8>, verification_key: &Vec<u8>) -> bool {
+        let native = verify_circom_groth16_bn254(proof, public_input, verification_key);
+        let ffi = verify_circom(
+            &ProvingSystemId::CircomGroth16Bn256,
+            proof,
+            public_input,
+            verification_key,
+        );
+        assert_eq!(native, ffi, "Native and FFI Circom verifiers disagree");
+        native
+    }
+
+    #[test]
+    fn verify_valid_proof() {
+        let proof = read_fixture("proof.json");
+        let public_input = read_fixture("public.json");
+        let verification_key = read_fixture("verification_key.json");
+        assert!(verify_both(&proof, &public_input, &verification_key));
+    }
+
+    #[test]
+    fn reject_wrong_public_input() {
+        let proof = read_fixture("proof.json");
+        let public_input = br#"["1"]"#.to_vec();
+        let verification_key = read_fixture("verification_key.json");
+        assert!(!verify_both(&proof, &public_input, &verification_key));
+    }
+
+    #[test]
+    fn reject_tampered_proof() {
+        let mut proof: serde_json::Value =
+            serde_json::from_slice(&read_fixture("proof.json")).unwrap();
+        // Swap A and C, both are valid points on the curve
+        let pi_a = proof["pi_a"].clone();
+        proof["pi_a"] = proof["pi_c"].clone();
+        proof["pi_c"] = pi_a;
+        let proof = serde_json::to_vec(&proof).unwrap();
+        let public_input = read_fixture("public.json");
+        let verification_key = read_fixture("verification_key.json");
+        assert!(!verify_both(&proof, &public_input, &verification_key));
+    }
+
+    #[test]
+    fn reject_unreduced_public_input() {
+        let proof = read_fixture("proof.json");
+        let verification_key = read_fixture("verification_key.json");
+        // The valid public signal plus the BN254 scalar field modulus
+        let public_signal: num_bigint::BigUint =
+            "7713112592372404476342535432037683616424591277138491596200192981572885523208"
+                .parse()
+                .unwrap();
+        let modulus: num_bigint::BigUint =
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+                .parse()
+                .unwrap();
+        let public_input = format!(r#"["{}"]"#, public_signal + modulus).into_bytes();
+        assert!(!verify_circom_groth16_bn254(
+            &proof,
+            &public_input,
+            &verification_key
+        ));
+    }
+
+    #[test]
+    fn reject_malformed_inputs() {
+        let proof = read_fixture("proof.json");
+        let public_input = read_fixture("public.json");
+        let verification_key = read_fixture("verification_key.json");
+        assert!(!verify_circom_groth16_bn254(
+            b"{}",
+            &public_input,
+            &verification_key
+        ));
+        assert!(!verify_circom_groth16_bn254(
+            &proof,
+            b"not json",
+            &verification_key
+        ));
+        assert!(!verify_circom_groth16_bn254(
+            &proof,
+            br#"["1", "2"]"#,
+            &verification_key
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::ffi::circom_ffi::VerifyCircomGroth16ProofBN256;
-use aligned_sdk::common::types::ProvingSystemId;
+use crate::zk_utils::{AuxDataCommitment, ProofVerifier};
+use aligned_sdk::common::types::{ProvingSystemId, VerificationData, VerificationDataField};
+use log::debug;
 
 pub fn verify_circom(
     proving_system: &ProvingSystemId,
@@ -18,3 +20,40 @@ pub fn verify_circom(
         _ => false,
     }
 }
+
+pub(crate) struct CircomVerifier;
+
+impl ProofVerifier for CircomVerifier {
+    fn required_fields(&self) -> &'static [VerificationDataField] {
+        &[
+            VerificationDataField::Proof,
+            VerificationDataField::PubInput,
+            VerificationDataField::VerificationKey,
+        ]
+    }
+
+    fn aux_data_commitment(&self) -> AuxDataCommitment {
+        AuxDataCommitment::VerificationKey
+    }
+
+    fn verify(&self, verification_data: &VerificationData) -> bool {
+        let (Some(pub_input), Some(vk)) = (
+            verification_data.pub_input.as_ref(),
+            verification_data.verification_key.as_ref(),
+        ) else {
+            return false;
+        };
+        #[cfg(not(feature = "native-circom-verifier"))]
+        let is_valid = verify_circom(
+            &ProvingSystemId::CircomGroth16Bn256,
+            &verification_data.proof,
+            pub_input,
+            vk,
+        );
+        #[cfg(feature = "native-circom-verifier")]
+        let is_valid =
+            super::native::verify_circom_groth16_bn254(&verification_data.proof, pub_input, vk);
+        debug!("Circom Groth16 proof is valid: {}", is_valid);
+        is_valid
+    }
+}
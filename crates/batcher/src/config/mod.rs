@@ -35,6 +35,21 @@ impl NonPayingConfig {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TlsConfigFromYaml {
+    pub cert_path: String,
+    pub key_path: String,
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpConfigFromYaml {
+    pub address: String,
+    #[serde(default = "default_http_max_tracked_proofs")]
+    pub max_tracked_proofs: usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BatcherConfigFromYaml {
     #[serde(default = "default_aggregator_fee_percentage_multiplier")]
@@ -51,6 +66,14 @@ pub struct BatcherConfigFromYaml {
     pub metrics_port: u16,
     pub telemetry_ip_port_address: String,
     pub non_paying: Option<NonPayingConfigFromYaml>,
+    pub tls: Option<TlsConfigFromYaml>,
+    #[serde(default = "default_user_state_idle_eviction_secs")]
+    pub user_state_idle_eviction_secs: u64,
+    pub http: Option<HttpConfigFromYaml>,
+    #[serde(default = "default_ready_max_block_age_secs")]
+    pub ready_max_block_age_secs: u64,
+    #[serde(default = "default_ready_min_batcher_balance_wei")]
+    pub ready_min_batcher_balance_wei: u128,
 }
 
 #[derive(Debug, Deserialize)]
@@ -99,3 +122,24 @@ fn default_aggregator_fee_percentage_multiplier() -> u128 {
 fn default_aggregator_gas_cost() -> u128 {
     aligned_sdk::common::constants::DEFAULT_AGGREGATOR_GAS_COST
 }
+
+fn default_tls_reload_interval() -> u64 {
+    60
+}
+
+fn default_user_state_idle_eviction_secs() -> u64 {
+    3600
+}
+
+fn default_http_max_tracked_proofs() -> usize {
+    10000
+}
+
+fn default_ready_max_block_age_secs() -> u64 {
+    60
+}
+
+fn default_ready_min_batcher_balance_wei() -> u128 {
+    // 0.01 ETH
+    10_000_000_000_000_000
+}
@@ -1,34 +1,99 @@
-use std::sync::Arc;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 use crate::types::{batch_queue::BatchQueueEntry, errors::BatcherError};
 use aligned_sdk::{
-    common::types::{BatchInclusionData, SubmitProofResponseMessage, VerificationCommitmentBatch},
+    common::types::{
+        BatchInclusionData, SigningKey, SubmitProofResponseMessage, VerificationCommitmentBatch,
+        Wallet,
+    },
     communication::serialization::cbor_serialize,
 };
-use futures_util::{stream::SplitSink, SinkExt};
+use futures_util::{Sink, SinkExt};
 use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
-use log::{debug, error};
+use log::{debug, error, warn};
 use serde::Serialize;
-use tokio::{net::TcpStream, sync::RwLock};
-use tokio_tungstenite::{
-    tungstenite::{Error, Message},
-    WebSocketStream,
-};
+use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::tungstenite::{Error, Message};
+
+/// Sink where the responses to a client are written.
+/// It is the outgoing half of a websocket connection, or a [`ChannelSink`] for HTTP requests.
+pub(crate) type WsMessageSink = Arc<RwLock<dyn Sink<Message, Error = Error> + Send + Sync + Unpin>>;
+
+/// Message sink backed by a channel, used to get the responses of the batcher
+/// to clients that are not connected through a websocket.
+pub(crate) struct ChannelSink {
+    sender: Option<mpsc::UnboundedSender<Message>>,
+}
+
+impl ChannelSink {
+    pub(crate) fn channel() -> (WsMessageSink, mpsc::UnboundedReceiver<Message>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let sink = Arc::new(RwLock::new(ChannelSink {
+            sender: Some(sender),
+        }));
+        (sink, receiver)
+    }
+}
+
+impl Sink<Message> for ChannelSink {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.sender {
+            Some(ref sender) if !sender.is_closed() => Poll::Ready(Ok(())),
+            _ => Poll::Ready(Err(Error::AlreadyClosed)),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let Some(ref sender) = self.sender else {
+            return Err(Error::AlreadyClosed);
+        };
+        sender.send(item).map_err(|_| Error::AlreadyClosed)
+    }
 
-pub(crate) type WsMessageSink = Arc<RwLock<SplitSink<WebSocketStream<TcpStream>, Message>>>;
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // Dropping the sender ends the receiver stream
+        self.sender = None;
+        Poll::Ready(Ok(()))
+    }
+}
 
 pub(crate) async fn send_batch_inclusion_data_responses(
     finalized_batch: Vec<BatchQueueEntry>,
     batch_merkle_tree: &MerkleTree<VerificationCommitmentBatch>,
+    batcher_wallet: &Wallet<SigningKey>,
 ) -> Result<(), BatcherError> {
     // Finalized_batch is ordered as the PriorityQueue, ordered by: ascending max_fee && if max_fee is equal, by descending nonce.
     // We iter it in reverse because each sender wants to receive responses in ascending nonce order
     for (vd_batch_idx, entry) in finalized_batch.iter().enumerate().rev() {
-        let batch_inclusion_data = BatchInclusionData::new(
+        let mut batch_inclusion_data = BatchInclusionData::new(
             vd_batch_idx,
             batch_merkle_tree,
             entry.nonced_verification_data.nonce,
         );
+        // The inclusion data is still sent if signing fails, since the proof was already included
+        if let Err(e) = batch_inclusion_data
+            .sign(
+                batcher_wallet,
+                entry.nonced_verification_data.chain_id,
+                entry.nonced_verification_data.payment_service_addr,
+            )
+            .await
+        {
+            warn!("Could not sign batch inclusion data: {}", e);
+        }
         let response = SubmitProofResponseMessage::BatchInclusionData(batch_inclusion_data);
 
         let serialized_response = cbor_serialize(&response)
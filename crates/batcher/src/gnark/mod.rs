@@ -1,7 +1,9 @@
 use crate::ffi::gnark_ffi::{
     VerifyGnarkGroth16ProofBN254, VerifyGnarkPlonkProofBLS12_381, VerifyGnarkPlonkProofBN254,
 };
-use aligned_sdk::common::types::ProvingSystemId;
+use crate::zk_utils::{AuxDataCommitment, ProofVerifier};
+use aligned_sdk::common::types::{ProvingSystemId, VerificationData, VerificationDataField};
+use log::debug;
 
 pub fn verify_gnark(
     proving_system: &ProvingSystemId,
@@ -26,3 +28,45 @@ pub fn verify_gnark(
         _ => false,
     }
 }
+
+/// Verifier of the gnark proving systems, all of them verified through the gnark FFI.
+pub(crate) struct GnarkVerifier {
+    proving_system: ProvingSystemId,
+}
+
+impl GnarkVerifier {
+    pub(crate) fn new(proving_system: ProvingSystemId) -> Self {
+        Self { proving_system }
+    }
+}
+
+impl ProofVerifier for GnarkVerifier {
+    fn required_fields(&self) -> &'static [VerificationDataField] {
+        &[
+            VerificationDataField::Proof,
+            VerificationDataField::PubInput,
+            VerificationDataField::VerificationKey,
+        ]
+    }
+
+    fn aux_data_commitment(&self) -> AuxDataCommitment {
+        AuxDataCommitment::VerificationKey
+    }
+
+    fn verify(&self, verification_data: &VerificationData) -> bool {
+        let (Some(pub_input), Some(vk)) = (
+            verification_data.pub_input.as_ref(),
+            verification_data.verification_key.as_ref(),
+        ) else {
+            return false;
+        };
+        let is_valid = verify_gnark(
+            &self.proving_system,
+            &verification_data.proof,
+            pub_input,
+            vk,
+        );
+        debug!("Gnark proof is valid: {}", is_valid);
+        is_valid
+    }
+}
@@ -0,0 +1,306 @@
+//! Health and readiness reports served by the metrics server.
+//!
+//! `/health` only tells the process is alive, while `/ready` runs the checks
+//! below and answers 503 if any of them fails:
+//! * `block_subscription`: the new blocks subscription is alive on the main or fallback WS node.
+//! * `last_block`: a new block was received within `ready_max_block_age_secs`.
+//! * `eth_rpc`: the main or fallback RPC node answers.
+//! * `s3`: the S3 bucket where batches are uploaded is reachable.
+//! * `batcher_balance`: the batcher wallet holds at least `ready_min_batcher_balance_wei`.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock, Weak,
+    },
+    time::Duration,
+};
+
+use aws_sdk_s3::error::DisplayErrorContext;
+use ethers::providers::Middleware;
+use futures_util::{future::BoxFuture, join, Future, FutureExt};
+use serde::Serialize;
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::Batcher;
+
+/// Maximum time each readiness check can take before it is considered failed.
+const READINESS_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: &'static str,
+    pub uptime_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl ReadinessReport {
+    pub fn new(checks: Vec<CheckResult>) -> Self {
+        Self {
+            ready: checks.iter().all(|check| check.ok),
+            checks,
+        }
+    }
+}
+
+/// Something that can tell if it is ready to receive traffic.
+pub trait ReadinessCheck: Send + Sync {
+    fn readiness_report(&self) -> BoxFuture<'_, ReadinessReport>;
+}
+
+/// Handle shared with the metrics server to run the readiness checks.
+/// The batcher registers itself once it is built; until then it is reported as not ready.
+#[derive(Clone, Default)]
+pub struct ReadinessProbe(Arc<OnceLock<Weak<dyn ReadinessCheck>>>);
+
+impl ReadinessProbe {
+    pub fn set<T: ReadinessCheck + 'static>(&self, check: &Arc<T>) {
+        let check: Weak<dyn ReadinessCheck> = Arc::downgrade(check) as Weak<dyn ReadinessCheck>;
+        if self.0.set(check).is_err() {
+            log::warn!("Readiness check was already registered");
+        }
+    }
+
+    pub async fn report(&self) -> ReadinessReport {
+        match self.0.get().and_then(Weak::upgrade) {
+            Some(check) => check.readiness_report().await,
+            None => ReadinessReport::new(vec![CheckResult::new(
+                "batcher",
+                false,
+                "Batcher is not running",
+            )]),
+        }
+    }
+}
+
+impl fmt::Debug for ReadinessProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadinessProbe")
+            .field("registered", &self.0.get().is_some())
+            .finish()
+    }
+}
+
+/// State of the new blocks subscription, updated by `listen_new_blocks`.
+#[derive(Default)]
+pub(crate) struct BlockListenerStatus {
+    main_subscribed: AtomicBool,
+    fallback_subscribed: AtomicBool,
+    last_block_at: Mutex<Option<Instant>>,
+}
+
+impl BlockListenerStatus {
+    pub(crate) fn set_subscriptions(&self, main: bool, fallback: bool) {
+        self.main_subscribed.store(main, Ordering::Relaxed);
+        self.fallback_subscribed.store(fallback, Ordering::Relaxed);
+    }
+
+    pub(crate) async fn block_received(&self) {
+        *self.last_block_at.lock().await = Some(Instant::now());
+    }
+
+    fn subscription_check(&self) -> CheckResult {
+        let main = self.main_subscribed.load(Ordering::Relaxed);
+        let fallback = self.fallback_subscribed.load(Ordering::Relaxed);
+        CheckResult::new(
+            "block_subscription",
+            main || fallback,
+            format!(
+                "main: {}, fallback: {}",
+                up_or_down(main),
+                up_or_down(fallback)
+            ),
+        )
+    }
+
+    async fn last_block_check(&self, max_block_age: Duration) -> CheckResult {
+        last_block_check(*self.last_block_at.lock().await, max_block_age)
+    }
+}
+
+fn up_or_down(up: bool) -> &'static str {
+    if up {
+        "up"
+    } else {
+        "down"
+    }
+}
+
+fn last_block_check(last_block_at: Option<Instant>, max_block_age: Duration) -> CheckResult {
+    match last_block_at {
+        Some(last_block_at) => {
+            let age = last_block_at.elapsed();
+            CheckResult::new(
+                "last_block",
+                age <= max_block_age,
+                format!("last block received {}s ago", age.as_secs()),
+            )
+        }
+        None => CheckResult::new("last_block", false, "no block received yet"),
+    }
+}
+
+/// Runs a check, failing it if it doesn't finish within [`READINESS_CHECK_TIMEOUT`].
+async fn with_timeout(name: &'static str, check: impl Future<Output = CheckResult>) -> CheckResult {
+    tokio::time::timeout(READINESS_CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| {
+            CheckResult::new(
+                name,
+                false,
+                format!("timed out after {}s", READINESS_CHECK_TIMEOUT.as_secs()),
+            )
+        })
+}
+
+impl Batcher {
+    async fn eth_rpc_check(&self) -> CheckResult {
+        let (main, fallback) = join!(
+            self.batcher_signer.provider().get_block_number(),
+            self.batcher_signer_fallback.provider().get_block_number()
+        );
+        CheckResult::new(
+            "eth_rpc",
+            main.is_ok() || fallback.is_ok(),
+            format!(
+                "main: {}, fallback: {}",
+                up_or_down(main.is_ok()),
+                up_or_down(fallback.is_ok())
+            ),
+        )
+    }
+
+    async fn s3_check(&self) -> CheckResult {
+        match self
+            .s3_client
+            .head_bucket()
+            .bucket(&self.s3_bucket_name)
+            .send()
+            .await
+        {
+            Ok(_) => CheckResult::new(
+                "s3",
+                true,
+                format!("bucket {} reachable", self.s3_bucket_name),
+            ),
+            Err(e) => CheckResult::new("s3", false, DisplayErrorContext(e).to_string()),
+        }
+    }
+
+    async fn batcher_balance_check(&self) -> CheckResult {
+        let batcher_addr = self.batcher_signer.address();
+        let balance = match self.batcher_signer.get_balance(batcher_addr, None).await {
+            Ok(balance) => Ok(balance),
+            Err(_) => self
+                .batcher_signer_fallback
+                .get_balance(batcher_addr, None)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        match balance {
+            Ok(balance) => CheckResult::new(
+                "batcher_balance",
+                balance >= self.ready_min_batcher_balance,
+                format!(
+                    "balance: {} wei, minimum: {} wei",
+                    balance, self.ready_min_batcher_balance
+                ),
+            ),
+            Err(e) => CheckResult::new("batcher_balance", false, e),
+        }
+    }
+}
+
+impl ReadinessCheck for Batcher {
+    fn readiness_report(&self) -> BoxFuture<'_, ReadinessReport> {
+        async move {
+            let (last_block, eth_rpc, s3, batcher_balance) = join!(
+                self.block_listener_status
+                    .last_block_check(self.ready_max_block_age),
+                with_timeout("eth_rpc", self.eth_rpc_check()),
+                with_timeout("s3", self.s3_check()),
+                with_timeout("batcher_balance", self.batcher_balance_check()),
+            );
+            ReadinessReport::new(vec![
+                self.block_listener_status.subscription_check(),
+                last_block,
+                eth_rpc,
+                s3,
+                batcher_balance,
+            ])
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report_is_ready_only_if_all_checks_pass() {
+        let report = ReadinessReport::new(vec![
+            CheckResult::new("a", true, ""),
+            CheckResult::new("b", true, ""),
+        ]);
+        assert!(report.ready);
+
+        let report = ReadinessReport::new(vec![
+            CheckResult::new("a", true, ""),
+            CheckResult::new("b", false, ""),
+        ]);
+        assert!(!report.ready);
+    }
+
+    #[tokio::test]
+    async fn unregistered_probe_is_not_ready() {
+        let probe = ReadinessProbe::default();
+        assert!(!probe.report().await.ready);
+    }
+
+    #[test]
+    fn last_block_check_fails_without_recent_blocks() {
+        assert!(!last_block_check(None, Duration::from_secs(60)).ok);
+
+        let now = Instant::now();
+        assert!(last_block_check(Some(now), Duration::from_secs(60)).ok);
+
+        let old_block = now - Duration::from_secs(120);
+        assert!(!last_block_check(Some(old_block), Duration::from_secs(60)).ok);
+    }
+
+    #[test]
+    fn subscription_check_passes_with_one_subscription() {
+        let status = BlockListenerStatus::default();
+        assert!(!status.subscription_check().ok);
+
+        status.set_subscriptions(false, true);
+        assert!(status.subscription_check().ok);
+
+        status.set_subscriptions(false, false);
+        assert!(!status.subscription_check().ok);
+    }
+}
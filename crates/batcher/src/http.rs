@@ -0,0 +1,762 @@
+//! Optional HTTP API of the batcher.
+//!
+//! It exposes the same operations as the websocket protocol for clients that would rather not keep
+//! a connection open. Submissions go through [`Batcher::handle_submit_proof_msg`], so they are
+//! validated exactly as websocket ones. Request bodies can be JSON or CBOR, depending on the
+//! `Content-Type` header, and responses are JSON unless CBOR is requested with the `Accept` header.
+//!
+//! Routes:
+//! * `POST /v1/proofs`: submits a `SubmitProofMessage`.
+//! * `GET /v1/nonce/{address}`: next nonce of the address.
+//! * `GET /v1/proofs?address={address}`: proofs of the address in the queue.
+//! * `GET /v1/proofs/{commitment}`: status of a proof, given its batch merkle tree leaf.
+//! * `GET /v1/proofs/{commitment}/wait?timeout={secs}`: long-polls until the proof is included in a batch.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+use aligned_sdk::{
+    common::types::{
+        BatchInclusionData, GetNonceResponseMessage, SignedProofReceipt, SubmitProofMessage,
+        SubmitProofResponseMessage, VerificationCommitmentBatch,
+    },
+    communication::serialization::{cbor_deserialize, cbor_serialize},
+};
+use bytes::Bytes;
+use ethers::types::{Address, U256};
+use lambdaworks_crypto::merkle_tree::traits::IsMerkleTreeBackend;
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex, Notify};
+use tokio_tungstenite::tungstenite::Message;
+use warp::{
+    http::{header::CONTENT_TYPE, StatusCode},
+    reply::Response,
+    Filter, Reply,
+};
+
+use crate::{connection::ChannelSink, Batcher};
+
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+const JSON_CONTENT_TYPE: &str = "application/json";
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 30;
+const MAX_WAIT_TIMEOUT_SECS: u64 = 120;
+
+/// Status of a proof, as returned by the HTTP API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ProofStatus {
+    /// The proof is in the queue, waiting to be included in a batch.
+    Queued(Option<SignedProofReceipt>),
+    /// The proof was included in a batch that was submitted to Ethereum.
+    Included(BatchInclusionData),
+    /// The proof was evicted from the full queue by proofs paying a higher fee.
+    Underpriced,
+    /// The proof was accepted but it could not be included in a batch.
+    Failed(String),
+}
+
+impl ProofStatus {
+    fn is_final(&self) -> bool {
+        !matches!(self, ProofStatus::Queued(_))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct QueuedProof {
+    pub commitment: String,
+    pub nonce: U256,
+    pub max_fee: U256,
+}
+
+/// Identifies a proof submitted through the HTTP API.
+/// Replacements keep the sender and nonce of the proof they replace,
+/// and also its commitment when only the max fee is bumped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ProofKey {
+    pub sender: Address,
+    pub nonce: U256,
+    pub commitment: [u8; 32],
+}
+
+/// Statuses of the proofs submitted through the HTTP API.
+/// Only the latest `capacity` proofs are tracked.
+pub(crate) struct ProofStatusTracker {
+    statuses: Mutex<TrackedStatuses>,
+    capacity: usize,
+    updated: Notify,
+}
+
+struct TrackedProof {
+    /// Submission that last registered the proof, updates from older ones are ignored
+    submission: u64,
+    status: ProofStatus,
+}
+
+#[derive(Default)]
+struct TrackedStatuses {
+    proofs: HashMap<ProofKey, TrackedProof>,
+    /// Latest tracked proof of each commitment, which is what status requests are made with
+    by_commitment: HashMap<[u8; 32], ProofKey>,
+    /// Tracked proofs, oldest first
+    order: VecDeque<ProofKey>,
+    next_submission: u64,
+}
+
+impl ProofStatusTracker {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            statuses: Mutex::new(TrackedStatuses::default()),
+            capacity,
+            updated: Notify::new(),
+        }
+    }
+
+    pub(crate) async fn get(&self, commitment: &[u8; 32]) -> Option<ProofStatus> {
+        let statuses = self.statuses.lock().await;
+        let key = statuses.by_commitment.get(commitment)?;
+        statuses
+            .proofs
+            .get(key)
+            .map(|tracked_proof| tracked_proof.status.clone())
+    }
+
+    /// Starts tracking a new submission of the proof, superseding any previous one with the same key.
+    /// Returns the id of the submission, which its status updates must be made with.
+    pub(crate) async fn register(&self, key: ProofKey, status: ProofStatus) -> u64 {
+        let submission = {
+            let mut statuses = self.statuses.lock().await;
+            let TrackedStatuses {
+                proofs,
+                by_commitment,
+                order,
+                next_submission,
+            } = &mut *statuses;
+            let submission = *next_submission;
+            *next_submission += 1;
+
+            if proofs
+                .insert(key, TrackedProof { submission, status })
+                .is_none()
+            {
+                order.push_back(key);
+            }
+            by_commitment.insert(key.commitment, key);
+            while order.len() > self.capacity {
+                if let Some(oldest) = order.pop_front() {
+                    proofs.remove(&oldest);
+                    if by_commitment.get(&oldest.commitment) == Some(&oldest) {
+                        by_commitment.remove(&oldest.commitment);
+                    }
+                }
+            }
+            submission
+        };
+        self.updated.notify_waiters();
+        submission
+    }
+
+    /// Updates the status of the proof, unless it was superseded by a newer submission.
+    pub(crate) async fn update(&self, key: &ProofKey, submission: u64, status: ProofStatus) {
+        {
+            let mut statuses = self.statuses.lock().await;
+            match statuses.proofs.get_mut(key) {
+                Some(tracked_proof) if tracked_proof.submission == submission => {
+                    tracked_proof.status = status;
+                }
+                _ => return,
+            }
+        }
+        self.updated.notify_waiters();
+    }
+
+    async fn get_submission(&self, key: &ProofKey, submission: u64) -> Option<ProofStatus> {
+        self.statuses
+            .lock()
+            .await
+            .proofs
+            .get(key)
+            .filter(|tracked_proof| tracked_proof.submission == submission)
+            .map(|tracked_proof| tracked_proof.status.clone())
+    }
+
+    /// Waits until the proof reaches a final status or `timeout` elapses, returning its last status.
+    pub(crate) async fn wait_final(
+        &self,
+        commitment: &[u8; 32],
+        timeout: Duration,
+    ) -> Option<ProofStatus> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // The notification is registered before checking, so updates in between are not missed
+            let updated = self.updated.notified();
+            tokio::pin!(updated);
+            updated.as_mut().enable();
+
+            let status = self.get(commitment).await;
+            if status.as_ref().is_none_or(ProofStatus::is_final) {
+                return status;
+            }
+            if tokio::time::timeout_at(deadline, updated).await.is_err() {
+                return status;
+            }
+        }
+    }
+
+    /// Tracks the responses the batcher sends for a submission after it was queued.
+    async fn track(
+        self: Arc<Self>,
+        key: ProofKey,
+        submission: u64,
+        mut receiver: mpsc::UnboundedReceiver<Message>,
+    ) {
+        while let Some(message) = receiver.recv().await {
+            let status = match cbor_deserialize(message.into_data().as_slice()) {
+                Ok(SubmitProofResponseMessage::BatchInclusionData(batch_inclusion_data)) => {
+                    ProofStatus::Included(batch_inclusion_data)
+                }
+                Ok(SubmitProofResponseMessage::UnderpricedProof) => ProofStatus::Underpriced,
+                Ok(SubmitProofResponseMessage::BatchReset) => {
+                    ProofStatus::Failed("Batch reset, the proof was removed from the queue".into())
+                }
+                Ok(SubmitProofResponseMessage::CreateNewTaskError(merkle_root, error)) => {
+                    ProofStatus::Failed(format!(
+                        "Could not create task with merkle root {merkle_root}: {error}"
+                    ))
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Could not deserialize batcher response: {}", e);
+                    continue;
+                }
+            };
+            self.update(&key, submission, status).await;
+            return;
+        }
+        // The sink was closed without a final response, which happens when the proof is replaced
+        if let Some(ProofStatus::Queued(_)) = self.get_submission(&key, submission).await {
+            self.update(
+                &key,
+                submission,
+                ProofStatus::Failed("The proof was replaced".into()),
+            )
+            .await;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Json,
+    Cbor,
+}
+
+impl Format {
+    fn from_header(header: Option<String>) -> Self {
+        match header {
+            Some(value) if value.contains(CBOR_CONTENT_TYPE) => Format::Cbor,
+            _ => Format::Json,
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, body: &[u8]) -> Result<T, String> {
+        match self {
+            Format::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+            Format::Cbor => cbor_deserialize(body).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn reply<T: Serialize>(&self, status: StatusCode, body: &T) -> Response {
+        let (content_type, serialized) = match self {
+            Format::Json => (
+                JSON_CONTENT_TYPE,
+                serde_json::to_vec(body).map_err(|e| e.to_string()),
+            ),
+            Format::Cbor => (
+                CBOR_CONTENT_TYPE,
+                cbor_serialize(body).map_err(|e| e.to_string()),
+            ),
+        };
+        match serialized {
+            Ok(serialized) => warp::reply::with_status(
+                warp::reply::with_header(serialized, CONTENT_TYPE, content_type),
+                status,
+            )
+            .into_response(),
+            Err(e) => {
+                warp::reply::with_status(e, StatusCode::INTERNAL_SERVER_ERROR).into_response()
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct QueuedProofsQuery {
+    address: Address,
+}
+
+#[derive(Deserialize)]
+struct WaitQuery {
+    timeout: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn error_reply(format: Format, status: StatusCode, error: impl ToString) -> Response {
+    format.reply(
+        status,
+        &ErrorBody {
+            error: error.to_string(),
+        },
+    )
+}
+
+fn parse_commitment(commitment: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(commitment.trim_start_matches("0x")).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Returns the first response written by the batcher into the sink.
+fn first_response<T: DeserializeOwned>(
+    receiver: &mut mpsc::UnboundedReceiver<Message>,
+) -> Option<T> {
+    let message = receiver.try_recv().ok()?;
+    cbor_deserialize(message.into_data().as_slice()).ok()
+}
+
+fn submit_response_status(response: &SubmitProofResponseMessage) -> StatusCode {
+    match response {
+        SubmitProofResponseMessage::ProofQueued(_) => StatusCode::OK,
+        SubmitProofResponseMessage::EthRpcError | SubmitProofResponseMessage::AddToBatchError => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        SubmitProofResponseMessage::UnderpricedProof => StatusCode::TOO_MANY_REQUESTS,
+        SubmitProofResponseMessage::ProofTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+async fn submit_proof(
+    batcher: Arc<Batcher>,
+    tracker: Arc<ProofStatusTracker>,
+    content_type: Option<String>,
+    accept: Option<String>,
+    body: Bytes,
+) -> Result<Response, Infallible> {
+    let response_format = Format::from_header(accept);
+    let submit_proof_message: SubmitProofMessage =
+        match Format::from_header(content_type).decode(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                batcher.metrics.user_error(&["deserialize_error", ""]);
+                return Ok(error_reply(response_format, StatusCode::BAD_REQUEST, e));
+            }
+        };
+    // Submissions with an invalid signature are rejected by the batcher, so they are never tracked
+    let sender = submit_proof_message.verify_signature().ok();
+    let nonce = submit_proof_message.verification_data.nonce;
+    let commitment = VerificationCommitmentBatch::hash_data(
+        &submit_proof_message
+            .verification_data
+            .verification_data
+            .clone()
+            .into(),
+    );
+
+    let (sink, mut receiver) = ChannelSink::channel();
+    if let Err(e) = batcher
+        .clone()
+        .handle_submit_proof_msg(Box::new(submit_proof_message), sink)
+        .await
+    {
+        return Ok(error_reply(
+            response_format,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e,
+        ));
+    }
+
+    let Some(response) = first_response::<SubmitProofResponseMessage>(&mut receiver) else {
+        return Ok(error_reply(
+            response_format,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Batcher did not respond to the submission",
+        ));
+    };
+
+    if let (SubmitProofResponseMessage::ProofQueued(proof_receipt), Some(sender)) =
+        (&response, sender)
+    {
+        let key = ProofKey {
+            sender,
+            nonce,
+            commitment,
+        };
+        let submission = tracker
+            .register(key, ProofStatus::Queued(Some(proof_receipt.clone())))
+            .await;
+        tokio::spawn(tracker.track(key, submission, receiver));
+    }
+
+    Ok(response_format.reply(submit_response_status(&response), &response))
+}
+
+async fn get_nonce(
+    address: Address,
+    batcher: Arc<Batcher>,
+    accept: Option<String>,
+) -> Result<Response, Infallible> {
+    let response_format = Format::from_header(accept);
+    let (sink, mut receiver) = ChannelSink::channel();
+    if let Err(e) = batcher
+        .clone()
+        .handle_get_nonce_for_address_msg(address, sink)
+        .await
+    {
+        return Ok(error_reply(
+            response_format,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e,
+        ));
+    }
+
+    let status = match first_response::<GetNonceResponseMessage>(&mut receiver) {
+        Some(response @ GetNonceResponseMessage::Nonce(_)) => {
+            return Ok(response_format.reply(StatusCode::OK, &response))
+        }
+        Some(response @ GetNonceResponseMessage::EthRpcError(_)) => {
+            (StatusCode::SERVICE_UNAVAILABLE, response)
+        }
+        Some(response @ GetNonceResponseMessage::InvalidRequest(_)) => {
+            (StatusCode::BAD_REQUEST, response)
+        }
+        None => {
+            return Ok(error_reply(
+                response_format,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Batcher did not respond to the nonce request",
+            ))
+        }
+    };
+    Ok(response_format.reply(status.0, &status.1))
+}
+
+async fn get_queued_proofs(
+    query: QueuedProofsQuery,
+    batcher: Arc<Batcher>,
+    accept: Option<String>,
+) -> Result<Response, Infallible> {
+    let queued_proofs = batcher.get_queued_proofs(&query.address).await;
+    Ok(Format::from_header(accept).reply(StatusCode::OK, &queued_proofs))
+}
+
+async fn proof_status(
+    batcher: &Batcher,
+    tracker: &ProofStatusTracker,
+    commitment: &[u8; 32],
+) -> Option<ProofStatus> {
+    if let Some(status) = tracker.get(commitment).await {
+        return Some(status);
+    }
+    // Proofs submitted through websockets are only known while they are in the queue
+    batcher
+        .is_commitment_queued(commitment)
+        .await
+        .then_some(ProofStatus::Queued(None))
+}
+
+async fn get_proof_status(
+    commitment: String,
+    batcher: Arc<Batcher>,
+    tracker: Arc<ProofStatusTracker>,
+    accept: Option<String>,
+) -> Result<Response, Infallible> {
+    let response_format = Format::from_header(accept);
+    let Some(commitment) = parse_commitment(&commitment) else {
+        return Ok(error_reply(
+            response_format,
+            StatusCode::BAD_REQUEST,
+            "Commitment must be 32 hex encoded bytes",
+        ));
+    };
+    match proof_status(&batcher, &tracker, &commitment).await {
+        Some(status) => Ok(response_format.reply(StatusCode::OK, &status)),
+        None => Ok(error_reply(
+            response_format,
+            StatusCode::NOT_FOUND,
+            "Proof not found",
+        )),
+    }
+}
+
+async fn wait_proof_inclusion(
+    commitment: String,
+    query: WaitQuery,
+    batcher: Arc<Batcher>,
+    tracker: Arc<ProofStatusTracker>,
+    accept: Option<String>,
+) -> Result<Response, Infallible> {
+    let response_format = Format::from_header(accept);
+    let Some(commitment) = parse_commitment(&commitment) else {
+        return Ok(error_reply(
+            response_format,
+            StatusCode::BAD_REQUEST,
+            "Commitment must be 32 hex encoded bytes",
+        ));
+    };
+    if tracker.get(&commitment).await.is_none() {
+        // Only proofs submitted through the HTTP API can be waited on
+        return match proof_status(&batcher, &tracker, &commitment).await {
+            Some(status) => Ok(response_format.reply(StatusCode::OK, &status)),
+            None => Ok(error_reply(
+                response_format,
+                StatusCode::NOT_FOUND,
+                "Proof not found",
+            )),
+        };
+    }
+
+    let timeout = query
+        .timeout
+        .unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS)
+        .min(MAX_WAIT_TIMEOUT_SECS);
+    match tracker
+        .wait_final(&commitment, Duration::from_secs(timeout))
+        .await
+    {
+        Some(status) => Ok(response_format.reply(StatusCode::OK, &status)),
+        None => Ok(error_reply(
+            response_format,
+            StatusCode::NOT_FOUND,
+            "Proof not found",
+        )),
+    }
+}
+
+/// Serves the HTTP API until the server fails.
+pub(crate) async fn serve(batcher: Arc<Batcher>, address: SocketAddr, max_tracked_proofs: usize) {
+    let tracker = Arc::new(ProofStatusTracker::new(max_tracked_proofs));
+    // JSON encodes each byte of the proof as a number, so bodies can be a few times bigger than the proof
+    let max_body_size = (batcher.max_proof_size * 4) as u64;
+
+    let with_batcher = {
+        let batcher = batcher.clone();
+        warp::any().map(move || batcher.clone())
+    };
+    let with_tracker = warp::any().map(move || tracker.clone());
+    let accept = warp::header::optional::<String>("accept");
+
+    let submit = warp::path!("v1" / "proofs")
+        .and(warp::post())
+        .and(with_batcher.clone())
+        .and(with_tracker.clone())
+        .and(warp::header::optional::<String>("content-type"))
+        .and(accept)
+        .and(warp::body::content_length_limit(max_body_size))
+        .and(warp::body::bytes())
+        .and_then(submit_proof);
+
+    let nonce = warp::path!("v1" / "nonce" / Address)
+        .and(warp::get())
+        .and(with_batcher.clone())
+        .and(accept)
+        .and_then(get_nonce);
+
+    let queued_proofs = warp::path!("v1" / "proofs")
+        .and(warp::get())
+        .and(warp::query::<QueuedProofsQuery>())
+        .and(with_batcher.clone())
+        .and(accept)
+        .and_then(get_queued_proofs);
+
+    let status = warp::path!("v1" / "proofs" / String)
+        .and(warp::get())
+        .and(with_batcher.clone())
+        .and(with_tracker.clone())
+        .and(accept)
+        .and_then(get_proof_status);
+
+    let wait = warp::path!("v1" / "proofs" / String / "wait")
+        .and(warp::get())
+        .and(warp::query::<WaitQuery>())
+        .and(with_batcher)
+        .and(with_tracker)
+        .and(accept)
+        .and_then(wait_proof_inclusion);
+
+    info!("Serving HTTP API on {}", address);
+    warp::serve(submit.or(nonce).or(queued_proofs).or(status).or(wait))
+        .run(address)
+        .await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::connection::send_message;
+
+    fn proof_key(commitment: [u8; 32]) -> ProofKey {
+        ProofKey {
+            sender: Address::repeat_byte(0x11),
+            nonce: U256::from(3),
+            commitment,
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_final_returns_once_the_proof_is_included() {
+        let tracker = Arc::new(ProofStatusTracker::new(10));
+        let key = proof_key([1u8; 32]);
+        let submission = tracker.register(key, ProofStatus::Queued(None)).await;
+
+        let (sink, receiver) = ChannelSink::channel();
+        tokio::spawn(tracker.clone().track(key, submission, receiver));
+
+        let waiter = {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                tracker
+                    .wait_final(&key.commitment, Duration::from_secs(10))
+                    .await
+            })
+        };
+
+        send_message(sink, SubmitProofResponseMessage::BatchReset).await;
+
+        let status = waiter.await.unwrap();
+        assert!(matches!(status, Some(ProofStatus::Failed(_))));
+    }
+
+    #[tokio::test]
+    async fn wait_final_times_out_while_queued() {
+        let tracker = ProofStatusTracker::new(10);
+        let key = proof_key([2u8; 32]);
+        tracker.register(key, ProofStatus::Queued(None)).await;
+
+        let status = tracker
+            .wait_final(&key.commitment, Duration::from_millis(50))
+            .await;
+        assert!(matches!(status, Some(ProofStatus::Queued(None))));
+    }
+
+    #[tokio::test]
+    async fn closing_the_sink_marks_the_proof_as_replaced() {
+        let tracker = Arc::new(ProofStatusTracker::new(10));
+        let key = proof_key([3u8; 32]);
+        let submission = tracker.register(key, ProofStatus::Queued(None)).await;
+
+        let (sink, receiver) = ChannelSink::channel();
+        let tracking = tokio::spawn(tracker.clone().track(key, submission, receiver));
+        futures_util::SinkExt::close(&mut *sink.write().await)
+            .await
+            .unwrap();
+        tracking.await.unwrap();
+
+        assert!(matches!(
+            tracker.get(&key.commitment).await,
+            Some(ProofStatus::Failed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn replaced_submission_does_not_override_its_replacement() {
+        let tracker = Arc::new(ProofStatusTracker::new(10));
+        // Bumping the fee keeps the commitment, so both submissions share the key
+        let key = proof_key([4u8; 32]);
+        let old_submission = tracker.register(key, ProofStatus::Queued(None)).await;
+        let (old_sink, old_receiver) = ChannelSink::channel();
+        let old_tracking = tokio::spawn(tracker.clone().track(key, old_submission, old_receiver));
+
+        let new_submission = tracker.register(key, ProofStatus::Queued(None)).await;
+        let (new_sink, new_receiver) = ChannelSink::channel();
+        let new_tracking = tokio::spawn(tracker.clone().track(key, new_submission, new_receiver));
+
+        // The batcher closes the sink of the replaced entry
+        futures_util::SinkExt::close(&mut *old_sink.write().await)
+            .await
+            .unwrap();
+        old_tracking.await.unwrap();
+        assert!(matches!(
+            tracker.get(&key.commitment).await,
+            Some(ProofStatus::Queued(None))
+        ));
+
+        send_message(new_sink, SubmitProofResponseMessage::BatchReset).await;
+        new_tracking.await.unwrap();
+        assert!(matches!(
+            tracker.get(&key.commitment).await,
+            Some(ProofStatus::Failed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn evicted_proof_is_marked_as_underpriced() {
+        let tracker = Arc::new(ProofStatusTracker::new(10));
+        let key = proof_key([5u8; 32]);
+        let submission = tracker.register(key, ProofStatus::Queued(None)).await;
+
+        let (sink, receiver) = ChannelSink::channel();
+        let tracking = tokio::spawn(tracker.clone().track(key, submission, receiver));
+        // The batcher sends the response and drops the evicted entry along with its sink
+        send_message(sink, SubmitProofResponseMessage::UnderpricedProof).await;
+        tracking.await.unwrap();
+
+        assert!(matches!(
+            tracker.get(&key.commitment).await,
+            Some(ProofStatus::Underpriced)
+        ));
+    }
+
+    #[tokio::test]
+    async fn tracker_only_keeps_latest_proofs() {
+        let tracker = ProofStatusTracker::new(2);
+        for i in 0..3u8 {
+            tracker
+                .register(proof_key([i; 32]), ProofStatus::Queued(None))
+                .await;
+        }
+        assert!(tracker.get(&[0; 32]).await.is_none());
+        assert!(tracker.get(&[1; 32]).await.is_some());
+        assert!(tracker.get(&[2; 32]).await.is_some());
+    }
+
+    #[test]
+    fn request_format_is_taken_from_header() {
+        let nonce = GetNonceResponseMessage::Nonce(U256::from(7));
+        let cbor = cbor_serialize(&nonce).unwrap();
+        let json = serde_json::to_vec(&nonce).unwrap();
+
+        let format = Format::from_header(Some("application/cbor".to_string()));
+        assert_eq!(format, Format::Cbor);
+        assert!(matches!(
+            format.decode::<GetNonceResponseMessage>(&cbor),
+            Ok(GetNonceResponseMessage::Nonce(n)) if n == U256::from(7)
+        ));
+        assert!(format.decode::<GetNonceResponseMessage>(&json).is_err());
+
+        let format = Format::from_header(None);
+        assert_eq!(format, Format::Json);
+        assert!(matches!(
+            format.decode::<GetNonceResponseMessage>(&json),
+            Ok(GetNonceResponseMessage::Nonce(n)) if n == U256::from(7)
+        ));
+    }
+
+    #[test]
+    fn commitments_are_parsed_from_hex() {
+        let commitment = [0xab; 32];
+        let encoded = hex::encode(commitment);
+        assert_eq!(parse_commitment(&encoded), Some(commitment));
+        assert_eq!(parse_commitment(&format!("0x{encoded}")), Some(commitment));
+        assert_eq!(parse_commitment("0x1234"), None);
+        assert_eq!(parse_commitment("not hex"), None);
+    }
+}
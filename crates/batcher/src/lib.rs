@@ -12,7 +12,9 @@ use retry::batcher_retryables::{
     user_balance_is_unlocked_retryable,
 };
 use retry::{retry_function, RetryError};
+use tls::{BatcherStream, CertificateResolver};
 use tokio::time::{timeout, Instant};
+use tokio_rustls::TlsAcceptor;
 use types::batch_state::BatchState;
 use types::user_state::UserState;
 
@@ -33,8 +35,9 @@ use aligned_sdk::common::constants::{
 };
 use aligned_sdk::common::types::{
     ClientMessage, GetNonceResponseMessage, NoncedVerificationData, ProofInvalidReason,
-    ProvingSystemId, SubmitProofMessage, SubmitProofResponseMessage, VerificationCommitmentBatch,
-    VerificationData, VerificationDataCommitment,
+    ProofReceipt, ProvingSystemId, SignedProofReceipt, SubmitProofMessage,
+    SubmitProofResponseMessage, VerificationCommitmentBatch, VerificationData,
+    VerificationDataCommitment,
 };
 
 use aws_sdk_s3::client::Client as S3Client;
@@ -60,12 +63,17 @@ mod connection;
 mod eth;
 mod ffi;
 pub mod gnark;
+pub mod health;
+mod http;
 pub mod metrics;
+#[cfg(feature = "noir")]
+pub mod noir;
 pub mod retry;
 pub mod risc_zero;
 pub mod s3;
 pub mod sp1;
 pub mod telemetry;
+pub mod tls;
 pub mod types;
 mod zk_utils;
 
@@ -91,12 +99,19 @@ pub struct Batcher {
     max_batch_byte_size: usize,
     max_batch_proof_qty: usize,
     last_uploaded_batch_block: Mutex<u64>,
+    last_seen_block: Mutex<u64>,
     pre_verification_is_enabled: bool,
     non_paying_config: Option<NonPayingConfig>,
     posting_batch: Mutex<bool>,
     disabled_verifiers: Mutex<U256>,
     aggregator_fee_percentage_multiplier: u128,
     aggregator_gas_cost: u128,
+    user_state_idle_eviction: Duration,
+    tls_acceptor: Option<TlsAcceptor>,
+    http_config: Option<(SocketAddr, usize)>,
+    block_listener_status: health::BlockListenerStatus,
+    ready_max_block_age: Duration,
+    ready_min_batcher_balance: U256,
     pub metrics: metrics::BatcherMetrics,
     pub telemetry: TelemetrySender,
 }
@@ -248,6 +263,30 @@ impl Batcher {
             config.batcher.telemetry_ip_port_address
         ));
 
+        let tls_acceptor = config.batcher.tls.as_ref().map(|tls_config| {
+            info!(
+                "TLS enabled. Loading certificate from {} and key from {}",
+                tls_config.cert_path, tls_config.key_path
+            );
+            let resolver = Arc::new(
+                CertificateResolver::new(&tls_config.cert_path, &tls_config.key_path)
+                    .expect("Failed to load TLS certificate"),
+            );
+            tls::spawn_certificate_reloader(
+                resolver.clone(),
+                Duration::from_secs(tls_config.reload_interval),
+            );
+            tls::build_tls_acceptor(resolver).expect("Failed to build TLS acceptor")
+        });
+
+        let http_config = config.batcher.http.as_ref().map(|http_config| {
+            let address = http_config
+                .address
+                .parse::<SocketAddr>()
+                .expect("Failed to parse HTTP API address");
+            (address, http_config.max_tracked_proofs)
+        });
+
         Self {
             s3_client,
             s3_bucket_name,
@@ -267,12 +306,21 @@ impl Batcher {
             max_batch_byte_size: config.batcher.max_batch_byte_size,
             max_batch_proof_qty: config.batcher.max_batch_proof_qty,
             last_uploaded_batch_block: Mutex::new(last_uploaded_batch_block),
+            last_seen_block: Mutex::new(last_uploaded_batch_block),
             pre_verification_is_enabled: config.batcher.pre_verification_is_enabled,
             non_paying_config,
             aggregator_fee_percentage_multiplier: config
                 .batcher
                 .aggregator_fee_percentage_multiplier,
             aggregator_gas_cost: config.batcher.aggregator_gas_cost,
+            user_state_idle_eviction: Duration::from_secs(
+                config.batcher.user_state_idle_eviction_secs,
+            ),
+            tls_acceptor,
+            http_config,
+            block_listener_status: health::BlockListenerStatus::default(),
+            ready_max_block_age: Duration::from_secs(config.batcher.ready_max_block_age_secs),
+            ready_min_batcher_balance: U256::from(config.batcher.ready_min_batcher_balance_wei),
             posting_batch: Mutex::new(false),
             batch_state: Mutex::new(batch_state),
             disabled_verifiers: Mutex::new(disabled_verifiers),
@@ -303,6 +351,15 @@ impl Batcher {
         }
     }
 
+    /// Serves the HTTP API if it is enabled in the config.
+    /// Returns immediately otherwise.
+    pub async fn listen_http(self: Arc<Self>) {
+        let Some((address, max_tracked_proofs)) = self.http_config else {
+            return;
+        };
+        http::serve(self, address, max_tracked_proofs).await;
+    }
+
     /// Listen for Ethereum new blocks.
     /// Retries on recoverable errors using exponential backoff
     /// with the maximum number of retries and a `MAX_DELAY` of 1 hour.
@@ -362,6 +419,8 @@ impl Batcher {
             },
             None => None,
         };
+        self.block_listener_status
+            .set_subscriptions(stream.is_some(), stream_fallback.is_some());
         if stream.is_none() && stream_fallback.is_none() {
             return Err(RetryError::Transient(
                 BatcherError::EthereumSubscriptionError(
@@ -370,8 +429,6 @@ impl Batcher {
             ));
         }
 
-        let last_seen_block = Mutex::<u64>::new(0);
-
         loop {
             // Wait for both responses
             let (block_main, block_fallback) = join!(
@@ -389,6 +446,8 @@ impl Batcher {
                 }
             );
 
+            self.block_listener_status
+                .set_subscriptions(block_main.is_some(), block_fallback.is_some());
             let block = if let Some(block) = block_main {
                 block
             } else if let Some(block) = block_fallback {
@@ -397,13 +456,14 @@ impl Batcher {
                 // Both rpc failed to respond, break and try to reconnect
                 break;
             };
+            self.block_listener_status.block_received().await;
 
             let batcher = self.clone();
             let block_number = block.number.unwrap_or_default();
             let block_number = u64::try_from(block_number).unwrap_or_default();
 
             {
-                let mut last_seen_block = last_seen_block.lock().await;
+                let mut last_seen_block = self.last_seen_block.lock().await;
                 if block_number <= *last_seen_block {
                     continue;
                 }
@@ -432,7 +492,28 @@ impl Batcher {
         info!("Incoming TCP connection from: {}", addr);
         self.metrics.open_connections.inc();
 
-        let ws_stream_future = tokio_tungstenite::accept_async(raw_stream);
+        let stream = match &self.tls_acceptor {
+            Some(tls_acceptor) => {
+                let tls_stream_future = tls_acceptor.accept(raw_stream);
+                match timeout(Duration::from_secs(CONNECTION_TIMEOUT), tls_stream_future).await {
+                    Ok(Ok(tls_stream)) => BatcherStream::Tls(Box::new(tls_stream)),
+                    Ok(Err(e)) => {
+                        warn!("Error while establishing TLS connection: {}", e);
+                        self.metrics.open_connections.dec();
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        warn!("Error while establishing TLS connection: {}", e);
+                        self.metrics.open_connections.dec();
+                        self.metrics.user_error(&["user_timeout", ""]);
+                        return Ok(());
+                    }
+                }
+            }
+            None => BatcherStream::Plain(raw_stream),
+        };
+
+        let ws_stream_future = tokio_tungstenite::accept_async(stream);
         let ws_stream =
             match timeout(Duration::from_secs(CONNECTION_TIMEOUT), ws_stream_future).await {
                 Ok(Ok(stream)) => stream,
@@ -451,7 +532,7 @@ impl Batcher {
 
         debug!("WebSocket connection established: {}", addr);
         let (outgoing, incoming) = ws_stream.split();
-        let outgoing = Arc::new(RwLock::new(outgoing));
+        let outgoing: WsMessageSink = Arc::new(RwLock::new(outgoing));
 
         let protocol_version_msg = SubmitProofResponseMessage::ProtocolVersion(
             aligned_sdk::communication::protocol::EXPECTED_PROTOCOL_VERSION,
@@ -687,17 +768,19 @@ impl Batcher {
                 return Ok(());
             }
 
-            if !zk_utils::verify(verification_data).await {
-                error!("Invalid proof detected. Verification failed");
+            if let Err(reason) = zk_utils::verify(verification_data).await {
+                error!("Invalid proof detected: {}", reason);
+                let error_type = match reason {
+                    ProofInvalidReason::RejectedProof => "rejected_proof",
+                    _ => "malformed_verification_data",
+                };
                 send_message(
                     ws_conn_sink.clone(),
-                    SubmitProofResponseMessage::InvalidProof(ProofInvalidReason::RejectedProof),
+                    SubmitProofResponseMessage::InvalidProof(reason),
                 )
                 .await;
-                self.metrics.user_error(&[
-                    "rejected_proof",
-                    &format!("{}", verification_data.proving_system),
-                ]);
+                self.metrics
+                    .user_error(&[error_type, &format!("{}", verification_data.proving_system)]);
                 return Ok(());
             }
         }
@@ -711,40 +794,6 @@ impl Batcher {
             return Ok(());
         }
 
-        // We acquire the lock first only to query if the user is already present and the lock is dropped.
-        // If it was not present, then the user nonce is queried to the Aligned contract.
-        // Lastly, we get a lock of the batch state again and insert the user state if it was still missing.
-
-        let is_user_in_state: bool;
-        {
-            let batch_state_lock = self.batch_state.lock().await;
-            is_user_in_state = batch_state_lock.user_states.contains_key(&addr);
-        }
-
-        if !is_user_in_state {
-            let ethereum_user_nonce = match self.get_user_nonce_from_ethereum(addr).await {
-                Ok(ethereum_user_nonce) => ethereum_user_nonce,
-                Err(e) => {
-                    error!(
-                        "Failed to get user nonce from Ethereum for address {addr:?}. Error: {e:?}"
-                    );
-                    send_message(
-                        ws_conn_sink.clone(),
-                        SubmitProofResponseMessage::EthRpcError,
-                    )
-                    .await;
-                    self.metrics.user_error(&["eth_rpc_error", ""]);
-                    return Ok(());
-                }
-            };
-            let user_state = UserState::new(ethereum_user_nonce);
-            let mut batch_state_lock = self.batch_state.lock().await;
-            batch_state_lock
-                .user_states
-                .entry(addr)
-                .or_insert(user_state);
-        }
-
         // * ---------------------------------------------------*
         // *        Perform validations over user state         *
         // * ---------------------------------------------------*
@@ -764,7 +813,19 @@ impl Batcher {
         // This is needed because we need to query the user state to make validations and
         // finally add the proof to the batch queue.
 
-        let mut batch_state_lock = self.batch_state.lock().await;
+        let mut batch_state_lock = match self.lock_batch_state_with_user(addr).await {
+            Ok(batch_state_lock) => batch_state_lock,
+            Err(e) => {
+                error!("Failed to get user nonce from Ethereum for address {addr:?}. Error: {e:?}");
+                send_message(
+                    ws_conn_sink.clone(),
+                    SubmitProofResponseMessage::EthRpcError,
+                )
+                .await;
+                self.metrics.user_error(&["eth_rpc_error", ""]);
+                return Ok(());
+            }
+        };
 
         let msg_max_fee = nonced_verification_data.max_fee;
         let Some(user_last_max_fee_limit) =
@@ -924,6 +985,9 @@ impl Batcher {
         // *        Add message data into the queue and update user state         *
         // * ---------------------------------------------------------------------*
 
+        let block_received = *self.last_seen_block.lock().await;
+        let proof_receipt = ProofReceipt::new(&nonced_verification_data, block_received);
+
         if let Err(e) = self
             .add_to_batch(
                 batch_state_lock,
@@ -940,10 +1004,26 @@ impl Batcher {
             return Ok(());
         };
 
+        self.send_proof_receipt(ws_conn_sink, proof_receipt).await;
+
         info!("Verification data message handled");
         Ok(())
     }
 
+    /// Sends the user a receipt signed by the batcher, acknowledging their proof was added to the queue.
+    async fn send_proof_receipt(&self, ws_conn_sink: WsMessageSink, proof_receipt: ProofReceipt) {
+        match SignedProofReceipt::new(proof_receipt, self.batcher_signer.signer()).await {
+            Ok(signed_receipt) => {
+                send_message(
+                    ws_conn_sink,
+                    SubmitProofResponseMessage::ProofQueued(signed_receipt),
+                )
+                .await
+            }
+            Err(e) => error!("Could not sign proof receipt: {}", e),
+        }
+    }
+
     async fn is_verifier_disabled(&self, verifier: ProvingSystemId) -> bool {
         let disabled_verifiers = self.disabled_verifiers.lock().await;
         zk_utils::is_verifier_disabled(*disabled_verifiers, verifier)
@@ -1090,7 +1170,14 @@ impl Batcher {
                 SubmitProofResponseMessage::AddToBatchError,
             )
             .await;
+            return;
         };
+        std::mem::drop(batch_state_lock);
+
+        let block_received = *self.last_seen_block.lock().await;
+        let proof_receipt =
+            ProofReceipt::new(&replacement_entry.nonced_verification_data, block_received);
+        self.send_proof_receipt(ws_conn_sink, proof_receipt).await;
     }
 
     async fn disabled_verifiers(&self) -> Result<U256, ContractError<SignerMiddlewareT>> {
@@ -1105,6 +1192,29 @@ impl Batcher {
         }
     }
 
+    /// Locks the batch state, making sure the state of the user with address `addr` is cached.
+    ///
+    /// If the user is not cached, either because it is new or because it was evicted for being idle,
+    /// the lock is released while its nonce is fetched from the `BatcherPaymentService`, and its state
+    /// is inserted once the lock is taken again. Since the returned lock is held until the message is
+    /// processed, the user can't be evicted in the meantime.
+    async fn lock_batch_state_with_user(
+        &self,
+        addr: Address,
+    ) -> Result<MutexGuard<'_, BatchState>, RetryError<String>> {
+        {
+            let mut batch_state_lock = self.batch_state.lock().await;
+            if batch_state_lock.touch_user_state(&addr) {
+                return Ok(batch_state_lock);
+            }
+        }
+
+        let ethereum_user_nonce = self.get_user_nonce_from_ethereum(addr).await?;
+        let mut batch_state_lock = self.batch_state.lock().await;
+        batch_state_lock.load_user_state(addr, ethereum_user_nonce);
+        Ok(batch_state_lock)
+    }
+
     /// Gets the user nonce from Ethereum.
     /// Retries on recoverable errors using exponential backoff up to `ETHEREUM_CALL_MAX_RETRIES` times:
     /// (0,5 secs - 1 secs - 2 secs - 4 secs - 8 secs).
@@ -1141,18 +1251,13 @@ impl Batcher {
         let verification_data_comm = verification_data.clone().into();
         info!("Adding verification data to batch...");
 
-        let max_fee = verification_data.max_fee;
-        let nonce = verification_data.nonce;
-        batch_state_lock.batch_queue.push(
-            BatchQueueEntry::new(
-                verification_data,
-                verification_data_comm,
-                ws_conn_sink,
-                proof_submitter_sig,
-                proof_submitter_addr,
-            ),
-            BatchQueueEntryPriority::new(max_fee, nonce),
-        );
+        let result = batch_state_lock.add_entry(BatchQueueEntry::new(
+            verification_data,
+            verification_data_comm,
+            ws_conn_sink,
+            proof_submitter_sig,
+            proof_submitter_addr,
+        ));
 
         // Update metrics
         let queue_len = batch_state_lock.batch_queue.len();
@@ -1162,47 +1267,10 @@ impl Batcher {
 
         info!("Current batch queue length: {}", queue_len);
 
-        let Some(user_proof_count) = batch_state_lock
-            .get_user_proof_count(&proof_submitter_addr)
-            .await
-        else {
-            error!("User state of address {proof_submitter_addr} was not found when trying to update user state. This user state should have been present");
-            std::mem::drop(batch_state_lock);
-            return Err(BatcherError::AddressNotFoundInUserStates(
-                proof_submitter_addr,
-            ));
-        };
-
-        let Some(current_total_fees_in_queue) = batch_state_lock
-            .get_user_total_fees_in_queue(&proof_submitter_addr)
-            .await
-        else {
+        if result.is_err() {
             error!("User state of address {proof_submitter_addr} was not found when trying to update user state. This user state should have been present");
-            std::mem::drop(batch_state_lock);
-            return Err(BatcherError::AddressNotFoundInUserStates(
-                proof_submitter_addr,
-            ));
-        };
-
-        // User state is updated
-        if batch_state_lock
-            .update_user_state(
-                &proof_submitter_addr,
-                nonce + U256::one(),
-                max_fee,
-                user_proof_count + 1,
-                current_total_fees_in_queue + max_fee,
-            )
-            .is_none()
-        {
-            error!("User state of address {proof_submitter_addr} was not found when trying to update user state. This user state should have been present");
-            std::mem::drop(batch_state_lock);
-            return Err(BatcherError::AddressNotFoundInUserStates(
-                proof_submitter_addr,
-            ));
-        };
-
-        Ok(())
+        }
+        result
     }
 
     /// Given a new block number listened from the blockchain, checks if the current batch is ready to be posted.
@@ -1290,45 +1358,7 @@ impl Batcher {
         info!("Removing proofs from queue...");
         let mut batch_state_lock = self.batch_state.lock().await;
 
-        finalized_batch.iter().for_each(|entry| {
-            if batch_state_lock.batch_queue.remove(entry).is_none() {
-                // If this happens, we have a bug in our code
-                error!("Some proofs were not found in the queue. This should not happen.");
-            }
-        });
-
-        // now we calculate the new user_states
-        let new_user_states = // proofs, max_fee_limit, total_fees_in_queue
-            batch_state_lock.calculate_new_user_states_data();
-
-        let user_addresses: Vec<Address> = batch_state_lock.user_states.keys().cloned().collect();
-        let default_value = (0, U256::MAX, U256::zero());
-        for addr in user_addresses.iter() {
-            let (proof_count, max_fee_limit, total_fees_in_queue) =
-                new_user_states.get(addr).unwrap_or(&default_value);
-
-            // FIXME: The case where a the update functions return `None` can only happen when the user was not found
-            // in the `user_states` map should not really happen here, but doing this check so that we don't unwrap.
-            // Once https://github.com/yetanotherco/aligned_layer/issues/1046 is done we could return a more
-            // informative error.
-
-            // Now we update the user states related to the batch (proof count in batch and min fee in batch)
-            batch_state_lock
-                .update_user_proof_count(addr, *proof_count)
-                .ok_or(BatcherError::QueueRemoveError(
-                    "Could not update_user_proof_count".into(),
-                ))?;
-            batch_state_lock
-                .update_user_max_fee_limit(addr, *max_fee_limit)
-                .ok_or(BatcherError::QueueRemoveError(
-                    "Could not update_user_max_fee_limit".into(),
-                ))?;
-            batch_state_lock
-                .update_user_total_fees_in_queue(addr, *total_fees_in_queue)
-                .ok_or(BatcherError::QueueRemoveError(
-                    "Could not update_user_total_fees_in_queue".into(),
-                ))?;
-        }
+        batch_state_lock.remove_finalized_entries(&finalized_batch)?;
 
         // Update metrics
         let queue_len = batch_state_lock.batch_queue.len();
@@ -1447,7 +1477,40 @@ impl Batcher {
             error!("Unexpected error while updating queue: {:?}", e);
         }
 
-        connection::send_batch_inclusion_data_responses(finalized_batch, &batch_merkle_tree).await
+        connection::send_batch_inclusion_data_responses(
+            finalized_batch,
+            &batch_merkle_tree,
+            self.batcher_signer.signer(),
+        )
+        .await
+    }
+
+    /// Returns the proofs of the given address that are waiting in the queue.
+    pub(crate) async fn get_queued_proofs(&self, addr: &Address) -> Vec<http::QueuedProof> {
+        let batch_state_lock = self.batch_state.lock().await;
+        let mut queued_proofs: Vec<http::QueuedProof> = batch_state_lock
+            .batch_queue
+            .iter()
+            .filter(|(entry, _)| entry.sender == *addr)
+            .map(|(entry, _)| http::QueuedProof {
+                commitment: hex::encode(VerificationCommitmentBatch::hash_data(
+                    &entry.verification_data_commitment,
+                )),
+                nonce: entry.nonced_verification_data.nonce,
+                max_fee: entry.nonced_verification_data.max_fee,
+            })
+            .collect();
+        queued_proofs.sort_by_key(|proof| proof.nonce);
+        queued_proofs
+    }
+
+    /// Checks whether a proof with the given commitment is waiting in the queue.
+    pub(crate) async fn is_commitment_queued(&self, commitment: &[u8; 32]) -> bool {
+        let batch_state_lock = self.batch_state.lock().await;
+        batch_state_lock.batch_queue.iter().any(|(entry, _)| {
+            VerificationCommitmentBatch::hash_data(&entry.verification_data_commitment)
+                == *commitment
+        })
     }
 
     async fn flush_queue_and_clear_nonce_cache(&self) {
@@ -1488,6 +1551,22 @@ impl Batcher {
         self.metrics.update_queue_metrics(0, 0);
     }
 
+    /// Removes the cached state of users that have been idle for longer than the configured period.
+    /// Their nonce is fetched again from the `BatcherPaymentService` on their next message.
+    async fn evict_idle_users(&self) {
+        let mut batch_state_lock = self.batch_state.lock().await;
+        let evicted_users = batch_state_lock.evict_idle_users(
+            self.user_state_idle_eviction,
+            self.get_nonpaying_replacement_addr(),
+        );
+        if evicted_users > 0 {
+            info!("Evicted {} idle users from the user states", evicted_users);
+        }
+        self.metrics
+            .cached_users
+            .set(batch_state_lock.user_states.len() as i64);
+    }
+
     /// Receives new block numbers, checks if conditions are met for submission and
     /// finalizes the batch.
     async fn handle_new_block(&self, block_number: u64) -> Result<(), BatcherError> {
@@ -1511,6 +1590,8 @@ impl Batcher {
             }
         }
 
+        self.evict_idle_users().await;
+
         let modified_gas_price = gas_price * U256::from(GAS_PRICE_PERCENTAGE_MULTIPLIER)
             / U256::from(PERCENTAGE_DIVIDER);
 
@@ -42,6 +42,7 @@ async fn main() -> Result<(), BatcherError> {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
     let batcher = Batcher::new(cli.config).await;
     let batcher = Arc::new(batcher);
+    batcher.metrics.set_readiness_check(&batcher);
 
     let address = format!("{addr}:{port}");
 
@@ -55,6 +56,12 @@ async fn main() -> Result<(), BatcherError> {
         }
     });
 
+    // spawn task to serve the HTTP API, if enabled
+    tokio::spawn({
+        let app = batcher.clone();
+        async move { app.listen_http().await }
+    });
+
     batcher.metrics.inc_batcher_restart();
 
     batcher.listen_connections(&address).await?;
@@ -1,4 +1,8 @@
-use std::{thread, time::Duration};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 // Prometheus
 use prometheus::{
@@ -7,7 +11,9 @@ use prometheus::{
     IntCounter, IntCounterVec, IntGauge,
 };
 
-use warp::{Filter, Rejection, Reply};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+use crate::health::{HealthReport, ReadinessCheck, ReadinessProbe};
 
 #[derive(Clone, Debug)]
 pub struct BatcherMetrics {
@@ -22,11 +28,13 @@ pub struct BatcherMetrics {
     pub broken_ws_connections: IntCounter,
     pub queue_len: IntGauge,
     pub queue_size_bytes: IntGauge,
+    pub cached_users: IntGauge,
     pub s3_duration: IntGauge,
     pub create_new_task_duration: IntGauge,
     pub cancel_create_new_task_duration: IntGauge,
     pub batcher_gas_cost_create_task_total: GenericCounter<AtomicF64>,
     pub batcher_gas_cost_cancel_task_total: GenericCounter<AtomicF64>,
+    readiness_probe: ReadinessProbe,
 }
 
 impl BatcherMetrics {
@@ -59,6 +67,10 @@ impl BatcherMetrics {
             "queue_size_bytes",
             "Accumulated size in bytes of all proofs in the queue"
         ))?;
+        let cached_users = register_int_gauge!(opts!(
+            "cached_users",
+            "Amount of user states cached by the batcher"
+        ))?;
         let s3_duration = register_int_gauge!(opts!("s3_duration", "S3 Duration"))?;
         let create_new_task_duration = register_int_gauge!(opts!(
             "create_new_task_duration",
@@ -91,6 +103,7 @@ impl BatcherMetrics {
         registry.register(Box::new(broken_ws_connections.clone()))?;
         registry.register(Box::new(queue_len.clone()))?;
         registry.register(Box::new(queue_size_bytes.clone()))?;
+        registry.register(Box::new(cached_users.clone()))?;
         registry.register(Box::new(s3_duration.clone()))?;
         registry.register(Box::new(create_new_task_duration.clone()))?;
         registry.register(Box::new(cancel_create_new_task_duration.clone()))?;
@@ -101,8 +114,24 @@ impl BatcherMetrics {
             .and(warp::any().map(move || registry.clone()))
             .and_then(BatcherMetrics::metrics_handler);
 
+        let started_at = Instant::now();
+        let health_route = warp::path!("health").map(move || {
+            warp::reply::json(&HealthReport {
+                status: "ok",
+                uptime_secs: started_at.elapsed().as_secs(),
+            })
+        });
+
+        let readiness_probe = ReadinessProbe::default();
+        let ready_route = warp::path!("ready")
+            .and(warp::any().map({
+                let readiness_probe = readiness_probe.clone();
+                move || readiness_probe.clone()
+            }))
+            .and_then(BatcherMetrics::ready_handler);
+
         tokio::task::spawn(async move {
-            warp::serve(metrics_route)
+            warp::serve(metrics_route.or(health_route).or(ready_route))
                 .run(([0, 0, 0, 0], metrics_port))
                 .await;
         });
@@ -119,11 +148,13 @@ impl BatcherMetrics {
             broken_ws_connections,
             queue_len,
             queue_size_bytes,
+            cached_users,
             s3_duration,
             create_new_task_duration,
             cancel_create_new_task_duration,
             batcher_gas_cost_create_task_total,
             batcher_gas_cost_cancel_task_total,
+            readiness_probe,
         })
     }
 
@@ -143,6 +174,22 @@ impl BatcherMetrics {
         Ok(res)
     }
 
+    /// Answers 200 if all the readiness checks pass and 503 otherwise, with the report as JSON.
+    pub async fn ready_handler(readiness_probe: ReadinessProbe) -> Result<impl Reply, Rejection> {
+        let report = readiness_probe.report().await;
+        let status = if report.ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Ok(warp::reply::with_status(warp::reply::json(&report), status))
+    }
+
+    /// Sets what `/ready` checks. Until this is called, it reports not ready.
+    pub fn set_readiness_check<T: ReadinessCheck + 'static>(&self, check: &Arc<T>) {
+        self.readiness_probe.set(check);
+    }
+
     pub fn inc_batcher_restart(&self) {
         // Sleep for 2 seconds to allow prometheus to start and set the metrics with default intial values.
         // If prometheus is not ready, the metrics will directly be set to 1 and prometheus will not be able to display the correct increment.
@@ -0,0 +1,157 @@
+//! Verification of Noir UltraHonk proofs through the Barretenberg `bb` CLI.
+//!
+//! The binary is looked up in `PATH`, or in `BB_BINARY_PATH` if set. The proof,
+//! verification key and public inputs are the `proof`, `vk` and `public_inputs`
+//! files written by `bb prove --write_vk`. A verification that takes longer than
+//! [`BB_VERIFY_TIMEOUT`] is killed and the proof rejected.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::zk_utils::{AuxDataCommitment, ProofVerifier};
+use aligned_sdk::common::types::{VerificationData, VerificationDataField};
+use log::{debug, warn};
+
+const DEFAULT_BB_BINARY: &str = "bb";
+
+/// UltraHonk proofs are verified in well under a second, so this only bounds a stuck process.
+pub const BB_VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const BB_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static VERIFICATION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub fn verify_noir_ultra_honk_proof(
+    proof: &[u8],
+    public_inputs: &[u8],
+    verification_key: &[u8],
+) -> bool {
+    if proof.is_empty() || verification_key.is_empty() {
+        warn!("Noir UltraHonk input buffers zero size");
+        return false;
+    }
+
+    let work_dir = env::temp_dir().join(format!(
+        "aligned-noir-{}-{}",
+        std::process::id(),
+        VERIFICATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let is_valid =
+        run_bb_verify(&work_dir, proof, public_inputs, verification_key).unwrap_or_else(|e| {
+            warn!("Could not verify Noir UltraHonk proof: {}", e);
+            false
+        });
+    if let Err(e) = fs::remove_dir_all(&work_dir) {
+        warn!("Could not remove {}: {}", work_dir.display(), e);
+    }
+    is_valid
+}
+
+fn run_bb_verify(
+    work_dir: &Path,
+    proof: &[u8],
+    public_inputs: &[u8],
+    verification_key: &[u8],
+) -> Result<bool, io::Error> {
+    fs::create_dir_all(work_dir)?;
+    let proof_path = work_dir.join("proof");
+    let public_inputs_path = work_dir.join("public_inputs");
+    let vk_path = work_dir.join("vk");
+    fs::write(&proof_path, proof)?;
+    fs::write(&public_inputs_path, public_inputs)?;
+    fs::write(&vk_path, verification_key)?;
+
+    let mut child = Command::new(bb_binary())
+        .arg("verify")
+        .arg("--scheme")
+        .arg("ultra_honk")
+        .arg("-k")
+        .arg(&vk_path)
+        .arg("-p")
+        .arg(&proof_path)
+        .arg("-i")
+        .arg(&public_inputs_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let status = wait_with_timeout(&mut child, BB_VERIFY_TIMEOUT)?;
+    debug!("Noir UltraHonk proof is valid: {}", status.success());
+    Ok(status.success())
+}
+
+/// Waits for the process to exit, killing it if it is still running after `timeout`.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<ExitStatus, io::Error> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("bb did not finish in {} seconds", timeout.as_secs()),
+            ));
+        }
+        thread::sleep(BB_POLL_INTERVAL);
+    }
+}
+
+fn bb_binary() -> PathBuf {
+    env::var_os("BB_BINARY_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_BB_BINARY))
+}
+
+pub(crate) struct NoirUltraHonkVerifier;
+
+impl ProofVerifier for NoirUltraHonkVerifier {
+    fn required_fields(&self) -> &'static [VerificationDataField] {
+        // Circuits without public inputs have an empty public inputs file
+        &[
+            VerificationDataField::Proof,
+            VerificationDataField::VerificationKey,
+        ]
+    }
+
+    fn aux_data_commitment(&self) -> AuxDataCommitment {
+        AuxDataCommitment::VerificationKey
+    }
+
+    fn verify(&self, verification_data: &VerificationData) -> bool {
+        let Some(vk) = verification_data.verification_key.as_ref() else {
+            return false;
+        };
+        let public_inputs = verification_data.pub_input.as_deref().unwrap_or_default();
+        verify_noir_ultra_honk_proof(&verification_data.proof, public_inputs, vk)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::wait_with_timeout;
+    use std::{io, process::Command, time::Duration};
+
+    #[test]
+    fn test_wait_with_timeout_kills_slow_process() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let result = wait_with_timeout(&mut child, Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        // The process was reaped
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_wait_with_timeout_returns_exit_status() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let status = wait_with_timeout(&mut child, Duration::from_secs(10)).unwrap();
+        assert!(status.success());
+    }
+}
@@ -1,4 +1,8 @@
-use log::error;
+use crate::zk_utils::{AuxDataCommitment, ProofVerifier};
+use aligned_sdk::common::types::{
+    ProofInvalidReason, ProvingSystemId, VerificationData, VerificationDataField,
+};
+use log::{error, warn};
 use risc0_zkvm::{InnerReceipt, Receipt};
 
 pub fn verify_risc_zero_proof(
@@ -13,9 +17,91 @@ pub fn verify_risc_zero_proof(
     }
 
     if let Ok(inner_receipt) = bincode::deserialize::<InnerReceipt>(inner_receipt_bytes) {
+        let kind = receipt_kind(&inner_receipt);
+        // The default verifier context checks composite, succinct and Groth16 receipts
+        // against the parameters of the risc0-zkvm version the batcher is built with.
         let receipt = Receipt::new(inner_receipt, public_input.to_vec());
-        return receipt.verify(*image_id).is_ok();
+        return match receipt.verify(*image_id) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Risc0 {} receipt verification failed: {}", kind, e);
+                false
+            }
+        };
     }
 
     false
 }
+
+/// Checks that the image id is 32 bytes long and the proof deserializes into a receipt
+/// of a supported kind: composite, succinct or Groth16.
+pub fn validate_risc_zero_proof(
+    inner_receipt_bytes: &[u8],
+    image_id: &[u8],
+) -> Result<(), ProofInvalidReason> {
+    if image_id.len() != 32 {
+        return Err(ProofInvalidReason::MalformedImageId(image_id.len()));
+    }
+    let inner_receipt = bincode::deserialize::<InnerReceipt>(inner_receipt_bytes)
+        .map_err(|_| ProofInvalidReason::UndeserializableProof(ProvingSystemId::Risc0))?;
+    match inner_receipt {
+        InnerReceipt::Composite(_) | InnerReceipt::Succinct(_) | InnerReceipt::Groth16(_) => Ok(()),
+        // Fake receipts only verify in dev mode
+        other => Err(ProofInvalidReason::UnsupportedReceiptKind(
+            receipt_kind(&other).to_string(),
+        )),
+    }
+}
+
+/// Name of the kind of the receipt, used in logs and rejection reasons.
+pub fn receipt_kind(inner_receipt: &InnerReceipt) -> &'static str {
+    match inner_receipt {
+        InnerReceipt::Composite(_) => "composite",
+        InnerReceipt::Succinct(_) => "succinct",
+        InnerReceipt::Groth16(_) => "groth16",
+        InnerReceipt::Fake(_) => "fake",
+        _ => "unknown",
+    }
+}
+
+pub(crate) struct Risc0Verifier;
+
+impl ProofVerifier for Risc0Verifier {
+    fn required_fields(&self) -> &'static [VerificationDataField] {
+        &[
+            VerificationDataField::Proof,
+            VerificationDataField::VmProgramCode,
+        ]
+    }
+
+    fn aux_data_commitment(&self) -> AuxDataCommitment {
+        AuxDataCommitment::VmProgramCode
+    }
+
+    fn validate(&self, verification_data: &VerificationData) -> Result<(), ProofInvalidReason> {
+        crate::zk_utils::validate_fields(
+            verification_data,
+            self.required_fields(),
+            self.aux_data_commitment(),
+        )?;
+        let image_id = verification_data
+            .vm_program_code
+            .as_deref()
+            .unwrap_or_default();
+        validate_risc_zero_proof(&verification_data.proof, image_id)
+    }
+
+    fn verify(&self, verification_data: &VerificationData) -> bool {
+        let image_id = verification_data
+            .vm_program_code
+            .as_deref()
+            .unwrap_or_default();
+        let Ok(image_id) = <[u8; 32]>::try_from(image_id) else {
+            warn!("Trying to verify Risc0 proof but image id is not 32 bytes. Returning false");
+            return false;
+        };
+        // Risc0 can have 0 public input. In which case we supply an empty Vec<u8>.
+        let pub_input = verification_data.pub_input.as_deref().unwrap_or_default();
+        verify_risc_zero_proof(&verification_data.proof, &image_id, pub_input)
+    }
+}
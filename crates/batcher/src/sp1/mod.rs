@@ -1,3 +1,7 @@
+use crate::zk_utils::{AuxDataCommitment, ProofVerifier};
+use aligned_sdk::common::types::{
+    ProofInvalidReason, ProvingSystemId, VerificationData, VerificationDataField,
+};
 use log::{debug, error, warn};
 use sp1_sdk::{EnvProver, ProverClient, SP1ProofWithPublicValues};
 use std::sync::OnceLock;
@@ -30,3 +34,48 @@ pub fn verify_sp1_proof(proof: &[u8], public_inputs: &[u8], elf: &[u8]) -> bool
 
     false
 }
+
+/// Checks that the proof deserializes and commits to the given public inputs.
+pub fn validate_sp1_proof(proof: &[u8], public_inputs: &[u8]) -> Result<(), ProofInvalidReason> {
+    let proof = bincode::deserialize::<SP1ProofWithPublicValues>(proof)
+        .map_err(|_| ProofInvalidReason::UndeserializableProof(ProvingSystemId::SP1))?;
+    if proof.public_values.as_slice() != public_inputs {
+        return Err(ProofInvalidReason::PublicInputMismatch);
+    }
+    Ok(())
+}
+
+pub(crate) struct Sp1Verifier;
+
+impl ProofVerifier for Sp1Verifier {
+    fn required_fields(&self) -> &'static [VerificationDataField] {
+        &[
+            VerificationDataField::Proof,
+            VerificationDataField::VmProgramCode,
+        ]
+    }
+
+    fn aux_data_commitment(&self) -> AuxDataCommitment {
+        AuxDataCommitment::VmProgramCode
+    }
+
+    fn validate(&self, verification_data: &VerificationData) -> Result<(), ProofInvalidReason> {
+        crate::zk_utils::validate_fields(
+            verification_data,
+            self.required_fields(),
+            self.aux_data_commitment(),
+        )?;
+        // SP1 can have 0 public inputs, in which case the public values are empty.
+        let pub_input = verification_data.pub_input.as_deref().unwrap_or_default();
+        validate_sp1_proof(&verification_data.proof, pub_input)
+    }
+
+    fn verify(&self, verification_data: &VerificationData) -> bool {
+        let Some(elf) = &verification_data.vm_program_code else {
+            warn!("Trying to verify SP1 proof but ELF was not provided. Returning invalid");
+            return false;
+        };
+        let pub_input = verification_data.pub_input.as_deref().unwrap_or_default();
+        verify_sp1_proof(&verification_data.proof, pub_input, elf)
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use log::{error, info, warn};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+        ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+
+use crate::types::errors::BatcherError;
+
+/// Stream of an accepted client connection.
+/// When TLS is enabled, the TLS session is terminated by the batcher itself,
+/// otherwise the plain TCP stream is used, relying on an external proxy for TLS.
+pub enum BatcherStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for BatcherStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BatcherStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            BatcherStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for BatcherStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            BatcherStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            BatcherStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BatcherStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            BatcherStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            BatcherStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            BatcherStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Serves the certificate and key loaded from `cert_path` and `key_path`.
+/// The files can be reloaded at any time with [`CertificateResolver::reload_if_changed`],
+/// new handshakes will use the new certificate while established connections are not affected.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+    last_modified: RwLock<Option<SystemTime>>,
+}
+
+impl CertificateResolver {
+    pub fn new(cert_path: &str, key_path: &str) -> Result<Self, BatcherError> {
+        let cert_path = PathBuf::from(cert_path);
+        let key_path = PathBuf::from(key_path);
+        let certified_key = load_certified_key(&cert_path, &key_path)?;
+        let last_modified = last_modified(&cert_path, &key_path);
+
+        Ok(Self {
+            cert_path,
+            key_path,
+            certified_key: RwLock::new(Arc::new(certified_key)),
+            last_modified: RwLock::new(last_modified),
+        })
+    }
+
+    /// Reloads the certificate and key if any of the files was modified since the last load.
+    /// Returns true if the certificate was reloaded.
+    /// If the new files can't be loaded, the previous certificate keeps being served.
+    pub fn reload_if_changed(&self) -> Result<bool, BatcherError> {
+        let modified = last_modified(&self.cert_path, &self.key_path);
+        {
+            let last_modified = self
+                .last_modified
+                .read()
+                .map_err(|e| BatcherError::TlsError(e.to_string()))?;
+            if modified.is_none() || modified == *last_modified {
+                return Ok(false);
+            }
+        }
+
+        let certified_key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self
+            .certified_key
+            .write()
+            .map_err(|e| BatcherError::TlsError(e.to_string()))? = Arc::new(certified_key);
+        *self
+            .last_modified
+            .write()
+            .map_err(|e| BatcherError::TlsError(e.to_string()))? = modified;
+
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.certified_key
+            .read()
+            .inspect_err(|e| error!("Could not read TLS certificate: {}", e))
+            .ok()
+            .map(|certified_key| certified_key.clone())
+    }
+}
+
+/// Builds the acceptor used to terminate TLS connections, serving the certificate of `resolver`.
+pub fn build_tls_acceptor(resolver: Arc<CertificateResolver>) -> Result<TlsAcceptor, BatcherError> {
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| BatcherError::TlsError(e.to_string()))?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Spawns a task that checks every `reload_interval` if the certificate files changed and reloads them.
+pub fn spawn_certificate_reloader(resolver: Arc<CertificateResolver>, reload_interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(reload_interval);
+        loop {
+            interval.tick().await;
+            match resolver.reload_if_changed() {
+                Ok(true) => info!("TLS certificate reloaded"),
+                Ok(false) => (),
+                Err(e) => warn!(
+                    "Failed to reload TLS certificate, keeping the previous one: {:?}",
+                    e
+                ),
+            }
+        }
+    });
+}
+
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, BatcherError> {
+    let cert_file = File::open(cert_path).map_err(|e| {
+        BatcherError::TlsError(format!(
+            "Failed to open certificate file {}: {}",
+            cert_path.display(),
+            e
+        ))
+    })?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| BatcherError::TlsError(format!("Failed to parse certificate: {}", e)))?;
+    if certs.is_empty() {
+        return Err(BatcherError::TlsError(format!(
+            "No certificate found in {}",
+            cert_path.display()
+        )));
+    }
+
+    let key_file = File::open(key_path).map_err(|e| {
+        BatcherError::TlsError(format!(
+            "Failed to open private key file {}: {}",
+            key_path.display(),
+            e
+        ))
+    })?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))
+        .map_err(|e| BatcherError::TlsError(format!("Failed to parse private key: {}", e)))?
+        .ok_or_else(|| {
+            BatcherError::TlsError(format!("No private key found in {}", key_path.display()))
+        })?;
+    let signing_key = ring::sign::any_supported_type(&key)
+        .map_err(|e| BatcherError::TlsError(format!("Unsupported private key: {}", e)))?;
+
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+fn last_modified(cert_path: &Path, key_path: &Path) -> Option<SystemTime> {
+    let cert_modified = std::fs::metadata(cert_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    let key_modified = std::fs::metadata(key_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    Some(cert_modified.max(key_modified))
+}
@@ -1,11 +1,15 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::{Duration, Instant},
+};
 
 use super::{
-    batch_queue::{BatchQueue, BatchQueueEntry},
+    batch_queue::{BatchQueue, BatchQueueEntry, BatchQueueEntryPriority},
+    errors::BatcherError,
     user_state::UserState,
 };
 use ethers::types::{Address, U256};
-use log::debug;
+use log::{debug, error};
 
 pub(crate) struct BatchState {
     pub(crate) batch_queue: BatchQueue,
@@ -63,11 +67,6 @@ impl BatchState {
         Some(user_state.total_fees_in_queue)
     }
 
-    pub(crate) async fn get_user_proof_count(&self, addr: &Address) -> Option<usize> {
-        let user_state = self.get_user_state(addr)?;
-        Some(user_state.proofs_in_batch)
-    }
-
     pub(crate) fn get_user_min_fee_in_batch(&self, addr: &Address) -> U256 {
         self.batch_queue
             .iter()
@@ -142,6 +141,28 @@ impl BatchState {
         None
     }
 
+    /// Refreshes the activity of the user with address `addr`, so it is not evicted
+    /// while one of its messages is being processed.
+    /// If the address was not found in the user states, returns `false`
+    pub(crate) fn touch_user_state(&mut self, addr: &Address) -> bool {
+        match self.user_states.get_mut(addr) {
+            Some(user_state) => {
+                user_state.last_activity = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts the state of a user that was not cached, with its nonce from `BatcherPaymentService`.
+    /// If the state was inserted while the nonce was fetched, it is kept and its activity refreshed.
+    pub(crate) fn load_user_state(&mut self, addr: Address, ethereum_user_nonce: U256) {
+        self.user_states
+            .entry(addr)
+            .and_modify(|user_state| user_state.last_activity = Instant::now())
+            .or_insert_with(|| UserState::new(ethereum_user_nonce));
+    }
+
     /// Updates the user with address `addr` with the provided values of
     /// `new_nonce`, `new_max_fee_limit`, `new_proof_count` and `new_total_fees_in_queue`
     /// If state is updated successfully, returns the updated values inside a `Some()`
@@ -161,6 +182,10 @@ impl BatchState {
         let updated_total_fees_in_queue =
             self.update_user_total_fees_in_queue(addr, new_total_fees_in_queue);
 
+        if let Entry::Occupied(mut user_state) = self.user_states.entry(*addr) {
+            user_state.get_mut().last_activity = Instant::now();
+        }
+
         if updated_nonce.is_some()
             && updated_max_fee_limit.is_some()
             && updated_proof_count.is_some()
@@ -252,7 +277,287 @@ impl BatchState {
         }
     }
 
+    /// Pushes `entry` to the batch queue and updates the state of its sender accordingly.
+    /// The sender must already be present in the user states.
+    pub(crate) fn add_entry(&mut self, entry: BatchQueueEntry) -> Result<(), BatcherError> {
+        let addr = entry.sender;
+        let max_fee = entry.nonced_verification_data.max_fee;
+        let nonce = entry.nonced_verification_data.nonce;
+        self.batch_queue
+            .push(entry, BatchQueueEntryPriority::new(max_fee, nonce));
+
+        let Some(user_state) = self.get_user_state(&addr) else {
+            return Err(BatcherError::AddressNotFoundInUserStates(addr));
+        };
+        let proof_count = user_state.proofs_in_batch + 1;
+        let total_fees_in_queue = user_state.total_fees_in_queue + max_fee;
+
+        self.update_user_state(
+            &addr,
+            nonce + U256::one(),
+            max_fee,
+            proof_count,
+            total_fees_in_queue,
+        )
+        .ok_or(BatcherError::AddressNotFoundInUserStates(addr))?;
+        Ok(())
+    }
+
+    /// Removes the entries of a batch that was confirmed onchain from the queue
+    /// and recalculates the state of the users from the proofs left in it.
+    pub(crate) fn remove_finalized_entries(
+        &mut self,
+        finalized_batch: &[BatchQueueEntry],
+    ) -> Result<(), BatcherError> {
+        finalized_batch.iter().for_each(|entry| {
+            if self.batch_queue.remove(entry).is_none() {
+                // If this happens, we have a bug in our code
+                error!("Some proofs were not found in the queue. This should not happen.");
+            }
+        });
+
+        // now we calculate the new user_states
+        let new_user_states = // proofs, max_fee_limit, total_fees_in_queue
+            self.calculate_new_user_states_data();
+
+        let user_addresses: Vec<Address> = self.user_states.keys().cloned().collect();
+        let default_value = (0, U256::MAX, U256::zero());
+        for addr in user_addresses.iter() {
+            let (proof_count, max_fee_limit, total_fees_in_queue) =
+                new_user_states.get(addr).unwrap_or(&default_value);
+
+            // FIXME: The case where a the update functions return `None` can only happen when the user was not found
+            // in the `user_states` map should not really happen here, but doing this check so that we don't unwrap.
+            // Once https://github.com/yetanotherco/aligned_layer/issues/1046 is done we could return a more
+            // informative error.
+
+            // Now we update the user states related to the batch (proof count in batch and min fee in batch)
+            self.update_user_proof_count(addr, *proof_count).ok_or(
+                BatcherError::QueueRemoveError("Could not update_user_proof_count".into()),
+            )?;
+            self.update_user_max_fee_limit(addr, *max_fee_limit).ok_or(
+                BatcherError::QueueRemoveError("Could not update_user_max_fee_limit".into()),
+            )?;
+            self.update_user_total_fees_in_queue(addr, *total_fees_in_queue)
+                .ok_or(BatcherError::QueueRemoveError(
+                    "Could not update_user_total_fees_in_queue".into(),
+                ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the state of users that have no proofs in the queue and have not submitted
+    /// anything for at least `idle_timeout`. The state of `retained_addr` (if any) is never evicted.
+    ///
+    /// This is safe since proofs are only removed from the queue after the batch was confirmed onchain,
+    /// so the nonce of a user without queued proofs matches the one in `BatcherPaymentService`,
+    /// which is fetched again when the user comes back.
+    ///
+    /// Returns the number of evicted users.
+    pub(crate) fn evict_idle_users(
+        &mut self,
+        idle_timeout: Duration,
+        retained_addr: Option<Address>,
+    ) -> usize {
+        let users_before = self.user_states.len();
+        self.user_states.retain(|addr, user_state| {
+            Some(*addr) == retained_addr
+                || user_state.proofs_in_batch > 0
+                || user_state.last_activity.elapsed() < idle_timeout
+        });
+        users_before - self.user_states.len()
+    }
+
     pub(crate) fn is_queue_full(&self) -> bool {
         self.batch_queue.len() >= self.max_size
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::batch_queue::try_build_batch;
+    use aligned_sdk::common::constants::DEFAULT_CONSTANT_GAS_COST;
+    use aligned_sdk::common::types::{
+        NoncedVerificationData, ProvingSystemId, VerificationData, VerificationDataCommitment,
+    };
+    use ethers::types::Signature;
+
+    fn queue_entry(sender: Address, nonce: U256) -> BatchQueueEntry {
+        let verification_data = VerificationData {
+            proving_system: ProvingSystemId::Risc0,
+            proof: vec![42_u8; 10],
+            pub_input: Some(vec![42_u8; 10]),
+            verification_key: None,
+            vm_program_code: Some(vec![42_u8; 10]),
+            proof_generator_addr: Address::random(),
+        };
+        let max_fee = U256::from(1_000_000_000_000_000u128);
+        let nonced_verification_data = NoncedVerificationData::new(
+            verification_data,
+            nonce,
+            max_fee,
+            U256::from(42),
+            Address::random(),
+        );
+        let commitment: VerificationDataCommitment = nonced_verification_data.clone().into();
+        let signature = Signature {
+            r: U256::from(1),
+            s: U256::from(2),
+            v: 3,
+        };
+        BatchQueueEntry::new_for_testing(nonced_verification_data, commitment, signature, sender)
+    }
+
+    fn idle_user_state(nonce: U256, proofs_in_batch: usize, idle_for: Duration) -> UserState {
+        let mut user_state = UserState::new(nonce);
+        user_state.proofs_in_batch = proofs_in_batch;
+        user_state.last_activity = Instant::now() - idle_for;
+        user_state
+    }
+
+    #[test]
+    fn evict_idle_users_only_removes_idle_users_without_queued_proofs() {
+        let idle_addr = Address::random();
+        let queued_addr = Address::random();
+        let active_addr = Address::random();
+        let retained_addr = Address::random();
+        let idle_timeout = Duration::from_secs(60);
+        let long_ago = Duration::from_secs(120);
+
+        let mut user_states = HashMap::new();
+        user_states.insert(idle_addr, idle_user_state(U256::from(3), 0, long_ago));
+        user_states.insert(queued_addr, idle_user_state(U256::from(7), 2, long_ago));
+        user_states.insert(
+            active_addr,
+            idle_user_state(U256::from(1), 0, Duration::ZERO),
+        );
+        user_states.insert(retained_addr, idle_user_state(U256::from(9), 0, long_ago));
+        let mut batch_state = BatchState::new_with_user_states(user_states, 100);
+
+        let evicted = batch_state.evict_idle_users(idle_timeout, Some(retained_addr));
+
+        assert_eq!(evicted, 1);
+        assert!(batch_state.get_user_state(&idle_addr).is_none());
+        assert_eq!(
+            batch_state.get_user_state(&queued_addr).unwrap().nonce,
+            U256::from(7)
+        );
+        assert_eq!(
+            batch_state.get_user_state(&active_addr).unwrap().nonce,
+            U256::from(1)
+        );
+        assert_eq!(
+            batch_state.get_user_state(&retained_addr).unwrap().nonce,
+            U256::from(9)
+        );
+    }
+
+    #[test]
+    fn update_user_state_refreshes_activity() {
+        let addr = Address::random();
+        let idle_timeout = Duration::from_secs(60);
+
+        let mut user_states = HashMap::new();
+        user_states.insert(
+            addr,
+            idle_user_state(U256::zero(), 0, Duration::from_secs(120)),
+        );
+        let mut batch_state = BatchState::new_with_user_states(user_states, 100);
+
+        batch_state
+            .update_user_state(&addr, U256::one(), U256::from(10), 1, U256::from(10))
+            .unwrap();
+        // Proofs were drained from the queue, but the user submitted recently
+        batch_state.update_user_proof_count(&addr, 0).unwrap();
+
+        assert_eq!(batch_state.evict_idle_users(idle_timeout, None), 0);
+        assert!(batch_state.get_user_state(&addr).is_some());
+    }
+
+    #[test]
+    fn touch_user_state_refreshes_activity() {
+        let addr = Address::random();
+        let idle_timeout = Duration::from_secs(60);
+
+        let mut user_states = HashMap::new();
+        user_states.insert(
+            addr,
+            idle_user_state(U256::from(3), 0, Duration::from_secs(120)),
+        );
+        let mut batch_state = BatchState::new_with_user_states(user_states, 100);
+
+        // Looking the user up when its message arrives keeps it from being evicted
+        assert!(batch_state.touch_user_state(&addr));
+        assert!(!batch_state.touch_user_state(&Address::random()));
+
+        assert_eq!(batch_state.evict_idle_users(idle_timeout, None), 0);
+        assert_eq!(
+            batch_state.get_user_state(&addr).unwrap().nonce,
+            U256::from(3)
+        );
+    }
+
+    #[test]
+    fn evicted_user_is_reloaded_with_its_onchain_nonce() {
+        let addr = Address::random();
+        let mut batch_state = BatchState::new(100);
+
+        // New users are loaded with their nonce from Ethereum
+        assert!(!batch_state.touch_user_state(&addr));
+        batch_state.load_user_state(addr, U256::from(5));
+        batch_state
+            .add_entry(queue_entry(addr, U256::from(5)))
+            .unwrap();
+        batch_state
+            .add_entry(queue_entry(addr, U256::from(6)))
+            .unwrap();
+
+        // Users with queued proofs are never evicted, even with a zero timeout
+        assert_eq!(batch_state.evict_idle_users(Duration::ZERO, None), 0);
+
+        let finalized_batch = try_build_batch(
+            batch_state.batch_queue.clone(),
+            U256::one(),
+            usize::MAX,
+            100,
+            DEFAULT_CONSTANT_GAS_COST,
+        )
+        .unwrap();
+        assert_eq!(finalized_batch.len(), 2);
+        batch_state
+            .remove_finalized_entries(&finalized_batch)
+            .unwrap();
+        assert_eq!(batch_state.evict_idle_users(Duration::ZERO, None), 1);
+
+        // The user comes back after being evicted and is loaded again with the nonce
+        // the contract holds once the batch was confirmed
+        assert!(!batch_state.touch_user_state(&addr));
+        batch_state.load_user_state(addr, U256::from(7));
+        batch_state
+            .add_entry(queue_entry(addr, U256::from(7)))
+            .unwrap();
+        let user_state = batch_state.get_user_state(&addr).unwrap();
+        assert_eq!(user_state.nonce, U256::from(8));
+        assert_eq!(user_state.proofs_in_batch, 1);
+    }
+
+    #[test]
+    fn load_user_state_keeps_a_state_inserted_concurrently() {
+        let addr = Address::random();
+        let mut batch_state = BatchState::new(100);
+
+        // Two messages of a new user fetch its nonce while the batch state is unlocked,
+        // and the first one adds its proof before the second one loads the user
+        batch_state.load_user_state(addr, U256::from(5));
+        batch_state
+            .add_entry(queue_entry(addr, U256::from(5)))
+            .unwrap();
+        batch_state.load_user_state(addr, U256::from(5));
+
+        let user_state = batch_state.get_user_state(&addr).unwrap();
+        assert_eq!(user_state.nonce, U256::from(6));
+        assert_eq!(user_state.proofs_in_batch, 1);
+    }
+}
@@ -58,6 +58,7 @@ pub enum BatcherError {
     WsSinkEmpty,
     AddressNotFoundInUserStates(Address),
     QueueRemoveError(String),
+    TlsError(String),
 }
 
 impl From<tungstenite::Error> for BatcherError {
@@ -139,6 +140,9 @@ impl fmt::Debug for BatcherError {
             BatcherError::QueueRemoveError(e) => {
                 write!(f, "Error while removing entry from queue: {}", e)
             }
+            BatcherError::TlsError(e) => {
+                write!(f, "TLS error: {}", e)
+            }
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use ethers::types::U256;
 
 pub(crate) struct UserState {
@@ -5,6 +7,9 @@ pub(crate) struct UserState {
     pub last_max_fee_limit: U256,
     pub total_fees_in_queue: U256,
     pub proofs_in_batch: usize,
+    /// Last time the user state was created, looked up or updated by a submission.
+    /// Used to evict idle users from the cache.
+    pub last_activity: Instant,
 }
 
 impl UserState {
@@ -14,6 +19,7 @@ impl UserState {
             last_max_fee_limit: U256::max_value(),
             total_fees_in_queue: U256::zero(),
             proofs_in_batch: 0,
+            last_activity: Instant::now(),
         }
     }
 }
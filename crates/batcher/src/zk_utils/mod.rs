@@ -1,90 +1,32 @@
-use crate::circom::verifier::verify_circom;
-use crate::gnark::verify_gnark;
-use crate::risc_zero::verify_risc_zero_proof;
-use crate::sp1::verify_sp1_proof;
-use aligned_sdk::common::types::{ProvingSystemId, VerificationData};
+mod verifier;
+
+use aligned_sdk::common::types::{ProofInvalidReason, ProvingSystemId, VerificationData};
 use ethers::types::U256;
-use log::{debug, warn};
+pub(crate) use verifier::{validate_fields, verifier_registry, AuxDataCommitment, ProofVerifier};
 
-pub(crate) async fn verify(verification_data: &VerificationData) -> bool {
+/// Validates the verification data and verifies the proof with the verifier of its proving system.
+/// Returns the reason why the proof is invalid, if it is.
+pub(crate) async fn verify(verification_data: &VerificationData) -> Result<(), ProofInvalidReason> {
     let verification_data = verification_data.clone();
-    tokio::task::spawn_blocking(move || verify_internal(&verification_data))
-        .await
-        .unwrap_or(false)
-}
-
-fn verify_internal(verification_data: &VerificationData) -> bool {
-    match verification_data.proving_system {
-        ProvingSystemId::SP1 => {
-            let Some(elf) = &verification_data.vm_program_code else {
-                warn!("Trying to verify SP1 proof but ELF was not provided. Returning invalid");
-                return false;
-            };
-            let pub_inputs = &verification_data.pub_input.clone().unwrap_or_default();
-            verify_sp1_proof(
-                verification_data.proof.as_slice(),
-                pub_inputs.as_slice(),
-                elf.as_slice(),
-            )
-        }
-        ProvingSystemId::Risc0 => {
-            let Some(image_id_slice) = &verification_data.vm_program_code else {
-                warn!(
-                    "Trying to verify Risc0 proof but image id was not provided. Returning false"
-                );
-                return false;
-            };
-
-            // Risc0 can have 0 public input. In which case we supply an empty Vec<u8>.
-            let pub_input = verification_data.pub_input.clone().unwrap_or_default();
-
-            let mut image_id = [0u8; 32];
-            image_id.copy_from_slice(image_id_slice.as_slice());
-            verify_risc_zero_proof(verification_data.proof.as_slice(), &image_id, &pub_input)
+    tokio::task::spawn_blocking(move || {
+        let verifier = get_verifier(verification_data.proving_system)?;
+        verifier.validate(&verification_data)?;
+        if verifier.verify(&verification_data) {
+            Ok(())
+        } else {
+            Err(ProofInvalidReason::RejectedProof)
         }
-        ProvingSystemId::GnarkPlonkBls12_381
-        | ProvingSystemId::GnarkPlonkBn254
-        | ProvingSystemId::GnarkGroth16Bn254 => {
-            let Some(vk) = verification_data.verification_key.as_ref() else {
-                warn!("Gnark verification key missing");
-                return false;
-            };
-
-            let Some(pub_input) = verification_data.pub_input.as_ref() else {
-                warn!("Gnark public input missing");
-                return false;
-            };
-
-            let is_valid = verify_gnark(
-                &verification_data.proving_system,
-                &verification_data.proof,
-                pub_input,
-                vk,
-            );
-
-            debug!("Gnark proof is valid: {}", is_valid);
-            is_valid
-        }
-        ProvingSystemId::CircomGroth16Bn256 => {
-            let Some(pub_input) = verification_data.pub_input.as_ref() else {
-                warn!("Circom Groth16 public input missing");
-                return false;
-            };
-            let Some(vk) = verification_data.verification_key.as_ref() else {
-                warn!("Circom Groth16 verification key missing");
-                return false;
-            };
+    })
+    .await
+    .unwrap_or(Err(ProofInvalidReason::RejectedProof))
+}
 
-            let is_valid = verify_circom(
-                &verification_data.proving_system,
-                &verification_data.proof,
-                pub_input,
-                vk,
-            );
-            debug!("Circom Groth16 proof is valid: {}", is_valid);
-            is_valid
-        }
-    }
+fn get_verifier(
+    proving_system: ProvingSystemId,
+) -> Result<&'static dyn ProofVerifier, ProofInvalidReason> {
+    verifier_registry()
+        .get(proving_system)
+        .ok_or(ProofInvalidReason::VerifierNotSupported)
 }
 
 pub(crate) fn is_verifier_disabled(
@@ -96,31 +38,41 @@ pub(crate) fn is_verifier_disabled(
 
 #[cfg(test)]
 mod test {
-    use super::is_verifier_disabled;
-    use aligned_sdk::common::types::{ProvingSystemId, VerificationData};
+    use super::{get_verifier, is_verifier_disabled, verifier_registry};
+    use aligned_sdk::common::types::{
+        ProofInvalidReason, ProvingSystemId, VerificationData, VerificationDataField,
+    };
     use ethers::types::Address;
 
-    fn get_all_verifiers() -> Vec<ProvingSystemId> {
-        let verifiers = vec![
-            ProvingSystemId::GnarkPlonkBls12_381,
-            ProvingSystemId::GnarkPlonkBn254,
-            ProvingSystemId::GnarkGroth16Bn254,
-            ProvingSystemId::SP1,
-            ProvingSystemId::Risc0,
-            ProvingSystemId::CircomGroth16Bn256,
-        ];
-        // Just to make sure we are not missing any verifier. The compilation will fail if we do and it forces us to add it to the vec above.
-        for verifier in verifiers.iter() {
-            match verifier {
-                ProvingSystemId::SP1 => (),
-                ProvingSystemId::Risc0 => (),
-                ProvingSystemId::GnarkPlonkBls12_381 => (),
-                ProvingSystemId::GnarkPlonkBn254 => (),
-                ProvingSystemId::GnarkGroth16Bn254 => (),
-                ProvingSystemId::CircomGroth16Bn256 => (),
-            }
+    const SP1_PROOF_PATH: &str = "../../scripts/test_files/sp1/sp1_fibonacci_5_0_0.proof";
+    const SP1_PUB_INPUT_PATH: &str = "../../scripts/test_files/sp1/sp1_fibonacci_5_0_0.pub";
+    const SP1_ELF_PATH: &str = "../../scripts/test_files/sp1/sp1_fibonacci_5_0_0.elf";
+    const RISC0_PROOF_PATH: &str =
+        "../../scripts/test_files/risc_zero/fibonacci_proof_generator/risc_zero_fibonacci_2_2_0.proof";
+    const RISC0_IMAGE_ID_PATH: &str =
+        "../../scripts/test_files/risc_zero/fibonacci_proof_generator/fibonacci_id_2_2_0.bin";
+
+    fn validate(verification_data: &VerificationData) -> Result<(), ProofInvalidReason> {
+        get_verifier(verification_data.proving_system)?.validate(verification_data)
+    }
+
+    fn verification_data(proving_system: ProvingSystemId) -> VerificationData {
+        let is_zkvm = matches!(
+            proving_system,
+            ProvingSystemId::SP1 | ProvingSystemId::Risc0
+        );
+        VerificationData {
+            proving_system,
+            vm_program_code: is_zkvm.then(|| vec![1; 32]),
+            pub_input: Some(vec![1]),
+            proof: vec![1, 2, 3],
+            verification_key: (!is_zkvm).then(|| vec![1]),
+            proof_generator_addr: Address::zero(),
         }
-        verifiers
+    }
+
+    fn get_all_verifiers() -> Vec<ProvingSystemId> {
+        ProvingSystemId::ALL.to_vec()
     }
 
     #[test]
@@ -167,8 +119,7 @@ mod test {
 
     #[test]
     fn test_some_verifiers_disabled() {
-        let verifiers = get_all_verifiers();
-        // Disabling only the first verifier
+        // Disabling the GnarkPlonkBls12_381 and CircomGroth16Bn256 verifiers
         let disabled_verifiers = ethers::types::U256::from(0b100001);
         for verifier in get_all_verifiers().iter() {
             let verification_data = VerificationData {
@@ -179,7 +130,10 @@ mod test {
                 verification_key: None,
                 proof_generator_addr: Address::zero(),
             };
-            if verifier == &verifiers[0] || verifier == &verifiers[verifiers.len() - 1] {
+            if matches!(
+                verifier,
+                ProvingSystemId::GnarkPlonkBls12_381 | ProvingSystemId::CircomGroth16Bn256
+            ) {
                 assert!(
                     is_verifier_disabled(disabled_verifiers, verification_data.proving_system),
                     "Verifier {:?} should be disabled",
@@ -194,4 +148,152 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_validate_missing_fields() {
+        let mut sp1_data = verification_data(ProvingSystemId::SP1);
+        sp1_data.vm_program_code = None;
+        assert_eq!(
+            validate(&sp1_data),
+            Err(ProofInvalidReason::MissingField(
+                ProvingSystemId::SP1,
+                VerificationDataField::VmProgramCode
+            ))
+        );
+
+        let mut gnark_data = verification_data(ProvingSystemId::GnarkPlonkBn254);
+        gnark_data.verification_key = Some(vec![]);
+        assert_eq!(
+            validate(&gnark_data),
+            Err(ProofInvalidReason::MissingField(
+                ProvingSystemId::GnarkPlonkBn254,
+                VerificationDataField::VerificationKey
+            ))
+        );
+
+        let mut circom_data = verification_data(ProvingSystemId::CircomGroth16Bn256);
+        circom_data.pub_input = None;
+        assert_eq!(
+            validate(&circom_data),
+            Err(ProofInvalidReason::MissingField(
+                ProvingSystemId::CircomGroth16Bn256,
+                VerificationDataField::PubInput
+            ))
+        );
+
+        let mut risc0_data = verification_data(ProvingSystemId::Risc0);
+        risc0_data.proof = vec![];
+        assert_eq!(
+            validate(&risc0_data),
+            Err(ProofInvalidReason::MissingField(
+                ProvingSystemId::Risc0,
+                VerificationDataField::Proof
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_risc0_image_id() {
+        let mut risc0_data = verification_data(ProvingSystemId::Risc0);
+        risc0_data.vm_program_code = Some(vec![1; 31]);
+        assert_eq!(
+            validate(&risc0_data),
+            Err(ProofInvalidReason::MalformedImageId(31))
+        );
+
+        risc0_data.vm_program_code = Some(vec![1; 32]);
+        assert_eq!(
+            validate(&risc0_data),
+            Err(ProofInvalidReason::UndeserializableProof(
+                ProvingSystemId::Risc0
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_risc0_receipt_kind() {
+        use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+        let mut risc0_data = verification_data(ProvingSystemId::Risc0);
+        risc0_data.proof = std::fs::read(RISC0_PROOF_PATH).unwrap();
+        risc0_data.vm_program_code = Some(std::fs::read(RISC0_IMAGE_ID_PATH).unwrap());
+        assert_eq!(validate(&risc0_data), Ok(()));
+
+        let fake_receipt = InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(
+            [1u8; 32],
+            risc0_data.pub_input.clone().unwrap(),
+        )));
+        risc0_data.proof = bincode::serialize(&fake_receipt).unwrap();
+        assert_eq!(
+            validate(&risc0_data),
+            Err(ProofInvalidReason::UnsupportedReceiptKind(
+                "fake".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_sp1_proof() {
+        let mut sp1_data = verification_data(ProvingSystemId::SP1);
+        assert_eq!(
+            validate(&sp1_data),
+            Err(ProofInvalidReason::UndeserializableProof(
+                ProvingSystemId::SP1
+            ))
+        );
+
+        sp1_data.proof = std::fs::read(SP1_PROOF_PATH).unwrap();
+        sp1_data.vm_program_code = Some(std::fs::read(SP1_ELF_PATH).unwrap());
+        assert_eq!(
+            validate(&sp1_data),
+            Err(ProofInvalidReason::PublicInputMismatch)
+        );
+
+        sp1_data.pub_input = Some(std::fs::read(SP1_PUB_INPUT_PATH).unwrap());
+        assert_eq!(validate(&sp1_data), Ok(()));
+    }
+
+    #[test]
+    fn test_all_verifiers_registered() {
+        for verifier in get_all_verifiers() {
+            assert!(
+                verifier_registry().get(verifier).is_some(),
+                "Verifier {:?} is not registered",
+                verifier
+            );
+        }
+    }
+
+    #[cfg(feature = "noir")]
+    #[test]
+    fn test_validate_noir_proof() {
+        let mut noir_data = verification_data(ProvingSystemId::NoirUltraHonk);
+        // Circuits without public inputs are accepted
+        noir_data.pub_input = None;
+        assert_eq!(validate(&noir_data), Ok(()));
+
+        noir_data.verification_key = None;
+        assert_eq!(
+            validate(&noir_data),
+            Err(ProofInvalidReason::MissingField(
+                ProvingSystemId::NoirUltraHonk,
+                VerificationDataField::VerificationKey
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_program_code_for_key_committed_systems() {
+        let mut gnark_data = verification_data(ProvingSystemId::GnarkGroth16Bn254);
+        assert_eq!(validate(&gnark_data), Ok(()));
+
+        gnark_data.vm_program_code = Some(vec![1]);
+        assert_eq!(
+            validate(&gnark_data),
+            Err(ProofInvalidReason::UnexpectedField(
+                ProvingSystemId::GnarkGroth16Bn254,
+                VerificationDataField::VmProgramCode
+            ))
+        );
+    }
 }
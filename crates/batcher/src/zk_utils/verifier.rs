@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use aligned_sdk::common::types::{
+    ProofInvalidReason, ProvingSystemId, VerificationData, VerificationDataField,
+};
+
+use crate::circom::verifier::CircomVerifier;
+use crate::gnark::GnarkVerifier;
+#[cfg(feature = "noir")]
+use crate::noir::NoirUltraHonkVerifier;
+use crate::risc_zero::Risc0Verifier;
+use crate::sp1::Sp1Verifier;
+
+static VERIFIER_REGISTRY: OnceLock<VerifierRegistry> = OnceLock::new();
+
+/// Data the proving system auxiliary data commitment of a proof is computed from.
+/// See `VerificationDataCommitment` in the SDK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuxDataCommitment {
+    /// The ELF or image id of the program, for zkVMs.
+    VmProgramCode,
+    VerificationKey,
+}
+
+/// Verifier of the proofs of one proving system.
+///
+/// To support a new proving system, add it to `ProvingSystemId::ALL` in the SDK, implement this
+/// trait in its module and register it in [`VerifierRegistry::new`], behind the same feature.
+/// The CLI accepts every proving system of `ProvingSystemId::ALL`.
+pub(crate) trait ProofVerifier: Send + Sync {
+    /// Fields of the verification data that must be present and non-empty.
+    fn required_fields(&self) -> &'static [VerificationDataField];
+
+    /// What the proving system auxiliary data commitment is computed from.
+    fn aux_data_commitment(&self) -> AuxDataCommitment;
+
+    /// Structural checks of the verification data, run before the verifier so malformed
+    /// submissions are rejected with a precise reason instead of a failed verification.
+    fn validate(&self, verification_data: &VerificationData) -> Result<(), ProofInvalidReason> {
+        validate_fields(
+            verification_data,
+            self.required_fields(),
+            self.aux_data_commitment(),
+        )
+    }
+
+    /// Verifies the proof. Only called with verification data that passed [`Self::validate`].
+    fn verify(&self, verification_data: &VerificationData) -> bool;
+}
+
+/// Checks that the required fields are present and that the commitment is computed from the
+/// data the verifier uses.
+pub(crate) fn validate_fields(
+    verification_data: &VerificationData,
+    required_fields: &[VerificationDataField],
+    aux_data_commitment: AuxDataCommitment,
+) -> Result<(), ProofInvalidReason> {
+    let proving_system = verification_data.proving_system;
+    for field in required_fields {
+        let is_present = match field {
+            VerificationDataField::Proof => !verification_data.proof.is_empty(),
+            VerificationDataField::PubInput => is_present(&verification_data.pub_input),
+            VerificationDataField::VerificationKey => {
+                is_present(&verification_data.verification_key)
+            }
+            VerificationDataField::VmProgramCode => is_present(&verification_data.vm_program_code),
+        };
+        if !is_present {
+            return Err(ProofInvalidReason::MissingField(proving_system, *field));
+        }
+    }
+
+    // The commitment uses the program code whenever it is present, so for proving systems
+    // committing to the verification key it would not bind the key that verified the proof.
+    if aux_data_commitment == AuxDataCommitment::VerificationKey
+        && verification_data.vm_program_code.is_some()
+    {
+        return Err(ProofInvalidReason::UnexpectedField(
+            proving_system,
+            VerificationDataField::VmProgramCode,
+        ));
+    }
+    Ok(())
+}
+
+fn is_present(value: &Option<Vec<u8>>) -> bool {
+    value.as_ref().is_some_and(|value| !value.is_empty())
+}
+
+/// Verifiers supported by the batcher, keyed by proving system.
+pub(crate) struct VerifierRegistry {
+    verifiers: HashMap<ProvingSystemId, Box<dyn ProofVerifier>>,
+}
+
+impl VerifierRegistry {
+    pub(crate) fn new() -> Self {
+        let mut registry = Self {
+            verifiers: HashMap::new(),
+        };
+        registry.register(ProvingSystemId::SP1, Box::new(Sp1Verifier));
+        registry.register(ProvingSystemId::Risc0, Box::new(Risc0Verifier));
+        for proving_system in [
+            ProvingSystemId::GnarkPlonkBls12_381,
+            ProvingSystemId::GnarkPlonkBn254,
+            ProvingSystemId::GnarkGroth16Bn254,
+        ] {
+            registry.register(proving_system, Box::new(GnarkVerifier::new(proving_system)));
+        }
+        registry.register(
+            ProvingSystemId::CircomGroth16Bn256,
+            Box::new(CircomVerifier),
+        );
+        #[cfg(feature = "noir")]
+        registry.register(
+            ProvingSystemId::NoirUltraHonk,
+            Box::new(NoirUltraHonkVerifier),
+        );
+        registry
+    }
+
+    pub(crate) fn register(
+        &mut self,
+        proving_system: ProvingSystemId,
+        verifier: Box<dyn ProofVerifier>,
+    ) {
+        self.verifiers.insert(proving_system, verifier);
+    }
+
+    pub(crate) fn get(&self, proving_system: ProvingSystemId) -> Option<&dyn ProofVerifier> {
+        self.verifiers
+            .get(&proving_system)
+            .map(|verifier| &**verifier)
+    }
+}
+
+/// Registry with all the verifiers the batcher was built with.
+pub(crate) fn verifier_registry() -> &'static VerifierRegistry {
+    VERIFIER_REGISTRY.get_or_init(VerifierRegistry::new)
+}
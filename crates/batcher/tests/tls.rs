@@ -0,0 +1,176 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use aligned_batcher::tls::{build_tls_acceptor, BatcherStream, CertificateResolver};
+use aligned_sdk::common::types::{
+    ClientMessage, GetNonceResponseMessage, Network, SubmitProofResponseMessage,
+};
+use aligned_sdk::communication::protocol::EXPECTED_PROTOCOL_VERSION;
+use aligned_sdk::communication::serialization::{cbor_deserialize, cbor_serialize};
+use aligned_sdk::verification_layer::get_nonce_from_batcher_with_ca_cert;
+use ethers::types::{Address, U256};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+struct SelfSignedCertificate {
+    cert_pem: String,
+    key_pem: String,
+}
+
+fn self_signed_certificate() -> SelfSignedCertificate {
+    let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+        .expect("Failed to generate self signed certificate");
+    SelfSignedCertificate {
+        cert_pem: certified_key.cert.pem(),
+        key_pem: certified_key.key_pair.serialize_pem(),
+    }
+}
+
+fn write_certificate(dir: &Path, certificate: &SelfSignedCertificate) -> (String, String) {
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    std::fs::write(&cert_path, &certificate.cert_pem).unwrap();
+    std::fs::write(&key_path, &certificate.key_pem).unwrap();
+    (
+        cert_path.to_string_lossy().to_string(),
+        key_path.to_string_lossy().to_string(),
+    )
+}
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "aligned-batcher-tls-{}-{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn tls_connector(certificate: &SelfSignedCertificate) -> TlsConnector {
+    let mut root_store = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut certificate.cert_pem.as_bytes()) {
+        root_store.add(cert.unwrap()).unwrap();
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+#[test]
+fn test_load_certificate_fails_with_missing_files() {
+    let dir = test_dir("missing");
+    let cert_path = dir.join("missing_cert.pem");
+    let key_path = dir.join("missing_key.pem");
+    assert!(
+        CertificateResolver::new(cert_path.to_str().unwrap(), key_path.to_str().unwrap()).is_err()
+    );
+}
+
+#[test]
+fn test_reload_if_changed_keeps_certificate_when_files_did_not_change() {
+    let dir = test_dir("unchanged");
+    let (cert_path, key_path) = write_certificate(&dir, &self_signed_certificate());
+    let resolver = CertificateResolver::new(&cert_path, &key_path).unwrap();
+
+    assert!(!resolver.reload_if_changed().unwrap());
+}
+
+#[tokio::test]
+async fn test_sdk_client_talks_to_batcher_over_tls_with_self_signed_certificate() {
+    let dir = test_dir("ws");
+    let certificate = self_signed_certificate();
+    let (cert_path, key_path) = write_certificate(&dir, &certificate);
+    let resolver = Arc::new(CertificateResolver::new(&cert_path, &key_path).unwrap());
+    let acceptor = build_tls_acceptor(resolver).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let address = Address::repeat_byte(0x11);
+
+    // Server side of the batcher handshake: protocol version first, then the client message
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = BatcherStream::Tls(Box::new(acceptor.accept(stream).await.unwrap()));
+        let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        let protocol_version =
+            SubmitProofResponseMessage::ProtocolVersion(EXPECTED_PROTOCOL_VERSION);
+        ws_stream
+            .send(Message::binary(cbor_serialize(&protocol_version).unwrap()))
+            .await
+            .unwrap();
+
+        let message = ws_stream.next().await.unwrap().unwrap();
+        let client_msg: ClientMessage = cbor_deserialize(&message.into_data()).unwrap();
+        let ClientMessage::GetNonceForAddress(requested_address) = client_msg else {
+            panic!("Unexpected client message: {}", client_msg);
+        };
+        let nonce = GetNonceResponseMessage::Nonce(U256::from(7));
+        ws_stream
+            .send(Message::binary(cbor_serialize(&nonce).unwrap()))
+            .await
+            .unwrap();
+        requested_address
+    });
+
+    let network = Network::Custom(
+        String::new(),
+        String::new(),
+        format!("wss://localhost:{}", addr.port()),
+    );
+    let nonce = get_nonce_from_batcher_with_ca_cert(network, address, Path::new(&cert_path))
+        .await
+        .unwrap();
+
+    assert_eq!(nonce, U256::from(7));
+    assert_eq!(server.await.unwrap(), address);
+}
+
+#[tokio::test]
+async fn test_certificate_is_reloaded_without_restart() {
+    let dir = test_dir("reload");
+    let old_certificate = self_signed_certificate();
+    let (cert_path, key_path) = write_certificate(&dir, &old_certificate);
+    let resolver = Arc::new(CertificateResolver::new(&cert_path, &key_path).unwrap());
+    let acceptor = build_tls_acceptor(resolver.clone()).unwrap();
+
+    // Make sure the modification time of the new files differs from the old ones
+    std::thread::sleep(Duration::from_millis(1100));
+    let new_certificate = self_signed_certificate();
+    write_certificate(&dir, &new_certificate);
+    assert!(resolver.reload_if_changed().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            // Handshakes signed with the old certificate fail on the client side
+            let _ = acceptor.accept(stream).await;
+        }
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let result = tls_connector(&old_certificate)
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await;
+    assert!(result.is_err());
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let result = tls_connector(&new_certificate)
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await;
+    assert!(result.is_ok());
+}
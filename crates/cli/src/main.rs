@@ -21,10 +21,10 @@ use aligned_sdk::verification_layer::get_nonce_from_batcher;
 use aligned_sdk::verification_layer::get_nonce_from_ethereum;
 use aligned_sdk::verification_layer::{deposit_to_aligned, get_balance_in_aligned};
 use aligned_sdk::verification_layer::{get_vk_commitment, save_response, submit_multiple};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
-use clap::ValueEnum;
 use env_logger::Env;
 use ethers::prelude::*;
 use ethers::utils::format_ether;
@@ -95,8 +95,12 @@ pub struct SubmitArgs {
     )]
     eth_rpc_url: String,
 
-    #[arg(name = "Proving system", long = "proving_system")]
-    proving_system_flag: ProvingSystemArg,
+    #[arg(
+        name = "Proving system",
+        long = "proving_system",
+        value_parser = proving_system_parser()
+    )]
+    proving_system_flag: ProvingSystemId,
 
     #[arg(name = "Proof file path", long = "proof")]
     proof_file_name: PathBuf,
@@ -229,8 +233,12 @@ pub struct VerifyProofOnchainArgs {
 pub struct GetVkCommitmentArgs {
     #[arg(name = "Verification key file path", long = "verification_key_file")]
     verification_key_file: PathBuf,
-    #[arg(name = "Proving system", long = "proving_system")]
-    proving_system: ProvingSystemArg,
+    #[arg(
+        name = "Proving system",
+        long = "proving_system",
+        value_parser = proving_system_parser()
+    )]
+    proving_system: ProvingSystemId,
     #[arg(name = "Output file", long = "output")]
     output_file: Option<PathBuf>,
 }
@@ -323,8 +331,17 @@ pub struct VerifyProofInAggModeArgs {
         long = "from-block"
     )]
     from_block: Option<u64>,
-    #[arg(name = "Proving system", long = "proving_system")]
-    proving_system: ProvingSystemArg,
+    #[arg(
+        name = "Url the aggregator publishes its aggregation manifests under, read when the blobs are expired",
+        long = "aggregation_manifests_url"
+    )]
+    aggregation_manifests_url: Option<String>,
+    #[arg(
+        name = "Proving system",
+        long = "proving_system",
+        value_parser = proving_system_parser()
+    )]
+    proving_system: ProvingSystemId,
     #[arg(name = "Public input file name", long = "public_input")]
     pub_input_file_name: Option<PathBuf>,
     #[arg(
@@ -344,35 +361,13 @@ pub struct PrivateKeyType {
     private_key: Option<String>,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-pub enum ProvingSystemArg {
-    #[clap(name = "GnarkPlonkBls12_381")]
-    GnarkPlonkBls12_381,
-    #[clap(name = "GnarkPlonkBn254")]
-    GnarkPlonkBn254,
-    #[clap(name = "GnarkGroth16Bn254")]
-    GnarkGroth16Bn254,
-    #[clap(name = "SP1")]
-    SP1,
-    #[clap(name = "Risc0")]
-    Risc0,
-    #[clap(name = "CircomGroth16Bn256")]
-    CircomGroth16Bn256,
-}
-
 const ANVIL_PRIVATE_KEY: &str = "2a871d0798f97d79848a013d4936a73bf4cc922c825d33c1cf7073dff6d409c6"; // Anvil address 9
 
-impl From<ProvingSystemArg> for ProvingSystemId {
-    fn from(proving_system: ProvingSystemArg) -> Self {
-        match proving_system {
-            ProvingSystemArg::GnarkPlonkBls12_381 => ProvingSystemId::GnarkPlonkBls12_381,
-            ProvingSystemArg::GnarkPlonkBn254 => ProvingSystemId::GnarkPlonkBn254,
-            ProvingSystemArg::GnarkGroth16Bn254 => ProvingSystemId::GnarkGroth16Bn254,
-            ProvingSystemArg::SP1 => ProvingSystemId::SP1,
-            ProvingSystemArg::Risc0 => ProvingSystemId::Risc0,
-            ProvingSystemArg::CircomGroth16Bn256 => ProvingSystemId::CircomGroth16Bn256,
-        }
-    }
+/// Parses the name of a proving system of `ProvingSystemId::ALL`.
+fn proving_system_parser() -> impl TypedValueParser<Value = ProvingSystemId> {
+    PossibleValuesParser::new(ProvingSystemId::ALL.iter().map(ProvingSystemId::name)).map(|name| {
+        ProvingSystemId::from_str(&name).expect("Possible values are proving system names")
+    })
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -670,9 +665,7 @@ async fn main() -> Result<(), AlignedError> {
         }
         GetVkCommitment(args) => {
             let verification_key_bytes = read_file(args.verification_key_file)?;
-            let proving_system = args.proving_system.into();
-
-            let vk_commitment = get_vk_commitment(&verification_key_bytes, proving_system);
+            let vk_commitment = get_vk_commitment(&verification_key_bytes, args.proving_system);
 
             info!("Commitment: {}", hex::encode(vk_commitment));
             if let Some(output_file) = args.output_file {
@@ -809,9 +802,7 @@ async fn main() -> Result<(), AlignedError> {
             return Ok(());
         }
         AlignedCommands::VerifyProofInAggMode(args) => {
-            let program_id_key = read_file(args.program_id_file)?
-                .try_into()
-                .expect("Invalid hexadecimal encoded vk hash");
+            let program_id_file = read_file(args.program_id_file)?;
 
             let Some(pub_inputs_file_name) = args.pub_input_file_name else {
                 error!("Public input file not provided");
@@ -820,26 +811,56 @@ async fn main() -> Result<(), AlignedError> {
             let public_inputs = read_file(pub_inputs_file_name)?;
 
             let proof_data = match args.proving_system {
-                ProvingSystemArg::SP1 => AggregationModeVerificationData::SP1 {
-                    vk: program_id_key,
+                ProvingSystemId::SP1 => AggregationModeVerificationData::SP1 {
+                    vk: program_id_file
+                        .try_into()
+                        .expect("Invalid hexadecimal encoded vk hash"),
                     public_inputs,
                 },
-                ProvingSystemArg::Risc0 => AggregationModeVerificationData::Risc0 {
-                    image_id: program_id_key,
+                ProvingSystemId::Risc0 => AggregationModeVerificationData::Risc0 {
+                    image_id: program_id_file
+                        .try_into()
+                        .expect("Invalid hexadecimal encoded vk hash"),
                     public_inputs,
                 },
+                // For Groth16 proofs the program id file is the verification key file
+                ProvingSystemId::GnarkGroth16Bn254 => {
+                    match AggregationModeVerificationData::gnark_groth16_bn254(
+                        &program_id_file,
+                        &public_inputs,
+                    ) {
+                        Ok(proof_data) => proof_data,
+                        Err(e) => {
+                            error!("Invalid public input: {:?}", e);
+                            return Ok(());
+                        }
+                    }
+                }
+                ProvingSystemId::CircomGroth16Bn256 => {
+                    match AggregationModeVerificationData::circom_groth16_bn254(
+                        &program_id_file,
+                        &public_inputs,
+                    ) {
+                        Ok(proof_data) => proof_data,
+                        Err(e) => {
+                            error!("Invalid public input: {:?}", e);
+                            return Ok(());
+                        }
+                    }
+                }
                 _ => {
                     error!("Proving system not supported in aggregation mode");
                     return Ok(());
                 }
             };
 
-            let proof_status = match aggregation_layer::check_proof_verification(
+            let proof_status = match aggregation_layer::check_proof_verification_with_manifests(
                 &proof_data,
                 args.network.into(),
                 args.eth_rpc_url,
                 args.beacon_client_url,
                 args.from_block,
+                args.aggregation_manifests_url,
             )
             .await
             {
@@ -872,63 +893,28 @@ async fn main() -> Result<(), AlignedError> {
 }
 
 fn verification_data_from_args(args: &SubmitArgs) -> Result<VerificationData, SubmitError> {
-    let proving_system = args.proving_system_flag.clone().into();
+    let proving_system = args.proving_system_flag;
 
     // Read proof file
     let proof = read_file(args.proof_file_name.clone())?;
 
-    let pub_input: Option<Vec<u8>>;
-    let mut verification_key: Option<Vec<u8>> = None;
-    let mut vm_program_code: Option<Vec<u8>> = None;
-
-    match proving_system {
-        ProvingSystemId::SP1 => {
-            vm_program_code = Some(read_file_option(
-                "--vm_program",
-                args.vm_program_code_file_name.clone(),
-            )?);
-            pub_input = args
-                .pub_input_file_name
-                .clone()
-                .map(read_file)
-                .transpose()?;
-        }
-        ProvingSystemId::Risc0 => {
-            vm_program_code = Some(read_file_option(
-                "--vm_program",
-                args.vm_program_code_file_name.clone(),
-            )?);
-
-            // Risc0 and have zero or none public inputs
-            pub_input = args
-                .pub_input_file_name
-                .clone()
-                .map(read_file)
-                .transpose()?;
-        }
-        ProvingSystemId::GnarkPlonkBls12_381
-        | ProvingSystemId::GnarkPlonkBn254
-        | ProvingSystemId::GnarkGroth16Bn254 => {
-            verification_key = Some(read_file_option(
-                "--vk",
-                args.verification_key_file_name.clone(),
-            )?);
-            pub_input = Some(read_file_option(
-                "--public_input",
-                args.pub_input_file_name.clone(),
-            )?);
-        }
-        ProvingSystemId::CircomGroth16Bn256 => {
-            verification_key = Some(read_file_option(
-                "--vk",
-                args.verification_key_file_name.clone(),
-            )?);
-            pub_input = Some(read_file_option(
-                "--public_input",
-                args.pub_input_file_name.clone(),
-            )?);
-        }
-    }
+    // The files the proving system requires are checked by the batcher, which rejects the proof
+    // with the missing or unexpected field
+    let pub_input = args
+        .pub_input_file_name
+        .clone()
+        .map(read_file)
+        .transpose()?;
+    let verification_key = args
+        .verification_key_file_name
+        .clone()
+        .map(read_file)
+        .transpose()?;
+    let vm_program_code = args
+        .vm_program_code_file_name
+        .clone()
+        .map(read_file)
+        .transpose()?;
 
     let proof_generator_addr = Address::from_str(&args.proof_generator_addr).map_err(|e| {
         SubmitError::InvalidEthereumAddress(format!("Error while parsing address: {}", e))
@@ -967,13 +953,6 @@ fn read_file(file_name: PathBuf) -> Result<Vec<u8>, SubmitError> {
     std::fs::read(&file_name).map_err(|e| SubmitError::IoError(file_name, e))
 }
 
-fn read_file_option(param_name: &str, file_name: Option<PathBuf>) -> Result<Vec<u8>, SubmitError> {
-    let file_name = file_name.ok_or(SubmitError::MissingRequiredParameter(
-        param_name.to_string(),
-    ))?;
-    read_file(file_name)
-}
-
 pub async fn get_user_balance(
     provider: Provider<Http>,
     contract_address: Address,
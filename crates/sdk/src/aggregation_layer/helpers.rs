@@ -1,10 +1,14 @@
-use crate::{beacon::BeaconClient, common::types::Network};
+use crate::{
+    beacon::BeaconClient, common::types::Network,
+    eth::aligned_proof_agg_service::aligned_proof_aggregation_service,
+};
+use aggregation_merkle_tree::MerkleTree;
 use ethers::{
     providers::{Http, Middleware, Provider},
     types::{Filter, Log},
 };
 
-use super::ProofVerificationAggModeError;
+use super::{types::AggregationManifest, ProofVerificationAggModeError};
 
 /// How much to go back from current block if from_block is not provided
 /// 7500 blocks = 25hr
@@ -111,6 +115,66 @@ pub async fn get_blob_data_from_verified_proof_event(
     Ok((merkle_root, proof_commitments))
 }
 
+/// Reads the leaves of the aggregation verified in `log` from the manifest the aggregator published
+/// under `aggregation_manifests_url`, for when its blobs are no longer available.
+///
+/// The manifest is checked against the chain: its leaves must build the merkle root of the event,
+/// and the contract must have that merkle root as verified.
+pub async fn get_leaves_from_aggregation_manifest(
+    network: &Network,
+    eth_rpc_url: String,
+    aggregation_manifests_url: &str,
+    log: &Log,
+) -> Result<([u8; 32], Vec<[u8; 32]>), ProofVerificationAggModeError> {
+    // Event is indexed by merkle root
+    let Some(merkle_root) = log.topics.get(1).map(|topic| topic.0) else {
+        return Err(ProofVerificationAggModeError::EventDecoding);
+    };
+
+    let url = format!(
+        "{}/{}",
+        aggregation_manifests_url.trim_end_matches('/'),
+        AggregationManifest::file_name(&merkle_root)
+    );
+    let manifest: AggregationManifest = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ProofVerificationAggModeError::FetchingAggregationManifest(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| ProofVerificationAggModeError::FetchingAggregationManifest(e.to_string()))?;
+
+    if manifest.merkle_root.0 != merkle_root {
+        return Err(ProofVerificationAggModeError::InvalidAggregationManifest);
+    }
+    let leaves: Vec<[u8; 32]> = manifest.leaves.iter().map(|leaf| leaf.0).collect();
+    let Some(merkle_tree) = MerkleTree::build(&leaves) else {
+        return Err(ProofVerificationAggModeError::MerkleTreeConstruction);
+    };
+    if merkle_tree.root() != merkle_root {
+        return Err(ProofVerificationAggModeError::InvalidAggregationManifest);
+    }
+
+    let eth_rpc_provider = Provider::<Http>::try_from(eth_rpc_url)
+        .map_err(|e| ProofVerificationAggModeError::EthereumProviderError(e.to_string()))?;
+    let contract_provider = aligned_proof_aggregation_service(
+        eth_rpc_provider,
+        network.get_aligned_proof_agg_service_address(),
+    )
+    .await
+    .map_err(|e| ProofVerificationAggModeError::EthereumProviderError(e.to_string()))?;
+    let verified_on_chain = contract_provider
+        .aggregated_proofs(merkle_root)
+        .call()
+        .await
+        .map_err(|e| ProofVerificationAggModeError::EthereumProviderError(e.to_string()))?;
+    if !verified_on_chain {
+        return Err(ProofVerificationAggModeError::InvalidAggregationManifest);
+    }
+
+    Ok((merkle_root, leaves))
+}
+
 fn decoded_blob(blob_data: &[u8]) -> Vec<[u8; 32]> {
     let mut proof_hashes = vec![];
 
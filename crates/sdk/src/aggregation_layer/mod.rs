@@ -3,18 +3,24 @@ mod helpers;
 mod types;
 
 // Makes only the two types on this use public
-pub use types::{AggregationModeVerificationData, ProofVerificationAggModeError};
+pub use types::{
+    AggregationManifest, AggregationModeVerificationData, AggregationModeVerificationDataError,
+    ProofVerificationAggModeError,
+};
 
 use crate::{
     common::types::Network, eth::aligned_proof_agg_service::aligned_proof_aggregation_service,
 };
+use aggregation_merkle_tree::MerkleTree;
 use ethers::{
     providers::{Http, Provider},
     types::Bytes,
 };
-use helpers::{fetch_verified_proofs_events, get_blob_data_from_verified_proof_event};
-use lambdaworks_crypto::merkle_tree::merkle::MerkleTree;
-use types::Hash32;
+use helpers::{
+    fetch_verified_proofs_events, get_blob_data_from_verified_proof_event,
+    get_leaves_from_aggregation_manifest,
+};
+use log::warn;
 
 pub enum ProofStatus {
     Verified {
@@ -33,6 +39,7 @@ pub enum ProofStatus {
 ///   which specific aggregation a proof belongs to. Instead, we check the events from the specified `from_block`.
 /// - The `from_block`  must not be older than 18 days, as blobs expire after that period and will no longer be retrievable.
 /// - If not provided, it  defaults to fetch logs from [`FROM_BLOCKS_AGO_DEFAULT`]
+/// - To check proofs whose blobs expired, see [`check_proof_verification_with_manifests`].
 ///
 /// ### The verification process includes:
 /// 1. Querying the blob versioned hash from the events emitted by the aligned proof aggregation service contract since `from_block`
@@ -51,38 +58,77 @@ pub async fn check_proof_verification(
     beacon_client_url: String,
     from_block: Option<u64>,
 ) -> Result<ProofStatus, ProofVerificationAggModeError> {
-    let logs = fetch_verified_proofs_events(network, eth_rpc_url.clone(), from_block).await?;
+    check_proof_verification_with_manifests(
+        verification_data,
+        network,
+        eth_rpc_url,
+        beacon_client_url,
+        from_block,
+        None,
+    )
+    .await
+}
+
+/// Same as [`check_proof_verification`], but when the blobs of an aggregation can't be retrieved,
+/// e.g. because they expired, and `aggregation_manifests_url` is given, the leaves are read from the
+/// [`AggregationManifest`] the aggregator published under that url instead.
+///
+/// The manifest is only trusted if its leaves build the merkle root the contract marks as verified.
+pub async fn check_proof_verification_with_manifests(
+    verification_data: &AggregationModeVerificationData,
+    network: Network,
+    eth_rpc_url: String,
+    beacon_client_url: String,
+    from_block: Option<u64>,
+    aggregation_manifests_url: Option<String>,
+) -> Result<ProofStatus, ProofVerificationAggModeError> {
+    let logs =
+        fetch_verified_proofs_events(network.clone(), eth_rpc_url.clone(), from_block).await?;
     let proof_commitment = verification_data.commitment();
 
     for log in logs {
-        let (merkle_root, leaves) = get_blob_data_from_verified_proof_event(
+        let blob_data = get_blob_data_from_verified_proof_event(
             eth_rpc_url.clone(),
             beacon_client_url.clone(),
-            log,
+            log.clone(),
         )
-        .await?;
-
-        let leaves: Vec<Hash32> = leaves.iter().map(|leaf| Hash32(*leaf)).collect();
+        .await;
+        let (merkle_root, leaves) = match (blob_data, &aggregation_manifests_url) {
+            (Ok(blob_data), _) => blob_data,
+            (Err(err), Some(aggregation_manifests_url)) => {
+                warn!(
+                    "Could not get the aggregation blobs, falling back to its manifest: {:?}",
+                    err
+                );
+                get_leaves_from_aggregation_manifest(
+                    &network,
+                    eth_rpc_url.clone(),
+                    aggregation_manifests_url,
+                    &log,
+                )
+                .await?
+            }
+            (Err(err), None) => return Err(err),
+        };
 
-        let Some(pos) = leaves.iter().position(|p| p.0 == proof_commitment) else {
+        let Some(pos) = leaves.iter().position(|leaf| *leaf == proof_commitment) else {
             continue;
         };
 
-        let Some(merkle_tree) = MerkleTree::<Hash32>::build(&leaves) else {
+        let Some(merkle_tree) = MerkleTree::build(&leaves) else {
             continue;
         };
 
-        let Some(proof) = merkle_tree.get_proof_by_pos(pos) else {
+        let Some(proof) = merkle_tree.proof(pos) else {
             continue;
         };
 
-        let result = proof.verify::<Hash32>(&merkle_root, pos, &Hash32(proof_commitment));
-        if !result {
+        if !proof.verify(&merkle_root, &proof_commitment) {
             return Ok(ProofStatus::Invalid);
         }
 
         return Ok(ProofStatus::Verified {
-            merkle_path: proof.merkle_path,
+            merkle_path: proof.path,
             merkle_root,
         });
     }
@@ -1,25 +1,154 @@
-use lambdaworks_crypto::merkle_tree::traits::IsMerkleTreeBackend;
+use ethers::types::{H256, U256};
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
 use crate::beacon::BeaconClientError;
 
+/// Modulus of the BN254 scalar field, public inputs of Groth16 proofs must be lower.
+const BN254_SCALAR_FIELD_MODULUS: &str =
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+const FIELD_ELEMENT_SIZE: usize = 32;
+
+/// Prefer the constructors of each proving system to building the variants by hand: the commitment
+/// of data encoded differently than the aggregator does is never found, i.e. [`super::ProofStatus::NotFound`].
 #[derive(Debug)]
 pub enum AggregationModeVerificationData {
+    /// Build it with [`Self::sp1`] or [`Self::sp1_from_elf`], behind the `sp1` feature.
     SP1 {
+        /// `hash_u32` words of the verifying key serialized big endian, i.e. `SP1VerifyingKey::hash_bytes`.
         vk: [u8; 32],
         public_inputs: Vec<u8>,
     },
+    /// Build it with [`Self::risc0`], behind the `risc0` feature.
     Risc0 {
+        /// Image id words serialized little endian, as submitted to the batcher.
         image_id: [u8; 32],
+        /// Journal of the receipt.
+        public_inputs: Vec<u8>,
+    },
+    /// A gnark or Circom Groth16 proof over BN254.
+    /// Build it with [`Self::gnark_groth16_bn254`] or [`Self::circom_groth16_bn254`].
+    Groth16Bn254 {
+        /// Keccak256 of the verification key file submitted to the batcher.
+        vk_hash: [u8; 32],
+        /// Public inputs as 32 bytes big endian field elements.
         public_inputs: Vec<u8>,
     },
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregationModeVerificationDataError {
+    MalformedPublicInputs(String),
+}
+
 impl AggregationModeVerificationData {
+    /// Builds the verification data of a gnark Groth16 BN254 proof from the verification key
+    /// and the public witness, as written with gnark `WriteTo` and submitted to the batcher.
+    pub fn gnark_groth16_bn254(
+        verification_key: &[u8],
+        public_witness: &[u8],
+    ) -> Result<Self, AggregationModeVerificationDataError> {
+        // Public witness: [nb_public u32][nb_secret u32][len u32][elements]
+        let malformed = |reason: &str| {
+            AggregationModeVerificationDataError::MalformedPublicInputs(reason.to_string())
+        };
+        let read_u32 = |offset: usize| {
+            public_witness
+                .get(offset..offset + 4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+                .ok_or_else(|| malformed("witness header is too short"))
+        };
+        let (nb_public, nb_secret, len) = (read_u32(0)?, read_u32(4)?, read_u32(8)?);
+        if nb_secret != 0 || len != nb_public {
+            return Err(malformed("witness is not a public witness"));
+        }
+        let public_inputs = &public_witness[12..];
+        if public_inputs.len() != len as usize * FIELD_ELEMENT_SIZE {
+            return Err(malformed("unexpected number of public inputs"));
+        }
+
+        Ok(Self::Groth16Bn254 {
+            vk_hash: Keccak256::digest(verification_key).into(),
+            public_inputs: public_inputs.to_vec(),
+        })
+    }
+
+    /// Builds the verification data of a Circom Groth16 BN254 proof from the `verification_key.json`
+    /// and `public.json` files generated by snarkjs and submitted to the batcher.
+    pub fn circom_groth16_bn254(
+        verification_key: &[u8],
+        public_signals: &[u8],
+    ) -> Result<Self, AggregationModeVerificationDataError> {
+        let malformed =
+            |reason: String| AggregationModeVerificationDataError::MalformedPublicInputs(reason);
+        let public_signals: Vec<String> =
+            serde_json::from_slice(public_signals).map_err(|e| malformed(e.to_string()))?;
+        let modulus = U256::from_dec_str(BN254_SCALAR_FIELD_MODULUS).unwrap();
+
+        let mut public_inputs = Vec::with_capacity(public_signals.len() * FIELD_ELEMENT_SIZE);
+        for signal in public_signals {
+            let value = U256::from_dec_str(&signal)
+                .map_err(|_| malformed(format!("invalid field element {}", signal)))?;
+            if value >= modulus {
+                return Err(malformed(format!(
+                    "field element {} is not reduced",
+                    signal
+                )));
+            }
+            let mut bytes = [0u8; FIELD_ELEMENT_SIZE];
+            value.to_big_endian(&mut bytes);
+            public_inputs.extend(bytes);
+        }
+
+        Ok(Self::Groth16Bn254 {
+            vk_hash: Keccak256::digest(verification_key).into(),
+            public_inputs,
+        })
+    }
+
+    /// Builds the verification data of an SP1 proof from the verifying key of its program.
+    /// The proof is not verified.
+    #[cfg(feature = "sp1")]
+    pub fn sp1(vk: &sp1_sdk::SP1VerifyingKey, proof: &sp1_sdk::SP1ProofWithPublicValues) -> Self {
+        use sp1_sdk::HashableKey;
+
+        Self::SP1 {
+            vk: vk.hash_bytes(),
+            public_inputs: proof.public_values.to_vec(),
+        }
+    }
+
+    /// Builds the verification data of an SP1 proof from the ELF of its program, as submitted to the batcher.
+    /// Computing the verifying key sets up the program with a CPU prover, which takes a few seconds.
+    #[cfg(feature = "sp1")]
+    pub fn sp1_from_elf(elf: &[u8], proof: &sp1_sdk::SP1ProofWithPublicValues) -> Self {
+        use sp1_sdk::Prover;
+
+        let (_pk, vk) = sp1_sdk::ProverClient::builder().cpu().build().setup(elf);
+        Self::sp1(&vk, proof)
+    }
+
+    /// Builds the verification data of a Risc0 receipt from the image id of its program,
+    /// e.g. the `<METHOD>_ID` generated by `risc0-build`. The receipt is not verified.
+    #[cfg(feature = "risc0")]
+    pub fn risc0(
+        image_id: impl Into<risc0_zkvm::sha::Digest>,
+        receipt: &risc0_zkvm::Receipt,
+    ) -> Self {
+        let mut image_id_bytes = [0u8; 32];
+        image_id_bytes.copy_from_slice(image_id.into().as_bytes());
+
+        Self::Risc0 {
+            image_id: image_id_bytes,
+            public_inputs: receipt.journal.bytes.clone(),
+        }
+    }
+
     pub fn program_id(&self) -> [u8; 32] {
         match self {
             Self::Risc0 { image_id, .. } => *image_id,
             Self::SP1 { vk, .. } => *vk,
+            Self::Groth16Bn254 { vk_hash, .. } => *vk_hash,
         }
     }
 
@@ -27,74 +156,33 @@ impl AggregationModeVerificationData {
         match self {
             Self::Risc0 { public_inputs, .. } => public_inputs,
             Self::SP1 { public_inputs, .. } => public_inputs,
+            Self::Groth16Bn254 { public_inputs, .. } => public_inputs,
         }
     }
 
     pub fn commitment(&self) -> [u8; 32] {
-        match self {
-            AggregationModeVerificationData::SP1 { vk, public_inputs } => {
-                let mut hasher = Keccak256::new();
-                hasher.update(vk);
-                hasher.update(public_inputs);
-                hasher.finalize().into()
-            }
-            AggregationModeVerificationData::Risc0 {
-                image_id,
-                public_inputs,
-            } => {
-                let mut hasher = Keccak256::new();
-                hasher.update(image_id);
-                hasher.update(public_inputs);
-                hasher.finalize().into()
-            }
-        }
+        aggregation_merkle_tree::commitment(&self.program_id(), self.public_inputs())
     }
 }
 
-// We use a newtype wrapper around `[u8; 32]` because Rust's orphan rule
-// prevents implementing a foreign trait (`IsMerkleTreeBackend`) for a foreign type (`[u8; 32]`).
-#[derive(Default, Debug, PartialEq, Eq)]
-pub struct Hash32(pub [u8; 32]);
-
-// Note:
-// We define a version of the backend that takes the leaves as hashed data
-// since the user may not have access to the proofs that he didn't submit
-// The original MerkleTreeBackend is defined in three locations
-// - aggregation_mode/src/aggregators/mod.rs
-// - aggregation_mode/src/aggregators/risc0_aggregator.rs
-// - aggregation_mode/src/aggregators/sp1_aggregator.rs
-// The definition on aggregator/mod.rs supports taking proofs from both Risc0 and SP1
-// Hashes of all implementations should match
-impl IsMerkleTreeBackend for Hash32 {
-    type Data = Hash32;
-    type Node = [u8; 32];
-
-    /// We don't have to hash the data, as the blob already contains the proof commitments (which represent the merkle leaves)
-    fn hash_data(leaf: &Self::Data) -> Self::Node {
-        leaf.0
-    }
+/// Published by the aggregator to its storage after each aggregation, so the leaves can be
+/// retrieved once the blobs holding them are pruned by the beacon nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationManifest {
+    pub merkle_root: H256,
+    /// Versioned hashes of the blobs holding the leaves, in order
+    pub blob_versioned_hashes: Vec<H256>,
+    /// Proof commitments, in the order they were aggregated
+    pub leaves: Vec<H256>,
+    /// Hash of the `verifySP1`/`verifyRisc0` transaction
+    pub tx_hash: H256,
+    pub block_number: u64,
+}
 
-    /// Computes a commutative Keccak256 hash, ensuring H(a, b) == H(b, a).
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#Hashes
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/Hashes.sol#L17-L19
-    ///
-    /// Compliant with OpenZeppelin's `processProofCalldata` function from MerkleProof.sol.
-    ///
-    /// See: https://docs.openzeppelin.com/contracts/5.x/api/utils#MerkleProof
-    ///
-    /// Source: https://github.com/OpenZeppelin/openzeppelin-contracts/blob/master/contracts/utils/cryptography/MerkleProof.sol#L114-L128
-    fn hash_new_parent(child_1: &Self::Node, child_2: &Self::Node) -> Self::Node {
-        let mut hasher = Keccak256::new();
-        if child_1 < child_2 {
-            hasher.update(child_1);
-            hasher.update(child_2);
-        } else {
-            hasher.update(child_2);
-            hasher.update(child_1);
-        }
-        hasher.finalize().into()
+impl AggregationManifest {
+    /// Name of the manifest of the aggregation with `merkle_root` in the storage.
+    pub fn file_name(merkle_root: &[u8; 32]) -> String {
+        format!("0x{}.json", hex::encode(merkle_root))
     }
 }
 
@@ -105,4 +193,140 @@ pub enum ProofVerificationAggModeError {
     BeaconClient(BeaconClientError),
     EventDecoding,
     MerkleTreeConstruction,
+    /// The aggregation manifest could not be downloaded or parsed
+    FetchingAggregationManifest(String),
+    /// The leaves of the aggregation manifest don't build the merkle root verified on chain
+    InvalidAggregationManifest,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GNARK_FILES: &str =
+        "../../scripts/test_files/gnark_groth16_bn254_script/gnark_groth16_0_12_0";
+    const CIRCOM_FILES: &str = "../../scripts/test_files/circom_groth16_bn256_script";
+    #[cfg(feature = "sp1")]
+    const SP1_FILES: &str = "../../scripts/test_files/sp1/sp1_fibonacci_5_0_0";
+    #[cfg(feature = "risc0")]
+    const RISC0_FILES: &str = "../../scripts/test_files/risc_zero/fibonacci_proof_generator";
+
+    #[test]
+    fn gnark_groth16_public_inputs_are_the_witness_elements() {
+        let vk = std::fs::read(format!("{}.vk", GNARK_FILES)).unwrap();
+        let public_witness = std::fs::read(format!("{}.pub", GNARK_FILES)).unwrap();
+
+        let data =
+            AggregationModeVerificationData::gnark_groth16_bn254(&vk, &public_witness).unwrap();
+
+        let mut expected_public_input = [0u8; 32];
+        expected_public_input[31] = 35;
+        assert_eq!(data.public_inputs(), &expected_public_input.to_vec());
+        assert_eq!(data.program_id(), <[u8; 32]>::from(Keccak256::digest(&vk)));
+
+        assert!(
+            AggregationModeVerificationData::gnark_groth16_bn254(&vk, &public_witness[..40])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn circom_groth16_public_inputs_are_big_endian_field_elements() {
+        let vk = std::fs::read(format!("{}/verification_key.json", CIRCOM_FILES)).unwrap();
+
+        let data =
+            AggregationModeVerificationData::circom_groth16_bn254(&vk, br#"["1", "256"]"#).unwrap();
+        let mut expected_public_inputs = vec![0u8; 64];
+        expected_public_inputs[31] = 1;
+        expected_public_inputs[62] = 1;
+        assert_eq!(data.public_inputs(), &expected_public_inputs);
+
+        let unreduced = format!(r#"["{}"]"#, BN254_SCALAR_FIELD_MODULUS);
+        assert!(
+            AggregationModeVerificationData::circom_groth16_bn254(&vk, unreduced.as_bytes())
+                .is_err()
+        );
+    }
+
+    // Expected commitments are `keccak(program_id || public_inputs)` of the test files,
+    // i.e. the leaves the aggregator commits the proofs to.
+    #[cfg(feature = "sp1")]
+    #[test]
+    fn sp1_commitment_matches_the_aggregator_one() {
+        let elf = std::fs::read(format!("{}.elf", SP1_FILES)).unwrap();
+        let proof =
+            sp1_sdk::SP1ProofWithPublicValues::load(format!("{}.proof", SP1_FILES)).unwrap();
+
+        let data = AggregationModeVerificationData::sp1_from_elf(&elf, &proof);
+
+        // `hash_u32` of the verifying key, serialized big endian as the aggregation program does
+        let vk_hash_u32: [u32; 8] = [
+            509346106, 249343173, 977581595, 940916059, 1642173950, 70065127, 1573916952,
+            1965872424,
+        ];
+        let vk: Vec<u8> = vk_hash_u32
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect();
+        assert_eq!(data.program_id().to_vec(), vk);
+        assert_eq!(
+            data.public_inputs(),
+            &std::fs::read(format!("{}.pub", SP1_FILES)).unwrap()
+        );
+        assert_eq!(
+            hex::encode(data.commitment()),
+            "9a400bbb252a69df19ea5415a8f2b46d01e1873fd2a77af4c7c261b3efbea3f1"
+        );
+    }
+
+    #[cfg(feature = "risc0")]
+    #[test]
+    fn risc0_commitment_matches_the_aggregator_one() {
+        let inner_receipt: risc0_zkvm::InnerReceipt = bincode::deserialize(
+            &std::fs::read(format!("{}/risc_zero_fibonacci_2_2_0.proof", RISC0_FILES)).unwrap(),
+        )
+        .unwrap();
+        let journal =
+            std::fs::read(format!("{}/risc_zero_fibonacci_2_2_0.pub", RISC0_FILES)).unwrap();
+        let receipt = risc0_zkvm::Receipt::new(inner_receipt, journal.clone());
+        let image_id: [u32; 8] = [
+            298245868, 47774846, 3428833080, 2138056208, 3934112426, 2419855515, 629312957,
+            2924681760,
+        ];
+
+        let data = AggregationModeVerificationData::risc0(image_id, &receipt);
+
+        assert_eq!(
+            data.program_id().to_vec(),
+            std::fs::read(format!("{}/fibonacci_id_2_2_0.bin", RISC0_FILES)).unwrap()
+        );
+        assert_eq!(data.public_inputs(), &journal);
+        assert_eq!(
+            hex::encode(data.commitment()),
+            "bf5bae83114fa420e6b814508c09e42d0d7d08a1f248503349a4f78586c3b0a0"
+        );
+    }
+
+    #[test]
+    fn aggregation_manifest_is_keyed_and_encoded_by_hex_hashes() {
+        let manifest = AggregationManifest {
+            merkle_root: H256::repeat_byte(0xab),
+            blob_versioned_hashes: vec![H256::repeat_byte(0x01)],
+            leaves: vec![H256::repeat_byte(0x02), H256::repeat_byte(0x03)],
+            tx_hash: H256::repeat_byte(0x04),
+            block_number: 10,
+        };
+        assert_eq!(
+            AggregationManifest::file_name(&manifest.merkle_root.0),
+            format!("0x{}.json", "ab".repeat(32))
+        );
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["merkle_root"], format!("0x{}", "ab".repeat(32)));
+        assert_eq!(json["leaves"][1], format!("0x{}", "03".repeat(32)));
+
+        let decoded: AggregationManifest = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.leaves, manifest.leaves);
+        assert_eq!(decoded.block_number, 10);
+    }
 }
@@ -39,6 +39,20 @@ pub struct BlobData {
     pub kzg_commitment_inclusion_proof: Vec<String>,
 }
 
+impl BlobData {
+    pub fn versioned_hash(&self) -> [u8; 32] {
+        let kzg_commitment_bytes =
+            hex::decode(self.kzg_commitment.replace("0x", "")).expect("A valid commitment");
+
+        let mut hasher = Sha256::new();
+        hasher.update(&kzg_commitment_bytes);
+        let mut versioned_hash: [u8; 32] = hasher.finalize().into();
+        versioned_hash[0] = KZG_VERSIONED_HASH;
+
+        versioned_hash
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)]
 // https://ethereum.github.io/beacon-APIs/#/Beacon/getBlockHeaders
@@ -110,17 +124,9 @@ impl BeaconClient {
     ) -> Result<Option<BlobData>, BeaconClientError> {
         let res = self.get_blobs_from_slot(slot).await?;
 
-        let blob = res.into_iter().find(|blob| {
-            let kzg_commitment_bytes =
-                hex::decode(blob.kzg_commitment.replace("0x", "")).expect("A valid commitment");
-
-            let mut hasher = Sha256::new();
-            hasher.update(&kzg_commitment_bytes);
-            let mut versioned_hash: [u8; 32] = hasher.finalize().into();
-            versioned_hash[0] = KZG_VERSIONED_HASH;
-
-            versioned_hash == blob_versioned_hash
-        });
+        let blob = res
+            .into_iter()
+            .find(|blob| blob.versioned_hash() == blob_versioned_hash);
 
         Ok(blob)
     }
@@ -95,6 +95,7 @@ pub enum SubmitError {
     BatchSubmissionFailed(String),
     AddToBatchError,
     InvalidProofInclusionData,
+    InvalidBatcherSignature(String),
     GetNonceError(String),
     BatchQueueLimitExceededError,
     GenericError(String),
@@ -211,6 +212,9 @@ impl fmt::Display for SubmitError {
             SubmitError::InvalidProofInclusionData => {
                 write!(f, "Batcher responded with invalid batch inclusion data. Can't verify your proof was correctly included in the batch.")
             }
+            SubmitError::InvalidBatcherSignature(e) => {
+                write!(f, "Batcher response is not signed by the batcher: {}", e)
+            }
             SubmitError::BatchQueueLimitExceededError => {
                 write!(f, "Error while adding entry to batch, queue limit exeeded.")
             }
@@ -297,6 +301,8 @@ impl fmt::Display for FeeEstimateError {
 pub enum VerifySignatureError {
     RecoverTypedDataError(SignatureError),
     EncodeError(Eip712Error),
+    MissingSignature,
+    ReceiptMismatch,
 }
 
 impl From<SignatureError> for VerifySignatureError {
@@ -318,6 +324,10 @@ impl fmt::Display for VerifySignatureError {
                 write!(f, "Recover typed data error: {}", e)
             }
             VerifySignatureError::EncodeError(e) => write!(f, "Encode error: {}", e),
+            VerifySignatureError::MissingSignature => write!(f, "Missing signature"),
+            VerifySignatureError::ReceiptMismatch => {
+                write!(f, "Receipt does not match the verification data")
+            }
         }
     }
 }
@@ -4,6 +4,7 @@ use std::fmt::Formatter;
 use std::str::FromStr;
 
 pub use ethers::core::k256::ecdsa::SigningKey;
+use ethers::signers::WalletError;
 pub use ethers::signers::{Signer, Wallet};
 use ethers::types::transaction::eip712::EIP712Domain;
 use ethers::types::transaction::eip712::Eip712;
@@ -12,6 +13,7 @@ use ethers::types::Address;
 use ethers::types::Signature;
 use ethers::types::H160;
 use ethers::types::U256;
+use ethers::utils::keccak256;
 use lambdaworks_crypto::merkle_tree::{
     merkle::MerkleTree, proof::Proof, traits::IsMerkleTreeBackend,
 };
@@ -37,7 +39,16 @@ use super::errors::VerifySignatureError;
 const NONCED_VERIFICATION_DATA_TYPE: &[u8] =
     b"NoncedVerificationData(bytes32 verification_data_hash,uint256 nonce,uint256 max_fee)";
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, Copy)]
+// Signed by the batcher when a proof is accepted into its queue.
+const PROOF_RECEIPT_TYPE: &[u8] =
+    b"ProofReceipt(bytes32 verification_data_hash,uint256 nonce,uint256 max_fee,uint256 block_received)";
+
+// Signed by the batcher when the batch including the proof is posted.
+// The leaf itself is not included since it is proven by the inclusion proof against the root.
+const BATCH_INCLUSION_DATA_TYPE: &[u8] =
+    b"BatchInclusionData(bytes32 batch_merkle_root,uint256 index_in_batch,uint256 user_nonce)";
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, Hash, Copy)]
 #[repr(u8)]
 pub enum ProvingSystemId {
     GnarkPlonkBls12_381,
@@ -47,21 +58,57 @@ pub enum ProvingSystemId {
     SP1,
     Risc0,
     CircomGroth16Bn256,
+    #[cfg(feature = "noir")]
+    NoirUltraHonk,
 }
 
 impl Display for ProvingSystemId {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl ProvingSystemId {
+    /// Proving systems supported by this build. A proving system is added here and named in
+    /// [`Self::name`], which the CLI accepts, and its verifier is registered in the batcher.
+    pub const ALL: &'static [ProvingSystemId] = &[
+        ProvingSystemId::GnarkPlonkBls12_381,
+        ProvingSystemId::GnarkPlonkBn254,
+        ProvingSystemId::GnarkGroth16Bn254,
+        ProvingSystemId::SP1,
+        ProvingSystemId::Risc0,
+        ProvingSystemId::CircomGroth16Bn256,
+        #[cfg(feature = "noir")]
+        ProvingSystemId::NoirUltraHonk,
+    ];
+
+    pub fn name(&self) -> &'static str {
         match self {
-            ProvingSystemId::GnarkPlonkBls12_381 => write!(f, "GnarkPlonkBls12_381"),
-            ProvingSystemId::GnarkPlonkBn254 => write!(f, "GnarkPlonkBn254"),
-            ProvingSystemId::GnarkGroth16Bn254 => write!(f, "GnarkGroth16Bn254"),
-            ProvingSystemId::SP1 => write!(f, "SP1"),
-            ProvingSystemId::Risc0 => write!(f, "Risc0"),
-            ProvingSystemId::CircomGroth16Bn256 => write!(f, "CircomGroth16Bn256"),
+            ProvingSystemId::GnarkPlonkBls12_381 => "GnarkPlonkBls12_381",
+            ProvingSystemId::GnarkPlonkBn254 => "GnarkPlonkBn254",
+            ProvingSystemId::GnarkGroth16Bn254 => "GnarkGroth16Bn254",
+            ProvingSystemId::SP1 => "SP1",
+            ProvingSystemId::Risc0 => "Risc0",
+            ProvingSystemId::CircomGroth16Bn256 => "CircomGroth16Bn256",
+            #[cfg(feature = "noir")]
+            ProvingSystemId::NoirUltraHonk => "NoirUltraHonk",
         }
     }
 }
 
+impl FromStr for ProvingSystemId {
+    type Err = String;
+
+    /// Parses the [`ProvingSystemId::name`] of a proving system.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|proving_system| proving_system.name() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown proving system: {}", s))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VerificationData {
     pub proving_system: ProvingSystemId,
@@ -145,6 +192,8 @@ impl From<VerificationData> for VerificationDataCommitment {
         // FIXME(marian): This should probably be reworked, for the moment when the proving
         // system is SP1 or Risc0, `proving_system_aux_data` stands for information related to the
         // compiled ELF, while in the rest of the proving systems, stands for the verification key.
+        // Noir UltraHonk proofs commit to the verification key as well, and to no public input
+        // when the circuit has none.
         let proving_system_byte = verification_data.proving_system as u8;
         let proving_system_aux_data_commitment =
             if let Some(vm_program_code) = &verification_data.vm_program_code {
@@ -211,6 +260,10 @@ pub struct BatchInclusionData {
     pub batch_inclusion_proof: Proof<[u8; 32]>,
     pub index_in_batch: usize,
     pub user_nonce: U256,
+    /// EIP-712 signature of the batcher over the inclusion data.
+    /// See [`BatchInclusionData::sign`].
+    #[serde(default)]
+    pub batcher_signature: Option<Signature>,
 }
 
 impl BatchInclusionData {
@@ -228,8 +281,189 @@ impl BatchInclusionData {
             batch_inclusion_proof,
             index_in_batch: verification_data_batch_index,
             user_nonce,
+            batcher_signature: None,
+        }
+    }
+
+    /// Signs the inclusion data with the batcher wallet.
+    /// `chain_id` and `payment_service_addr` are the ones of the submitted proof, and are part of the EIP-712 domain.
+    pub async fn sign(
+        &mut self,
+        wallet: &Wallet<SigningKey>,
+        chain_id: U256,
+        payment_service_addr: Address,
+    ) -> Result<(), WalletError> {
+        let typed_data = self.typed_data(chain_id, payment_service_addr);
+        self.batcher_signature = Some(wallet.sign_typed_data(&typed_data).await?);
+        Ok(())
+    }
+
+    /// Verifies the inclusion data was signed by `batcher_addr`.
+    /// Fails if the inclusion data is not signed.
+    pub fn verify_batcher_signature(
+        &self,
+        batcher_addr: Address,
+        chain_id: U256,
+        payment_service_addr: Address,
+    ) -> Result<(), VerifySignatureError> {
+        let Some(signature) = self.batcher_signature else {
+            return Err(VerifySignatureError::MissingSignature);
+        };
+        let hashed_data = self
+            .typed_data(chain_id, payment_service_addr)
+            .encode_eip712()?;
+        signature.verify(hashed_data, batcher_addr)?;
+        Ok(())
+    }
+
+    fn typed_data(&self, chain_id: U256, payment_service_addr: Address) -> BatchInclusionTypedData {
+        BatchInclusionTypedData {
+            batch_merkle_root: self.batch_merkle_root,
+            index_in_batch: U256::from(self.index_in_batch),
+            user_nonce: self.user_nonce,
+            chain_id,
+            payment_service_addr,
+        }
+    }
+}
+
+/// EIP-712 view of [`BatchInclusionData`], which carries the domain parameters
+/// that are not part of the inclusion data itself.
+struct BatchInclusionTypedData {
+    batch_merkle_root: [u8; 32],
+    index_in_batch: U256,
+    user_nonce: U256,
+    chain_id: U256,
+    payment_service_addr: Address,
+}
+
+impl Eip712 for BatchInclusionTypedData {
+    type Error = Eip712Error;
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(batcher_eip712_domain(
+            self.chain_id,
+            self.payment_service_addr,
+        ))
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(BATCH_INCLUSION_DATA_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let mut hasher = Keccak256::new();
+        hasher.update(Self::type_hash()?);
+        hasher.update(self.batch_merkle_root);
+        hasher.update(u256_to_be_bytes(self.index_in_batch));
+        hasher.update(u256_to_be_bytes(self.user_nonce));
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Acknowledgment sent by the batcher once a proof is accepted into its queue.
+/// Together with its signature, it is evidence that the batcher received the proof.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofReceipt {
+    /// Leaf of the proof in the batch merkle tree, see [`VerificationCommitmentBatch`].
+    pub verification_data_hash: [u8; 32],
+    pub nonce: U256,
+    pub max_fee: U256,
+    /// Block number last seen by the batcher when the proof was received.
+    pub block_received: u64,
+    pub chain_id: U256,
+    pub payment_service_addr: Address,
+}
+
+impl ProofReceipt {
+    pub fn new(nonced_verification_data: &NoncedVerificationData, block_received: u64) -> Self {
+        Self {
+            verification_data_hash: VerificationCommitmentBatch::hash_data(
+                &nonced_verification_data.verification_data.clone().into(),
+            ),
+            nonce: nonced_verification_data.nonce,
+            max_fee: nonced_verification_data.max_fee,
+            block_received,
+            chain_id: nonced_verification_data.chain_id,
+            payment_service_addr: nonced_verification_data.payment_service_addr,
         }
     }
+
+    /// Returns whether the receipt acknowledges the proof in `nonced_verification_data`.
+    /// The max fee is not compared, since the batcher applies its own to the proofs of
+    /// non-paying addresses.
+    pub fn refers_to(&self, nonced_verification_data: &NoncedVerificationData) -> bool {
+        let sent = Self::new(nonced_verification_data, self.block_received);
+        self.verification_data_hash == sent.verification_data_hash
+            && self.nonce == sent.nonce
+            && self.chain_id == sent.chain_id
+            && self.payment_service_addr == sent.payment_service_addr
+    }
+}
+
+impl Eip712 for ProofReceipt {
+    type Error = Eip712Error;
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(batcher_eip712_domain(
+            self.chain_id,
+            self.payment_service_addr,
+        ))
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(PROOF_RECEIPT_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let mut hasher = Keccak256::new();
+        hasher.update(Self::type_hash()?);
+        hasher.update(self.verification_data_hash);
+        hasher.update(u256_to_be_bytes(self.nonce));
+        hasher.update(u256_to_be_bytes(self.max_fee));
+        hasher.update(u256_to_be_bytes(U256::from(self.block_received)));
+        Ok(hasher.finalize().into())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedProofReceipt {
+    pub receipt: ProofReceipt,
+    pub signature: Signature,
+}
+
+impl SignedProofReceipt {
+    pub async fn new(
+        receipt: ProofReceipt,
+        wallet: &Wallet<SigningKey>,
+    ) -> Result<Self, WalletError> {
+        let signature = wallet.sign_typed_data(&receipt).await?;
+        Ok(Self { receipt, signature })
+    }
+
+    /// Verifies the receipt was signed by `batcher_addr`.
+    pub fn verify_signature(&self, batcher_addr: Address) -> Result<(), VerifySignatureError> {
+        let hashed_data = self.receipt.encode_eip712()?;
+        self.signature.verify(hashed_data, batcher_addr)?;
+        Ok(())
+    }
+}
+
+// Messages signed by the batcher use their own domain name, so they can't be mistaken
+// for messages signed by users, which use the "Aligned" domain.
+fn batcher_eip712_domain(chain_id: U256, payment_service_addr: Address) -> EIP712Domain {
+    EIP712Domain {
+        name: Some("AlignedBatcher".into()),
+        version: Some("1".into()),
+        chain_id: Some(chain_id),
+        verifying_contract: Some(payment_service_addr),
+        salt: None,
+    }
+}
+
+//EIP requires big endian for u256
+fn u256_to_be_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -343,6 +577,12 @@ pub struct AlignedVerificationData {
     pub batch_merkle_root: [u8; 32],
     pub batch_inclusion_proof: Proof<[u8; 32]>,
     pub index_in_batch: usize,
+    /// Receipt signed by the batcher when the proof was accepted into the queue.
+    #[serde(default)]
+    pub proof_receipt: Option<SignedProofReceipt>,
+    /// Signature of the batcher over the batch inclusion data.
+    #[serde(default)]
+    pub batcher_signature: Option<Signature>,
 }
 
 impl AlignedVerificationData {
@@ -359,15 +599,80 @@ impl AlignedVerificationData {
             batch_merkle_root,
             batch_inclusion_proof: batch_inclusion_proof.clone(),
             index_in_batch,
+            proof_receipt: None,
+            batcher_signature: inclusion_data.batcher_signature,
+        }
+    }
+    /// Verifies the proof receipt and the batch inclusion data were signed by `batcher_addr`,
+    /// and that the receipt refers to the proof in this verification data.
+    pub fn verify_batcher_signatures(
+        &self,
+        batcher_addr: Address,
+    ) -> Result<(), VerifySignatureError> {
+        let (Some(proof_receipt), Some(batcher_signature)) =
+            (&self.proof_receipt, self.batcher_signature)
+        else {
+            return Err(VerifySignatureError::MissingSignature);
+        };
+        proof_receipt.verify_signature(batcher_addr)?;
+
+        let receipt = &proof_receipt.receipt;
+        if receipt.verification_data_hash
+            != VerificationCommitmentBatch::hash_data(&self.verification_data_commitment)
+        {
+            return Err(VerifySignatureError::ReceiptMismatch);
         }
+
+        let hashed_data = BatchInclusionTypedData {
+            batch_merkle_root: self.batch_merkle_root,
+            index_in_batch: U256::from(self.index_in_batch),
+            user_nonce: receipt.nonce,
+            chain_id: receipt.chain_id,
+            payment_service_addr: receipt.payment_service_addr,
+        }
+        .encode_eip712()?;
+        batcher_signature.verify(hashed_data, batcher_addr)?;
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Fields of [`VerificationData`] that a proving system may require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationDataField {
+    Proof,
+    PubInput,
+    VerificationKey,
+    VmProgramCode,
+}
+
+impl Display for VerificationDataField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationDataField::Proof => write!(f, "proof"),
+            VerificationDataField::PubInput => write!(f, "public input"),
+            VerificationDataField::VerificationKey => write!(f, "verification key"),
+            VerificationDataField::VmProgramCode => write!(f, "vm program code"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProofInvalidReason {
     RejectedProof,
     VerifierNotSupported,
     DisabledVerifier(ProvingSystemId),
+    /// A field required by the proving system is missing or empty.
+    MissingField(ProvingSystemId, VerificationDataField),
+    /// A field the proving system doesn't use was sent, and it would change what the commitment binds.
+    UnexpectedField(ProvingSystemId, VerificationDataField),
+    /// The Risc0 image id is not 32 bytes long. Holds the received length.
+    MalformedImageId(usize),
+    /// The public values committed in the SP1 proof differ from the submitted public input.
+    PublicInputMismatch,
+    /// The proof can't be deserialized for the proving system.
+    UndeserializableProof(ProvingSystemId),
+    /// The Risc0 receipt is not composite, succinct or Groth16. Holds the receipt kind.
+    UnsupportedReceiptKind(String),
 }
 
 impl Display for ProofInvalidReason {
@@ -378,6 +683,27 @@ impl Display for ProofInvalidReason {
                 write!(f, "Disabled verifier: {}", proving_system_id)
             }
             ProofInvalidReason::RejectedProof => write!(f, "Proof did not verify"),
+            ProofInvalidReason::MissingField(proving_system_id, field) => {
+                write!(f, "Missing {} for {} proof", field, proving_system_id)
+            }
+            ProofInvalidReason::UnexpectedField(proving_system_id, field) => {
+                write!(f, "Unexpected {} for {} proof", field, proving_system_id)
+            }
+            ProofInvalidReason::MalformedImageId(len) => {
+                write!(f, "Malformed image id: expected 32 bytes, got {}", len)
+            }
+            ProofInvalidReason::PublicInputMismatch => {
+                write!(
+                    f,
+                    "Public input does not match the public values of the proof"
+                )
+            }
+            ProofInvalidReason::UndeserializableProof(proving_system_id) => {
+                write!(f, "Could not deserialize {} proof", proving_system_id)
+            }
+            ProofInvalidReason::UnsupportedReceiptKind(kind) => {
+                write!(f, "Unsupported Risc0 receipt kind: {}", kind)
+            }
         }
     }
 }
@@ -385,6 +711,7 @@ impl Display for ProofInvalidReason {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SubmitProofResponseMessage {
     BatchInclusionData(BatchInclusionData),
+    ProofQueued(SignedProofReceipt),
     ProtocolVersion(u16),
     CreateNewTaskError(String, String), //merkle-root, error
     InvalidProof(ProofInvalidReason),
@@ -480,6 +807,22 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::common::constants::DEFAULT_MAX_FEE_PER_PROOF;
+
+    #[test]
+    fn all_proving_systems_are_listed_and_parsed_from_their_name() {
+        for proving_system in ProvingSystemId::ALL {
+            assert_eq!(
+                ProvingSystemId::from_str(proving_system.name()),
+                Ok(*proving_system)
+            );
+        }
+        // Listed in the order of their ids, without gaps
+        for (id, proving_system) in ProvingSystemId::ALL.iter().enumerate() {
+            assert_eq!(*proving_system as usize, id);
+        }
+        assert!(ProvingSystemId::from_str("Groth16").is_err());
+    }
 
     #[tokio::test]
     async fn eip_712_recovers_same_address_as_signed() {
@@ -520,4 +863,187 @@ mod tests {
 
         assert_eq!(recovered_address, wallet.address())
     }
+
+    const BATCHER_PRIVATE_KEY: &str =
+        "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"; // Anvil address 0
+    const OTHER_PRIVATE_KEY: &str =
+        "2a871d0798f97d79848a013d4936a73bf4cc922c825d33c1cf7073dff6d409c6"; // Anvil address 9
+
+    fn nonced_verification_data_for_testing(
+        nonce: U256,
+        proof_generator_addr: Address,
+    ) -> NoncedVerificationData {
+        let verification_data = VerificationData {
+            proving_system: ProvingSystemId::GnarkGroth16Bn254,
+            // Each nonce gets a different proof so that commitments differ
+            proof: vec![nonce.low_u32() as u8; 4],
+            pub_input: Some([32, 32, 32, 32].to_vec()),
+            verification_key: Some([8, 8, 8, 8].to_vec()),
+            vm_program_code: None,
+            proof_generator_addr,
+        };
+        NoncedVerificationData::new(
+            verification_data,
+            nonce,
+            2.into(),
+            3.into(),
+            Address::repeat_byte(0x42),
+        )
+    }
+
+    #[tokio::test]
+    async fn proof_receipt_is_verified_against_batcher_address() {
+        let batcher_wallet = LocalWallet::from_str(BATCHER_PRIVATE_KEY).unwrap();
+        let other_wallet = LocalWallet::from_str(OTHER_PRIVATE_KEY).unwrap();
+
+        let nonced_verification_data =
+            nonced_verification_data_for_testing(1.into(), other_wallet.address());
+        let receipt = ProofReceipt::new(&nonced_verification_data, 100);
+        let signed_receipt = SignedProofReceipt::new(receipt, &batcher_wallet)
+            .await
+            .unwrap();
+
+        assert!(signed_receipt
+            .verify_signature(batcher_wallet.address())
+            .is_ok());
+        assert!(signed_receipt
+            .verify_signature(other_wallet.address())
+            .is_err());
+
+        let mut tampered_receipt = signed_receipt.clone();
+        tampered_receipt.receipt.block_received = 101;
+        assert!(tampered_receipt
+            .verify_signature(batcher_wallet.address())
+            .is_err());
+    }
+
+    #[test]
+    fn proof_receipt_of_non_paying_address_refers_to_sent_proof() {
+        let user_addr = LocalWallet::from_str(OTHER_PRIVATE_KEY).unwrap().address();
+        let sent_data = nonced_verification_data_for_testing(1.into(), user_addr);
+
+        // The batcher replaces the max fee of the proofs of non-paying addresses
+        let mut applied_data = sent_data.clone();
+        applied_data.max_fee = (DEFAULT_MAX_FEE_PER_PROOF * 100).into();
+        let receipt = ProofReceipt::new(&applied_data, 100);
+        assert!(receipt.refers_to(&sent_data));
+
+        let other_proof = nonced_verification_data_for_testing(2.into(), user_addr);
+        assert!(!receipt.refers_to(&other_proof));
+
+        let mut other_nonce = sent_data.clone();
+        other_nonce.nonce = 2.into();
+        assert!(!receipt.refers_to(&other_nonce));
+
+        let mut other_payment_service = sent_data;
+        other_payment_service.payment_service_addr = Address::repeat_byte(0x43);
+        assert!(!receipt.refers_to(&other_payment_service));
+    }
+
+    #[tokio::test]
+    async fn batch_inclusion_data_signatures_are_verified_against_batcher_address() {
+        let batcher_wallet = LocalWallet::from_str(BATCHER_PRIVATE_KEY).unwrap();
+        let user_addr = LocalWallet::from_str(OTHER_PRIVATE_KEY).unwrap().address();
+
+        let nonced_verification_data: Vec<_> = (0..3)
+            .map(|nonce| nonced_verification_data_for_testing(nonce.into(), user_addr))
+            .collect();
+        let commitments: Vec<VerificationDataCommitment> = nonced_verification_data
+            .iter()
+            .map(|data| data.verification_data.clone().into())
+            .collect();
+        let batch_merkle_tree =
+            MerkleTree::<VerificationCommitmentBatch>::build(&commitments).unwrap();
+
+        let index_in_batch = 1;
+        let sent_data = &nonced_verification_data[index_in_batch];
+        let mut batch_inclusion_data =
+            BatchInclusionData::new(index_in_batch, &batch_merkle_tree, sent_data.nonce);
+        batch_inclusion_data
+            .sign(
+                &batcher_wallet,
+                sent_data.chain_id,
+                sent_data.payment_service_addr,
+            )
+            .await
+            .unwrap();
+
+        assert!(batch_inclusion_data
+            .verify_batcher_signature(
+                batcher_wallet.address(),
+                sent_data.chain_id,
+                sent_data.payment_service_addr
+            )
+            .is_ok());
+        assert!(batch_inclusion_data
+            .verify_batcher_signature(
+                user_addr,
+                sent_data.chain_id,
+                sent_data.payment_service_addr
+            )
+            .is_err());
+
+        let mut aligned_verification_data =
+            AlignedVerificationData::new(&commitments[index_in_batch], &batch_inclusion_data);
+        // Without the receipt there is no evidence the batcher received the proof
+        assert!(aligned_verification_data
+            .verify_batcher_signatures(batcher_wallet.address())
+            .is_err());
+
+        aligned_verification_data.proof_receipt = Some(
+            SignedProofReceipt::new(ProofReceipt::new(sent_data, 100), &batcher_wallet)
+                .await
+                .unwrap(),
+        );
+        assert!(aligned_verification_data
+            .verify_batcher_signatures(batcher_wallet.address())
+            .is_ok());
+        assert!(aligned_verification_data
+            .verify_batcher_signatures(user_addr)
+            .is_err());
+
+        // A receipt for another proof must not be accepted
+        let mut mismatched_verification_data = aligned_verification_data.clone();
+        mismatched_verification_data.proof_receipt = Some(
+            SignedProofReceipt::new(
+                ProofReceipt::new(&nonced_verification_data[0], 100),
+                &batcher_wallet,
+            )
+            .await
+            .unwrap(),
+        );
+        assert!(mismatched_verification_data
+            .verify_batcher_signatures(batcher_wallet.address())
+            .is_err());
+    }
+
+    #[cfg(feature = "noir")]
+    #[test]
+    fn noir_commitment_binds_verification_key_and_proving_system() {
+        let verification_data = VerificationData {
+            proving_system: ProvingSystemId::NoirUltraHonk,
+            proof: vec![1, 2, 3],
+            pub_input: None,
+            verification_key: Some(vec![4, 5, 6]),
+            vm_program_code: None,
+            proof_generator_addr: Address::zero(),
+        };
+        let commitment = VerificationDataCommitment::from(verification_data.clone());
+        assert_eq!(commitment.pub_input_commitment, [0u8; 32]);
+
+        let mut other_vk = verification_data.clone();
+        other_vk.verification_key = Some(vec![7, 8, 9]);
+        assert_ne!(
+            commitment.proving_system_aux_data_commitment,
+            VerificationDataCommitment::from(other_vk).proving_system_aux_data_commitment
+        );
+
+        let mut other_proving_system = verification_data;
+        other_proving_system.proving_system = ProvingSystemId::GnarkGroth16Bn254;
+        assert_ne!(
+            commitment.proving_system_aux_data_commitment,
+            VerificationDataCommitment::from(other_proving_system)
+                .proving_system_aux_data_commitment
+        );
+    }
 }
@@ -2,6 +2,7 @@ use ethers::signers::Signer;
 use ethers::types::Address;
 use futures_util::{stream::SplitStream, SinkExt, StreamExt};
 use log::{debug, error, info, warn};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::{net::TcpStream, sync::Mutex};
 
@@ -10,14 +11,14 @@ use futures_util::future::Ready;
 use futures_util::stream::{SplitSink, TryFilter};
 use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
-use crate::common::types::{BatchInclusionData, SubmitProofMessage};
+use crate::common::types::{BatchInclusionData, SignedProofReceipt, SubmitProofMessage};
 use crate::communication::serialization::{cbor_deserialize, cbor_serialize};
 use crate::{
     common::{
         errors::SubmitError,
         types::{
             AlignedVerificationData, ClientMessage, NoncedVerificationData,
-            SubmitProofResponseMessage, VerificationData, VerificationDataCommitment,
+            SubmitProofResponseMessage, VerificationData,
         },
     },
     communication::batch::process_batcher_response,
@@ -87,19 +88,28 @@ pub async fn send_messages(
     sent_verification_data_rev
 }
 
+enum BatcherResponse {
+    ProofQueued(SignedProofReceipt),
+    BatchInclusionData(BatchInclusionData),
+}
+
 // Receives the array of proofs sent
 // Reads the WS responses
 // Matches each response with the corresponding proof sent
 // finishes when the last proof sent receives its response
 // finishes early if the batcher replies with a SubmitError
+// If `batcher_addr` is given, the receipts and inclusion data must be signed by it
 pub async fn receive(
     response_stream: Arc<Mutex<ResponseStream>>,
     mut sent_verification_data_rev: Vec<Result<NoncedVerificationData, SubmitError>>,
+    batcher_addr: Option<Address>,
 ) -> Vec<Result<AlignedVerificationData, SubmitError>> {
     // Responses are filtered to only admit binary or close messages.
     let mut response_stream = response_stream.lock().await;
     let mut aligned_submitted_data: Vec<Result<AlignedVerificationData, SubmitError>> = Vec::new();
     let last_proof_nonce = get_biggest_nonce(&sent_verification_data_rev);
+    // Receipts are received when proofs are queued, and are returned along the inclusion data
+    let mut proof_receipts: HashMap<U256, SignedProofReceipt> = HashMap::new();
 
     // read from WS
     while let Some(Ok(msg)) = response_stream.next().await {
@@ -121,7 +131,19 @@ pub async fn receive(
         // first error msg from batcher will drop the rest of the messages in the burst
 
         let batch_inclusion_data_message = match handle_batcher_response(msg).await {
-            Ok(data) => data,
+            Ok(BatcherResponse::ProofQueued(proof_receipt)) => {
+                if let Err(e) =
+                    check_proof_receipt(&proof_receipt, &sent_verification_data_rev, batcher_addr)
+                {
+                    warn!("Error while checking proof receipt: {:?}", e);
+                    aligned_submitted_data.push(Err(e));
+                    break;
+                }
+                debug!("Proof with nonce {} queued", proof_receipt.receipt.nonce);
+                proof_receipts.insert(proof_receipt.receipt.nonce, proof_receipt);
+                continue;
+            }
+            Ok(BatcherResponse::BatchInclusionData(data)) => data,
             Err(e) => {
                 warn!("Error while handling batcher response: {:?}", e);
                 aligned_submitted_data.push(Err(e));
@@ -144,9 +166,22 @@ pub async fn receive(
             }
         };
 
-        let aligned_verification_data = match process_batcher_response(
+        if let Some(batcher_addr) = batcher_addr {
+            if let Err(e) = batch_inclusion_data_message.verify_batcher_signature(
+                batcher_addr,
+                related_verification_data.chain_id,
+                related_verification_data.payment_service_addr,
+            ) {
+                warn!("Batch inclusion data is not signed by the batcher: {}", e);
+                aligned_submitted_data
+                    .push(Err(SubmitError::InvalidBatcherSignature(e.to_string())));
+                break;
+            }
+        }
+
+        let mut aligned_verification_data = match process_batcher_response(
             &batch_inclusion_data_message,
-            &related_verification_data,
+            &related_verification_data.verification_data.clone().into(),
         ) {
             Ok(data) => data,
             Err(e) => {
@@ -156,6 +191,16 @@ pub async fn receive(
             }
         };
 
+        aligned_verification_data.proof_receipt =
+            proof_receipts.remove(&batch_inclusion_data_message.user_nonce);
+        if batcher_addr.is_some() && aligned_verification_data.proof_receipt.is_none() {
+            warn!("Batcher did not send a receipt for the queued proof");
+            aligned_submitted_data.push(Err(SubmitError::InvalidBatcherSignature(
+                "missing proof receipt".to_string(),
+            )));
+            break;
+        }
+
         aligned_submitted_data.push(Ok(aligned_verification_data));
         debug!("Message response handled successfully");
 
@@ -167,12 +212,16 @@ pub async fn receive(
     aligned_submitted_data
 }
 
-async fn handle_batcher_response(msg: Message) -> Result<BatchInclusionData, SubmitError> {
+async fn handle_batcher_response(msg: Message) -> Result<BatcherResponse, SubmitError> {
     let data = msg.into_data();
     match cbor_deserialize(data.as_slice()) {
         Ok(SubmitProofResponseMessage::BatchInclusionData(batch_inclusion_data)) => {
             //OK case. Proofs was valid and it was included in this batch.
-            Ok(batch_inclusion_data)
+            Ok(BatcherResponse::BatchInclusionData(batch_inclusion_data))
+        }
+        Ok(SubmitProofResponseMessage::ProofQueued(proof_receipt)) => {
+            // Proof was valid and it was added to the queue, the inclusion data will follow.
+            Ok(BatcherResponse::ProofQueued(proof_receipt))
         }
         Ok(SubmitProofResponseMessage::InvalidNonce) => {
             error!("Batcher responded with invalid nonce. Funds have not been spent.");
@@ -285,7 +334,7 @@ async fn handle_batcher_response(msg: Message) -> Result<BatchInclusionData, Sub
 fn match_batcher_response_with_stored_verification_data(
     batch_inclusion_data: &BatchInclusionData,
     sent_verification_data_rev: &mut Vec<Result<NoncedVerificationData, SubmitError>>,
-) -> Result<VerificationDataCommitment, SubmitError> {
+) -> Result<NoncedVerificationData, SubmitError> {
     debug!("Matching verification data with batcher response ...");
     let mut index = None;
     for (i, sent_nonced_verification_data) in
@@ -303,13 +352,40 @@ fn match_batcher_response_with_stored_verification_data(
 
     // cant remove an element while iterating, so we remove it here
     if let Some(i) = index {
-        let verification_data = sent_verification_data_rev.remove(i).unwrap();
-        return Ok(verification_data.verification_data.clone().into());
+        return sent_verification_data_rev.remove(i);
     }
 
     Err(SubmitError::InvalidProofInclusionData)
 }
 
+// Checks the receipt sent by the batcher matches one of the proofs sent,
+// and that it was signed by the batcher if its address is known
+fn check_proof_receipt(
+    proof_receipt: &SignedProofReceipt,
+    sent_verification_data_rev: &[Result<NoncedVerificationData, SubmitError>],
+    batcher_addr: Option<Address>,
+) -> Result<(), SubmitError> {
+    let receipt = &proof_receipt.receipt;
+    let matches_sent_data = sent_verification_data_rev
+        .iter()
+        .flatten()
+        .any(|sent| receipt.refers_to(sent));
+    if !matches_sent_data {
+        return Err(SubmitError::UnexpectedBatcherResponse(format!(
+            "Batcher sent a receipt for a proof that was not sent, nonce: {}",
+            receipt.nonce
+        )));
+    }
+
+    if let Some(batcher_addr) = batcher_addr {
+        proof_receipt
+            .verify_signature(batcher_addr)
+            .map_err(|e| SubmitError::InvalidBatcherSignature(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
 // Returns the biggest nonce from the sent verification data
 // Used to know which is the last proof sent to the Batcher,
 // to know when to stop reading the WS for responses
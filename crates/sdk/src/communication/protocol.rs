@@ -6,7 +6,11 @@ use crate::common::{errors::SubmitError, types::SubmitProofResponseMessage};
 
 use super::serialization::cbor_deserialize;
 
-pub const EXPECTED_PROTOCOL_VERSION: u16 = 4;
+/// Version of the messages exchanged with the batcher. It must be increased whenever a message
+/// older clients can't deserialize is added, so they fail before submitting their proofs.
+/// Version 5 added the `ProofQueued` receipt, sent before the batch inclusion data, and rejects
+/// the vm program code of proofs of proving systems verified with a verification key.
+pub const EXPECTED_PROTOCOL_VERSION: u16 = 5;
 
 pub async fn check_protocol_version(
     ws_read: &mut SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
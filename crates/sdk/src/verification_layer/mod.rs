@@ -34,7 +34,10 @@ use ethers::{
 use sha3::{Digest, Keccak256};
 use std::sync::Arc;
 use tokio::{net::TcpStream, sync::Mutex};
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{
+    connect_async, connect_async_tls_with_config, tungstenite::Message, Connector, MaybeTlsStream,
+    WebSocketStream,
+};
 
 use log::{debug, info};
 
@@ -46,7 +49,7 @@ use futures_util::{
 use serde_json::json;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Submits multiple proofs to the batcher to be verified in Aligned and waits for the verification on-chain.
 ///
@@ -79,6 +82,9 @@ use std::path::PathBuf;
 /// * `InsufficientBalance` if the sender balance is insufficient or unlocked
 /// * `ProofQueueFlushed` if there is an error in the batcher and the proof queue is flushed.
 /// * `GenericError` if the error doesn't match any of the previous ones.
+///
+/// The signatures of the batcher responses are not verified since the batcher address is unknown.
+/// See [`submit_multiple_with_batcher_address`].
 #[allow(clippy::too_many_arguments)] // TODO: Refactor this function, use NoncedVerificationData
 pub async fn submit_multiple_and_wait_verification(
     eth_rpc_url: &str,
@@ -229,12 +235,63 @@ async fn fetch_gas_price(
 /// * `InsufficientBalance` if the sender balance is insufficient or unlocked.
 /// * `ProofQueueFlushed` if there is an error in the batcher and the proof queue is flushed.
 /// * `GenericError` if the error doesn't match any of the previous ones.
+///
+/// The signatures of the batcher responses are not verified since the batcher address is unknown.
+/// See [`submit_multiple_with_batcher_address`].
 pub async fn submit_multiple(
     network: Network,
     verification_data: &[VerificationData],
     max_fee: U256,
     wallet: Wallet<SigningKey>,
     nonce: U256,
+) -> Vec<Result<AlignedVerificationData, errors::SubmitError>> {
+    connect_and_submit_multiple(network, verification_data, max_fee, wallet, nonce, None).await
+}
+
+/// Submits multiple proofs to the batcher like [`submit_multiple`], and checks its responses
+/// are signed by `batcher_addr`. The returned data then holds the signed receipts of the proofs,
+/// see [`AlignedVerificationData::verify_batcher_signatures`].
+///
+/// # Arguments
+/// * `network` - The network on which the proofs will be submitted.
+/// * `verification_data` - An array of verification data of each proof.
+/// * `max_fee` - The maximum fee that the submitter is willing to pay for each proof verification.
+/// * `wallet` - The wallet used to sign the proof.
+/// * `nonce` - The nonce of the submitter address. See [`get_nonce_from_ethereum`] or [`get_nonce_from_batcher`].
+/// * `batcher_addr` - The address of the batcher wallet. See [`get_batcher_address`].
+///
+/// # Returns
+/// * An array of aligned verification data obtained when submitting the proof.
+///
+/// # Errors
+/// * The errors of [`submit_multiple`].
+/// * `InvalidBatcherSignature` if the batcher responses are not signed by `batcher_addr`.
+pub async fn submit_multiple_with_batcher_address(
+    network: Network,
+    verification_data: &[VerificationData],
+    max_fee: U256,
+    wallet: Wallet<SigningKey>,
+    nonce: U256,
+    batcher_addr: Address,
+) -> Vec<Result<AlignedVerificationData, errors::SubmitError>> {
+    connect_and_submit_multiple(
+        network,
+        verification_data,
+        max_fee,
+        wallet,
+        nonce,
+        Some(batcher_addr),
+    )
+    .await
+}
+
+async fn connect_and_submit_multiple(
+    network: Network,
+    verification_data: &[VerificationData],
+    max_fee: U256,
+    wallet: Wallet<SigningKey>,
+    nonce: U256,
+    batcher_addr: Option<Address>,
 ) -> Vec<Result<AlignedVerificationData, errors::SubmitError>> {
     let (ws_stream, _) = match connect_async(network.get_batcher_url()).await {
         Ok((ws_stream, response)) => (ws_stream, response),
@@ -254,12 +311,14 @@ pub async fn submit_multiple(
         max_fee,
         wallet,
         nonce,
+        batcher_addr,
     )
     .await
 }
 
 // Will submit the proofs to the batcher and wait for their responses
 // Will return once all proofs are responded, or up to a proof that is responded with an error
+#[allow(clippy::too_many_arguments)]
 async fn _submit_multiple(
     ws_write: Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>,
     mut ws_read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
@@ -268,6 +327,7 @@ async fn _submit_multiple(
     max_fee: U256,
     wallet: Wallet<SigningKey>,
     nonce: U256,
+    batcher_addr: Option<Address>,
 ) -> Vec<Result<AlignedVerificationData, errors::SubmitError>> {
     // First message from the batcher is the protocol version
     if let Err(e) = check_protocol_version(&mut ws_read).await {
@@ -305,7 +365,7 @@ async fn _submit_multiple(
             nonce,
         )
         .await;
-        receive(response_stream, sent_verification_data_rev).await
+        receive(response_stream, sent_verification_data_rev, batcher_addr).await
     }
     .await;
 
@@ -539,6 +599,60 @@ pub async fn get_nonce_from_batcher(
             GetNonceError::ConnectionFailed("Ws connection to batcher failed".to_string())
         })?;
 
+    request_nonce_from_batcher(ws_stream, address).await
+}
+
+/// Returns the next nonce for a given address from a batcher serving a certificate signed by the CA
+/// in `ca_cert_path`, such as a self-hosted batcher with a self-signed certificate.
+/// The certificates trusted by the system are accepted as well. See [`get_nonce_from_batcher`].
+///
+/// # Arguments
+/// * `network` - The network from which the nonce will be retrieved.
+/// * `address` - The user address for which the nonce will be retrieved.
+/// * `ca_cert_path` - The path to the PEM encoded certificate of the CA.
+///
+/// # Returns
+/// * The next nonce of the proof submitter account.
+///
+/// # Errors
+/// * `ConnectionFailed` if the CA certificate can't be loaded.
+/// * `EthRpcError` if the batcher has an error in the Ethereum call when retrieving the nonce if not already cached.
+pub async fn get_nonce_from_batcher_with_ca_cert(
+    network: Network,
+    address: Address,
+    ca_cert_path: &Path,
+) -> Result<U256, GetNonceError> {
+    let ca_cert_pem = std::fs::read(ca_cert_path).map_err(|e| {
+        GetNonceError::ConnectionFailed(format!(
+            "Failed to read CA certificate {}: {}",
+            ca_cert_path.display(),
+            e
+        ))
+    })?;
+    let ca_cert = native_tls::Certificate::from_pem(&ca_cert_pem).map_err(|e| {
+        GetNonceError::ConnectionFailed(format!("Failed to parse CA certificate: {}", e))
+    })?;
+    let tls_connector = native_tls::TlsConnector::builder()
+        .add_root_certificate(ca_cert)
+        .build()
+        .map_err(|e| GetNonceError::ConnectionFailed(e.to_string()))?;
+
+    let (ws_stream, _) = connect_async_tls_with_config(
+        network.get_batcher_url(),
+        None,
+        false,
+        Some(Connector::NativeTls(tls_connector)),
+    )
+    .await
+    .map_err(|_| GetNonceError::ConnectionFailed("Ws connection to batcher failed".to_string()))?;
+
+    request_nonce_from_batcher(ws_stream, address).await
+}
+
+async fn request_nonce_from_batcher(
+    ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    address: Address,
+) -> Result<U256, GetNonceError> {
     debug!("WebSocket handshake has been successfully completed");
     let (mut ws_write, mut ws_read) = ws_stream.split();
     check_protocol_version(&mut ws_read)
@@ -626,6 +740,37 @@ pub async fn get_nonce_from_ethereum(
     }
 }
 
+/// Returns the address of the batcher wallet registered in the aligned payment service contract.
+/// Responses of the batcher are signed with this address, see [`AlignedVerificationData::verify_batcher_signatures`].
+///
+/// # Arguments
+/// * `eth_rpc_url` - The URL of the Ethereum RPC node.
+/// * `network` - The network from which the batcher address will be retrieved.
+///
+/// # Returns
+/// * The address of the batcher wallet.
+///
+/// # Errors
+/// * `EthereumProviderError` if there is an error in the connection with the RPC provider.
+/// * `EthereumCallError` if there is an error in the Ethereum call.
+/// * `EthereumNotAContract` if the payment service address does not contain a contract.
+pub async fn get_batcher_address(
+    eth_rpc_url: &str,
+    network: Network,
+) -> Result<Address, errors::VerificationError> {
+    let eth_rpc_provider = Provider::<Http>::try_from(eth_rpc_url)
+        .map_err(|e| errors::VerificationError::EthereumProviderError(e.to_string()))?;
+
+    let payment_service_address = network.get_batcher_payment_service_address();
+    let contract = batcher_payment_service(eth_rpc_provider, payment_service_address).await?;
+
+    contract
+        .batcher_wallet()
+        .call()
+        .await
+        .map_err(|e| errors::VerificationError::EthereumCallError(e.to_string()))
+}
+
 /// Returns the chain ID of the Ethereum network.
 ///
 /// # Arguments
@@ -1,4 +1,4 @@
-use aligned_sdk::common::types::{Network, ProvingSystemId, VerificationData};
+use aligned_sdk::common::types::{Network, VerificationData};
 use aligned_sdk::verification_layer::{
     deposit_to_aligned, get_nonce_from_batcher, submit_multiple,
 };
@@ -11,6 +11,7 @@ use rand::thread_rng;
 use std::fs::{self, File};
 use std::io::ErrorKind;
 use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str::FromStr;
 use std::thread;
@@ -25,18 +26,22 @@ use crate::structs::{
 
 const GROTH_16_PROOF_GENERATOR_FILE_PATH: &str =
     "../../scripts/test_files/gnark_groth16_bn254_infinite_script/cmd/main.go";
+#[cfg(feature = "noir")]
+const NOIR_ULTRA_HONK_PROOF_GENERATOR_FILE_PATH: &str =
+    "../../scripts/test_files/noir_ultra_honk_script/generate_proof.sh";
 pub async fn generate_proofs(args: GenerateProofsArgs) {
     std::fs::create_dir_all(args.dir_to_save_proofs.clone()).expect("Could not create directory");
 
     let mut handles = vec![];
     for i in 1..args.number_of_proofs + 1 {
         let dir_to_save_proofs = args.dir_to_save_proofs.clone();
+        let proof_type = args.proof_type.clone();
 
         let handle = thread::spawn(move || {
-            match args.proof_type {
+            match proof_type {
                 ProofType::Groth16 => {
                     let dir_to_save_proofs =
-                        format!("{}/groth16_{}/", dir_to_save_proofs.clone(), i);
+                        format!("{}/{}_{}/", dir_to_save_proofs, proof_type.dir_prefix(), i);
 
                     // we need to create the directory as the go script does not handle it
                     if let Err(e) = fs::create_dir(dir_to_save_proofs.clone()) {
@@ -54,6 +59,19 @@ pub async fn generate_proofs(args: GenerateProofsArgs) {
                         .status()
                         .unwrap();
                 }
+                #[cfg(feature = "noir")]
+                ProofType::NoirUltraHonk => {
+                    let dir_to_save_proofs =
+                        format!("{}/{}_{}/", dir_to_save_proofs, proof_type.dir_prefix(), i);
+
+                    // The script writes the proof, public inputs and vk files to the directory
+                    Command::new("bash")
+                        .arg(NOIR_ULTRA_HONK_PROOF_GENERATOR_FILE_PATH)
+                        .arg(format!("{:?}", i))
+                        .arg(dir_to_save_proofs)
+                        .status()
+                        .unwrap();
+                }
             }
         });
         handles.push(handle);
@@ -398,53 +416,77 @@ fn get_verification_data_from_proofs_folder(
     let dir = std::fs::read_dir(dir_path).expect("Directory does not exists");
 
     for proof_folder in dir {
-        // each proof_folder is a dir called groth16_n
+        // each proof_folder is a dir called <proof type prefix>_n, e.g. groth16_n
         let proof_folder_dir = proof_folder.unwrap().path();
-        if proof_folder_dir.is_dir() {
-            // todo(marcos): this should be improved if we want to support more proofs
-            // currently we stored the proofs on subdirs with a prefix for the proof type
-            // and here we check the subdir name and based on build the verification data accordingly
-            if proof_folder_dir.to_str().unwrap().contains("groth16") {
-                // Get the first file from the folder
-                let first_file = fs::read_dir(proof_folder_dir.clone())
-                    .expect("Can't read proofs directory")
-                    .filter_map(|entry| entry.ok().map(|e| e.path()))
-                    .find(|path| path.is_file()) // Find any valid file
-                    .expect("No valid proof files found");
-
-                // Extract the base name (file stem) without extension
-                let base_name = first_file
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .expect("Failed to extract base name");
-
-                // Generate the paths for the other files
-                let proof_path = proof_folder_dir.join(format!("{}.proof", base_name));
-                let public_input_path = proof_folder_dir.join(format!("{}.pub", base_name));
-                let vk_path = proof_folder_dir.join(format!("{}.vk", base_name));
-
-                let Ok(proof) = std::fs::read(&proof_path) else {
-                    continue;
-                };
-                let Ok(public_input) = std::fs::read(&public_input_path) else {
-                    continue;
-                };
-                let Ok(vk) = std::fs::read(&vk_path) else {
-                    continue;
-                };
-
-                let verification_data = VerificationData {
-                    proving_system: ProvingSystemId::GnarkGroth16Bn254,
-                    proof,
-                    pub_input: Some(public_input),
-                    verification_key: Some(vk),
-                    vm_program_code: None,
-                    proof_generator_addr: default_addr,
-                };
-                verifications_data.push(verification_data);
-            }
+        if !proof_folder_dir.is_dir() {
+            continue;
         }
+        let Some(proof_type) = proof_folder_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(ProofType::from_dir_name)
+        else {
+            continue;
+        };
+
+        let (proof_path, public_input_path, vk_path) =
+            proof_file_paths(&proof_type, &proof_folder_dir);
+        let Ok(proof) = std::fs::read(&proof_path) else {
+            continue;
+        };
+        let Ok(public_input) = std::fs::read(&public_input_path) else {
+            continue;
+        };
+        let Ok(vk) = std::fs::read(&vk_path) else {
+            continue;
+        };
+
+        let verification_data = VerificationData {
+            proving_system: proof_type.proving_system(),
+            proof,
+            pub_input: Some(public_input),
+            verification_key: Some(vk),
+            vm_program_code: None,
+            proof_generator_addr: default_addr,
+        };
+        verifications_data.push(verification_data);
     }
 
     verifications_data
 }
+
+/// Returns the paths of the proof, public input and verification key files written by the
+/// generator of the proof type
+fn proof_file_paths(
+    proof_type: &ProofType,
+    proof_folder_dir: &Path,
+) -> (PathBuf, PathBuf, PathBuf) {
+    match proof_type {
+        ProofType::Groth16 => {
+            // Get the first file from the folder
+            let first_file = fs::read_dir(proof_folder_dir)
+                .expect("Can't read proofs directory")
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .find(|path| path.is_file()) // Find any valid file
+                .expect("No valid proof files found");
+
+            // Extract the base name (file stem) without extension
+            let base_name = first_file
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("Failed to extract base name");
+
+            (
+                proof_folder_dir.join(format!("{}.proof", base_name)),
+                proof_folder_dir.join(format!("{}.pub", base_name)),
+                proof_folder_dir.join(format!("{}.vk", base_name)),
+            )
+        }
+        #[cfg(feature = "noir")]
+        ProofType::NoirUltraHonk => (
+            proof_folder_dir.join("proof"),
+            proof_folder_dir.join("public_inputs"),
+            proof_folder_dir.join("vk"),
+        ),
+    }
+}
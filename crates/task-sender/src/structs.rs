@@ -1,4 +1,4 @@
-use aligned_sdk::common::types::Network;
+use aligned_sdk::common::types::{Network, ProvingSystemId};
 use clap::Parser;
 use clap::Subcommand;
 use clap::ValueEnum;
@@ -40,6 +40,35 @@ pub struct GenerateProofsArgs {
 #[derive(Parser, Clone, Debug, ValueEnum)]
 pub enum ProofType {
     Groth16,
+    #[cfg(feature = "noir")]
+    NoirUltraHonk,
+}
+
+impl ProofType {
+    pub fn proving_system(&self) -> ProvingSystemId {
+        match self {
+            ProofType::Groth16 => ProvingSystemId::GnarkGroth16Bn254,
+            #[cfg(feature = "noir")]
+            ProofType::NoirUltraHonk => ProvingSystemId::NoirUltraHonk,
+        }
+    }
+
+    /// Prefix of the directories the proofs are saved in, followed by the proof number.
+    pub fn dir_prefix(&self) -> &'static str {
+        match self {
+            ProofType::Groth16 => "groth16",
+            #[cfg(feature = "noir")]
+            ProofType::NoirUltraHonk => "noir_ultra_honk",
+        }
+    }
+
+    /// Returns the proof type of a directory the proofs are saved in.
+    pub fn from_dir_name(dir_name: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .find(|proof_type| dir_name.starts_with(&format!("{}_", proof_type.dir_prefix())))
+            .cloned()
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -0,0 +1,24 @@
+#!/bin/bash
+
+# Generates an UltraHonk proof of the circuit in this directory.
+# Usage: ./generate_proof.sh <x> <output_dir>
+# Writes the `proof`, `public_inputs` and `vk` files to the output directory.
+set -e
+
+X=${1:-3}
+OUTPUT_DIR=${2:-.}
+SCRIPT_DIR=$(cd "$(dirname "$0")" && pwd)
+
+# Each proof is generated in its own copy of the circuit, so several can run in parallel
+WORK_DIR=$(mktemp -d)
+trap 'rm -rf "$WORK_DIR"' EXIT
+cp -r "$SCRIPT_DIR/Nargo.toml" "$SCRIPT_DIR/src" "$WORK_DIR"
+printf 'x = "%s"\ny = "%s"\n' "$X" "$((X * X))" > "$WORK_DIR/Prover.toml"
+
+(cd "$WORK_DIR" && nargo execute witness)
+mkdir -p "$OUTPUT_DIR"
+bb prove --scheme ultra_honk \
+    -b "$WORK_DIR/target/noir_ultra_honk.json" \
+    -w "$WORK_DIR/target/witness.gz" \
+    -o "$OUTPUT_DIR" \
+    --write_vk